@@ -0,0 +1,29 @@
+use crate::lod::ParkedCursor;
+use bevy::{ecs::system::SystemParam, prelude::*};
+use simula_behavior::prelude::*;
+
+/// Stops a running child and everything under it, so its parent can delegate elsewhere.
+/// Nodes lose their cursor and state, and start over when delegated to again.
+#[derive(SystemParam)]
+pub struct SubtreeAbort<'w, 's> {
+    children: Query<'w, 's, &'static BehaviorChildren>,
+}
+
+impl<'w, 's> SubtreeAbort<'w, 's> {
+    pub fn abort(&self, commands: &mut Commands, root: Entity) {
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            commands.entity(node).remove::<(
+                BehaviorCursor,
+                ParkedCursor,
+                BehaviorStarted,
+                BehaviorRunning,
+                BehaviorSuccess,
+                BehaviorFailure,
+            )>();
+            if let Ok(children) = self.children.get(node) {
+                stack.extend(children.iter().copied());
+            }
+        }
+    }
+}
@@ -1,4 +1,11 @@
-use super::npc::NPCBehavior;
+use super::{
+    ecosystem::{Census, Cull, Seed},
+    export::Export,
+    invoke::Invoke,
+    npc::NPCBehavior,
+    population::Population,
+    utility::UtilitySelector,
+};
 use bevy::{ecs::system::EntityCommands, prelude::*, reflect::TypeUuid};
 use serde::{Deserialize, Serialize};
 use simula_behavior::prelude::*;
@@ -35,6 +42,13 @@ pub enum BiomaBehavior {
     Delay(Delay),
     Guard(Guard),
     Timeout(Timeout),
+    UtilitySelector(UtilitySelector),
+    Population(Population),
+    Invoke(Invoke),
+    Census(Census),
+    Cull(Cull),
+    Seed(Seed),
+    Export(Export),
 
     Subtree(Subtree<BiomaBehavior>),
     NPC(Subtree<NPCBehavior>),
@@ -71,6 +85,13 @@ impl BehaviorInspectable for BiomaBehavior {
             BiomaBehavior::Delay(_) => Color::hex("#440").unwrap(),
             BiomaBehavior::Guard(_) => Color::hex("#440").unwrap(),
             BiomaBehavior::Timeout(_) => Color::hex("#440").unwrap(),
+            BiomaBehavior::UtilitySelector(_) => Color::hex("#522").unwrap(),
+            BiomaBehavior::Population(_) => Color::hex("#AA5500").unwrap(),
+            BiomaBehavior::Invoke(_) => Color::hex("#440").unwrap(),
+            BiomaBehavior::Census(_) => Color::hex("#AA5500").unwrap(),
+            BiomaBehavior::Cull(_) => Color::hex("#AA5500").unwrap(),
+            BiomaBehavior::Seed(_) => Color::hex("#AA5500").unwrap(),
+            BiomaBehavior::Export(_) => Color::hex("#AA5500").unwrap(),
             BiomaBehavior::Subtree(_) => Color::hex("#440").unwrap(),
             BiomaBehavior::NPC(_) => Color::hex("#440").unwrap(),
         }
@@ -91,6 +112,13 @@ impl BehaviorInspectable for BiomaBehavior {
             BiomaBehavior::Delay(_) => vec![<Delay as BehaviorSpec>::TYPE.as_ref()],
             BiomaBehavior::Guard(_) => vec![<Guard as BehaviorSpec>::TYPE.as_ref()],
             BiomaBehavior::Timeout(_) => vec![<Timeout as BehaviorSpec>::TYPE.as_ref()],
+            BiomaBehavior::UtilitySelector(_) => vec![<UtilitySelector as BehaviorSpec>::TYPE.as_ref()],
+            BiomaBehavior::Population(_) => vec![<Population as BehaviorSpec>::TYPE.as_ref(), "Bioma"],
+            BiomaBehavior::Invoke(_) => vec![<Invoke as BehaviorSpec>::TYPE.as_ref(), "Bioma"],
+            BiomaBehavior::Census(_) => vec![<Census as BehaviorSpec>::TYPE.as_ref(), "Bioma"],
+            BiomaBehavior::Cull(_) => vec![<Cull as BehaviorSpec>::TYPE.as_ref(), "Bioma"],
+            BiomaBehavior::Seed(_) => vec![<Seed as BehaviorSpec>::TYPE.as_ref(), "Bioma"],
+            BiomaBehavior::Export(_) => vec![<Export as BehaviorSpec>::TYPE.as_ref(), "Bioma"],
             BiomaBehavior::Subtree(_) => vec![<Subtree<BiomaBehavior> as BehaviorSpec>::TYPE.as_ref()],
             BiomaBehavior::NPC(_) => vec![<Subtree<NPCBehavior> as BehaviorSpec>::TYPE.as_ref()],
         }
@@ -0,0 +1,222 @@
+use bevy::{asset::HandleId, prelude::*, utils::HashMap};
+use rhai::{Dynamic, Engine, Map};
+use simula_script::ScriptContext;
+
+pub struct BlackboardPlugin;
+
+impl Plugin for BlackboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BlackboardWrite>()
+            .add_event::<BlackboardCopy>()
+            .init_resource::<ScriptFunctions>()
+            .add_system(register.in_base_set(CoreSet::PostUpdate))
+            .add_system(copy.in_base_set(CoreSet::PostUpdate).after(register))
+            .add_system(apply.in_base_set(CoreSet::PostUpdate).after(copy))
+            .add_system(facts.in_base_set(CoreSet::PostUpdate).after(apply));
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum BlackboardKey {
+    /// Entry of the `blackboard` map
+    Blackboard(String),
+    /// Variable in the script scope, like `memory`
+    Variable(String),
+}
+
+/// Request to set a value in a behavior tree blackboard.
+///
+/// Writes are queued and applied once per frame, so systems that already
+/// borrow script scopes through `ScriptQueries` can still update blackboards.
+/// Writes to a tree whose script context is not loaded yet wait for it, up to `PENDING_FRAMES`.
+#[derive(Debug, Clone)]
+pub struct BlackboardWrite {
+    pub tree: Entity,
+    pub key: BlackboardKey,
+    pub value: Dynamic,
+}
+
+impl BlackboardWrite {
+    pub fn new(tree: Entity, key: impl Into<String>, value: Dynamic) -> Self {
+        Self {
+            tree,
+            key: BlackboardKey::Blackboard(key.into()),
+            value,
+        }
+    }
+
+    pub fn variable(tree: Entity, name: impl Into<String>, value: Dynamic) -> Self {
+        Self {
+            tree,
+            key: BlackboardKey::Variable(name.into()),
+            value,
+        }
+    }
+}
+
+/// Request to copy blackboard entries from one tree to another, as `(from, to)` key pairs
+#[derive(Debug, Clone)]
+pub struct BlackboardCopy {
+    pub from: Entity,
+    pub to: Entity,
+    pub keys: Vec<(String, String)>,
+    /// Despawn the source tree once copied
+    pub despawn: bool,
+}
+
+/// Boolean entries of a tree blackboard, copied every frame so nodes can read
+/// them without borrowing script scopes
+#[derive(Default, Debug, Component, Clone, Deref)]
+pub struct BlackboardFacts(pub HashMap<String, bool>);
+
+/// Native functions made available to every behavior tree script engine
+#[derive(Default, Resource)]
+pub struct ScriptFunctions(Vec<fn(&mut Engine)>);
+
+pub trait ScriptFunctionsAppExt {
+    fn add_script_functions(&mut self, register: fn(&mut Engine)) -> &mut Self;
+}
+
+impl ScriptFunctionsAppExt for App {
+    fn add_script_functions(&mut self, register: fn(&mut Engine)) -> &mut Self {
+        self.init_resource::<ScriptFunctions>();
+        self.world
+            .resource_mut::<ScriptFunctions>()
+            .0
+            .push(register);
+        self
+    }
+}
+
+/// Script context of a tree that got the `ScriptFunctions`
+#[derive(Component)]
+pub struct ScriptFunctionsRegistered(HandleId);
+
+fn register(
+    mut commands: Commands,
+    functions: Res<ScriptFunctions>,
+    trees: Query<(
+        Entity,
+        &Handle<ScriptContext>,
+        Option<&ScriptFunctionsRegistered>,
+    )>,
+    mut contexts: ResMut<Assets<ScriptContext>>,
+) {
+    for (tree, handle, registered) in &trees {
+        // a replaced context needs them again
+        if registered.map_or(false, |registered| registered.0 == handle.id()) {
+            continue;
+        }
+        if let Some(context) = contexts.get_mut(handle) {
+            for register in &functions.0 {
+                register(&mut context.engine);
+            }
+            commands
+                .entity(tree)
+                .insert(ScriptFunctionsRegistered(handle.id()));
+        }
+    }
+}
+
+fn copy(
+    mut commands: Commands,
+    mut copies: EventReader<BlackboardCopy>,
+    trees: Query<&Handle<ScriptContext>>,
+    contexts: Res<Assets<ScriptContext>>,
+    mut writes: EventWriter<BlackboardWrite>,
+) {
+    for copy in copies.iter() {
+        let blackboard = trees
+            .get(copy.from)
+            .ok()
+            .and_then(|context| contexts.get(context))
+            .and_then(|context| context.scope.get_value::<Map>("blackboard"))
+            .unwrap_or_default();
+        for (from, to) in &copy.keys {
+            if let Some(value) = blackboard.get(from.as_str()) {
+                writes.send(BlackboardWrite::new(copy.to, to.clone(), value.clone()));
+            }
+        }
+        if copy.despawn {
+            if let Some(entity) = commands.get_entity(copy.from) {
+                entity.despawn_recursive();
+            }
+        }
+    }
+}
+
+/// Frames a write waits for the script context of its tree before it is dropped
+const PENDING_FRAMES: u32 = 600;
+
+fn apply(
+    mut writes: EventReader<BlackboardWrite>,
+    trees: Query<Option<&Handle<ScriptContext>>>,
+    mut contexts: ResMut<Assets<ScriptContext>>,
+    mut pending: Local<Vec<(BlackboardWrite, u32)>>,
+) {
+    let queued: Vec<(BlackboardWrite, u32)> = pending
+        .drain(..)
+        .chain(writes.iter().map(|write| (write.clone(), 0)))
+        .collect();
+    for (write, waited) in queued {
+        // tree is gone
+        let Ok(context) = trees.get(write.tree) else {
+            continue;
+        };
+        // tree script context is not ready yet
+        let Some(context) = context.and_then(|context| contexts.get_mut(context)) else {
+            if waited < PENDING_FRAMES {
+                pending.push((write, waited + 1));
+            } else {
+                warn!(
+                    "Blackboard: no script context on {:?}, dropped write",
+                    write.tree
+                );
+            }
+            continue;
+        };
+        match &write.key {
+            BlackboardKey::Blackboard(key) => {
+                let mut blackboard = context
+                    .scope
+                    .get_value::<Map>("blackboard")
+                    .unwrap_or_default();
+                blackboard.insert(key.as_str().into(), write.value.clone());
+                context.scope.set_value("blackboard", blackboard);
+            }
+            BlackboardKey::Variable(name) => {
+                context.scope.set_value(name.clone(), write.value.clone());
+            }
+        }
+    }
+}
+
+fn facts(
+    mut commands: Commands,
+    mut trees: Query<(Entity, &Handle<ScriptContext>, Option<&mut BlackboardFacts>)>,
+    contexts: Res<Assets<ScriptContext>>,
+) {
+    for (tree, context, facts) in &mut trees {
+        let Some(context) = contexts.get(context) else {
+            continue;
+        };
+        let blackboard = context
+            .scope
+            .get_value::<Map>("blackboard")
+            .unwrap_or_default();
+        let values: HashMap<String, bool> = blackboard
+            .iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value.clone().try_cast::<bool>()?)))
+            .collect();
+        match facts {
+            Some(mut facts) => {
+                if facts.0 != values {
+                    facts.0 = values;
+                }
+            }
+            None => {
+                commands.entity(tree).insert(BlackboardFacts(values));
+            }
+        }
+    }
+}
@@ -0,0 +1,349 @@
+use super::{blackboard::BlackboardWrite, population::spawn_point};
+use crate::{
+    ecosystem::{spawn_organism, spawn_patch, Ecosystem, EcosystemCensus, Organism},
+    random::RandomSeed,
+    region::RegionQuery,
+};
+use bevy::{prelude::*, reflect::TypeRegistry};
+use bevy_inspector_egui::{egui, prelude::*, reflect_inspector};
+use rhai::{Dynamic, Map};
+use serde::{Deserialize, Serialize};
+use simula_behavior::prelude::*;
+use simula_core::epath::EPathQueries;
+
+pub struct EcosystemBehaviorPlugin;
+
+impl Plugin for EcosystemBehaviorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Census>()
+            .register_type::<Cull>()
+            .register_type::<Seed>()
+            .add_system(census)
+            .add_system(cull)
+            .add_system(seed);
+    }
+}
+
+#[derive(
+    Debug, Component, Reflect, FromReflect, Clone, Deserialize, Serialize, InspectorOptions, Default,
+)]
+#[reflect(InspectorOptions)]
+pub struct Census {
+    /// Blackboard entry the counts are written to, like `blackboard.census.fox`
+    #[serde(default = "default_census_key")]
+    pub key: String,
+}
+
+fn default_census_key() -> String {
+    "census".into()
+}
+
+impl BehaviorSpec for Census {
+    const TYPE: BehaviorType = BehaviorType::Action;
+    const NAME: &'static str = "Census";
+    const ICON: &'static str = "📊";
+    const DESC: &'static str = "Write the number of organisms of each species to the blackboard";
+}
+
+impl BehaviorUI for Census {
+    fn ui(
+        &mut self,
+        _label: Option<&str>,
+        _state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) -> bool {
+        ui.label("key");
+        reflect_inspector::ui_for_value(&mut self.key, ui, type_registry)
+    }
+
+    fn ui_readonly(
+        &self,
+        _label: Option<&str>,
+        _state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        _type_registry: &TypeRegistry,
+    ) {
+        ui.label(egui::RichText::new(format!("key: {}", self.key)).small());
+    }
+}
+
+#[derive(
+    Debug, Component, Reflect, FromReflect, Clone, Deserialize, Serialize, InspectorOptions, Default,
+)]
+#[reflect(InspectorOptions)]
+pub struct Cull {
+    pub species: BehaviorPropStr,
+    pub count: BehaviorPropGeneric<i64>,
+}
+
+impl BehaviorSpec for Cull {
+    const TYPE: BehaviorType = BehaviorType::Action;
+    const NAME: &'static str = "Cull";
+    const ICON: &'static str = "💀";
+    const DESC: &'static str = "Remove the oldest organisms of a species";
+}
+
+impl BehaviorUI for Cull {
+    fn ui(
+        &mut self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) -> bool {
+        let mut changed = false;
+        changed |= behavior_ui!(self, species, state, ui, type_registry);
+        changed |= behavior_ui!(self, count, state, ui, type_registry);
+        changed
+    }
+
+    fn ui_readonly(
+        &self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) {
+        behavior_ui_readonly!(self, species, state, ui, type_registry);
+        behavior_ui_readonly!(self, count, state, ui, type_registry);
+    }
+}
+
+#[derive(
+    Debug, Component, Reflect, FromReflect, Clone, Deserialize, Serialize, InspectorOptions, Default,
+)]
+#[reflect(InspectorOptions)]
+pub struct Seed {
+    /// Species or resource to add
+    pub species: BehaviorPropStr,
+    pub count: BehaviorPropGeneric<i64>,
+    /// Region to seed in, instead of the center and radius
+    #[serde(default)]
+    pub region: BehaviorPropOption<BehaviorPropEPath>,
+    #[serde(default)]
+    pub center: Vec3,
+    #[serde(default)]
+    pub radius: f32,
+
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub seeded: u32,
+}
+
+impl BehaviorSpec for Seed {
+    const TYPE: BehaviorType = BehaviorType::Action;
+    const NAME: &'static str = "Seed";
+    const ICON: &'static str = "🌱";
+    const DESC: &'static str = "Add organisms of a species, or patches of a resource";
+}
+
+impl BehaviorUI for Seed {
+    fn ui(
+        &mut self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) -> bool {
+        let mut changed = false;
+        changed |= behavior_ui!(self, species, state, ui, type_registry);
+        changed |= behavior_ui!(self, count, state, ui, type_registry);
+        changed |= behavior_ui!(self, region, state, ui, type_registry);
+        ui.label("center");
+        changed |= reflect_inspector::ui_for_value(&mut self.center, ui, type_registry);
+        ui.label("radius");
+        changed |= reflect_inspector::ui_for_value(&mut self.radius, ui, type_registry);
+        changed
+    }
+
+    fn ui_readonly(
+        &self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) {
+        behavior_ui_readonly!(self, species, state, ui, type_registry);
+        behavior_ui_readonly!(self, count, state, ui, type_registry);
+        behavior_ui_readonly!(self, region, state, ui, type_registry);
+    }
+}
+
+pub fn census(
+    mut commands: Commands,
+    censuses: Query<(Entity, &Census, &BehaviorNode), BehaviorRunQuery>,
+    ecosystem_census: Res<EcosystemCensus>,
+    mut writes: EventWriter<BlackboardWrite>,
+) {
+    for (entity, census, node) in &censuses {
+        if let Some(tree) = node.tree {
+            let mut counts = Map::new();
+            for (species, count) in &ecosystem_census.counts {
+                counts.insert(species.as_str().into(), Dynamic::from(*count as i64));
+            }
+            writes.send(BlackboardWrite::new(
+                tree,
+                census.key.clone(),
+                Dynamic::from(counts),
+            ));
+        }
+        commands.entity(entity).insert(BehaviorSuccess);
+    }
+}
+
+pub fn cull(
+    mut commands: Commands,
+    mut culls: Query<
+        (Entity, &mut Cull, &BehaviorNode, Option<&BehaviorStarted>),
+        BehaviorRunQuery,
+    >,
+    organisms: Query<(Entity, &Organism)>,
+    mut scripts: ScriptQueries,
+) {
+    for (entity, mut cull, node, started) in &mut culls {
+        if started.is_some() {
+            // reset eval properties
+            cull.species.value = BehaviorPropValue::None;
+            cull.count.value = BehaviorPropValue::None;
+        }
+
+        // keep working on eval properties
+        if let BehaviorPropValue::None = cull.species.value {
+            let result = cull.species.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        if let BehaviorPropValue::None = cull.count.value {
+            let result = cull.count.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        let (BehaviorPropValue::Some(cull_species), BehaviorPropValue::Some(cull_count)) =
+            (&cull.species.value, &cull.count.value)
+        else {
+            continue;
+        };
+        let cull_species = cull_species.to_string();
+        let cull_count = (*cull_count).max(0) as usize;
+
+        // oldest first
+        let mut victims: Vec<(Entity, f32)> = organisms
+            .iter()
+            .filter(|(_, organism)| organism.species == cull_species)
+            .map(|(victim, organism)| (victim, organism.age))
+            .collect();
+        victims.sort_by(|a, b| b.1.total_cmp(&a.1));
+        for (victim, _) in victims.into_iter().take(cull_count) {
+            commands.entity(victim).despawn_recursive();
+        }
+        commands.entity(entity).insert(BehaviorSuccess);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn seed(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    ecosystem: Res<Ecosystem>,
+    random: Res<RandomSeed>,
+    mut seeds: Query<
+        (Entity, &mut Seed, &BehaviorNode, Option<&BehaviorStarted>),
+        BehaviorRunQuery,
+    >,
+    regions: RegionQuery,
+    equeries: EPathQueries,
+    mut scripts: ScriptQueries,
+) {
+    for (entity, mut seed, node, started) in &mut seeds {
+        let seed = seed.as_mut();
+
+        if started.is_some() {
+            // reset eval properties
+            seed.species.value = BehaviorPropValue::None;
+            seed.count.value = BehaviorPropValue::None;
+            if let Some(region) = &mut *seed.region {
+                region.value = BehaviorPropValue::None;
+            }
+        }
+
+        // keep working on eval properties
+        if let BehaviorPropValue::None = seed.species.value {
+            let result = seed.species.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        if let BehaviorPropValue::None = seed.count.value {
+            let result = seed.count.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        if let Some(prop) = &mut seed.region.as_mut() {
+            if let BehaviorPropValue::None = prop.value {
+                let result = prop.fetch(node, &mut scripts);
+                if let Some(Err(err)) = result {
+                    error!("Script errored: {:?}", err);
+                    commands.entity(entity).insert(BehaviorFailure);
+                    continue;
+                }
+            }
+        }
+
+        let seed_region = if let Some(prop) = &*seed.region {
+            if let BehaviorPropValue::Some(value) = &prop.value {
+                Some(Some(value.clone()))
+            } else {
+                None
+            }
+        } else {
+            Some(None)
+        };
+        let (
+            BehaviorPropValue::Some(seed_species),
+            BehaviorPropValue::Some(seed_count),
+            Some(seed_region),
+        ) = (&seed.species.value, &seed.count.value, seed_region)
+        else {
+            continue;
+        };
+        let seed_species = seed_species.to_string();
+        let seed_count = (*seed_count).max(0) as u32;
+        let region = seed_region.and_then(|path| regions.select(&path, &equeries));
+
+        let species = ecosystem.species(&seed_species);
+        let resource = ecosystem.resource(&seed_species);
+        if species.is_none() && resource.is_none() {
+            warn!("No species or resource named: {}", seed_species);
+            commands.entity(entity).insert(BehaviorFailure);
+            continue;
+        }
+        for _ in 0..seed_count {
+            let position = match region {
+                Some((region, transform)) => {
+                    region.random_point(transform, random.mix(seed.seeded as u64))
+                }
+                None => spawn_point(seed.center, seed.radius, seed.seeded),
+            };
+            seed.seeded += 1;
+            if let Some(species) = species {
+                let energy = species.max_energy * 0.5;
+                spawn_organism(&mut commands, &asset_server, species, position, energy);
+            } else if let Some(resource) = resource {
+                spawn_patch(&mut commands, &asset_server, resource, position);
+            }
+        }
+        commands.entity(entity).insert(BehaviorSuccess);
+    }
+}
@@ -0,0 +1,162 @@
+use super::npc::SpawnOwned;
+use crate::export::{SceneExport, SceneExported};
+use bevy::{prelude::*, reflect::TypeRegistry};
+use bevy_inspector_egui::prelude::*;
+use serde::{Deserialize, Serialize};
+use simula_behavior::prelude::*;
+use simula_core::epath::{self, EPathQueries};
+
+pub struct ExportBehaviorPlugin;
+
+impl Plugin for ExportBehaviorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Export>().add_system(run);
+    }
+}
+
+#[derive(
+    Debug, Component, Reflect, FromReflect, Clone, Deserialize, Serialize, InspectorOptions, Default,
+)]
+#[reflect(InspectorOptions)]
+pub struct Export {
+    /// Scene file, relative to the assets folder, like `scenes/market.scn.ron`
+    pub path: BehaviorPropStr,
+    /// NPCs to export, all spawned NPCs if not set
+    #[serde(default)]
+    pub target: BehaviorPropOption<BehaviorPropEPath>,
+
+    /// Waiting for the scene to be written
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub pending: bool,
+}
+
+impl BehaviorSpec for Export {
+    const TYPE: BehaviorType = BehaviorType::Action;
+    const NAME: &'static str = "Export";
+    const ICON: &'static str = "📦";
+    const DESC: &'static str = "Export spawned NPCs to a static scene file";
+}
+
+impl BehaviorUI for Export {
+    fn ui(
+        &mut self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) -> bool {
+        let mut changed = false;
+        changed |= behavior_ui!(self, path, state, ui, type_registry);
+        changed |= behavior_ui!(self, target, state, ui, type_registry);
+        changed
+    }
+
+    fn ui_readonly(
+        &self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) {
+        behavior_ui_readonly!(self, path, state, ui, type_registry);
+        behavior_ui_readonly!(self, target, state, ui, type_registry);
+    }
+}
+
+pub fn run(
+    mut commands: Commands,
+    mut exports: Query<
+        (
+            Entity,
+            &mut Export,
+            &BehaviorNode,
+            Option<&BehaviorStarted>,
+            Option<&SceneExported>,
+        ),
+        BehaviorRunQuery,
+    >,
+    npcs: Query<(Entity, Option<&Parent>), With<SpawnOwned>>,
+    mut scene_exports: EventWriter<SceneExport>,
+    mut scripts: ScriptQueries,
+    equeries: EPathQueries,
+) {
+    for (entity, mut export, node, started, exported) in &mut exports {
+        if started.is_some() {
+            // reset eval properties
+            export.path.value = BehaviorPropValue::None;
+            if let Some(target) = &mut *export.target {
+                target.value = BehaviorPropValue::None;
+            }
+            export.pending = false;
+            commands.entity(entity).remove::<SceneExported>();
+        }
+
+        // complete once the scene is written
+        if export.pending {
+            if let Some(exported) = exported {
+                export.pending = false;
+                commands.entity(entity).remove::<SceneExported>();
+                match exported.result {
+                    Ok(_) => commands.entity(entity).insert(BehaviorSuccess),
+                    Err(_) => commands.entity(entity).insert(BehaviorFailure),
+                };
+            }
+            continue;
+        }
+
+        // keep working on eval properties
+        if let BehaviorPropValue::None = export.path.value {
+            let result = export.path.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        if let Some(prop) = &mut export.target.as_mut() {
+            if let BehaviorPropValue::None = prop.value {
+                let result = prop.fetch(node, &mut scripts);
+                if let Some(Err(err)) = result {
+                    error!("Script errored: {:?}", err);
+                    commands.entity(entity).insert(BehaviorFailure);
+                    continue;
+                }
+            }
+        }
+
+        let BehaviorPropValue::Some(export_path) = &export.path.value else {
+            continue;
+        };
+        let roots: Vec<Entity> = match &*export.target {
+            Some(prop) => {
+                let BehaviorPropValue::Some(target) = &prop.value else {
+                    continue;
+                };
+                epath::select(None, target, &equeries)
+                    .into_iter()
+                    .map(|target| target.entity)
+                    .filter(|target| npcs.contains(*target))
+                    .collect()
+            }
+            // attached NPCs go with their parent
+            None => npcs
+                .iter()
+                .filter(|(_, parent)| parent.map_or(true, |parent| !npcs.contains(parent.get())))
+                .map(|(npc, _)| npc)
+                .collect(),
+        };
+
+        if roots.is_empty() {
+            warn!("No NPCs to export to: {:?}", export_path);
+            commands.entity(entity).insert(BehaviorFailure);
+            continue;
+        }
+        scene_exports.send(SceneExport {
+            roots,
+            path: export_path.to_string(),
+            requester: Some(entity),
+        });
+        export.pending = true;
+    }
+}
@@ -0,0 +1,364 @@
+use super::{
+    blackboard::{BlackboardCopy, BlackboardWrite},
+    npc::NPCBehavior,
+};
+use bevy::{asset::LoadState, prelude::*, reflect::TypeRegistry};
+use bevy_inspector_egui::{egui, prelude::*, reflect_inspector};
+use rhai::Dynamic;
+use serde::{Deserialize, Serialize};
+use simula_behavior::prelude::*;
+
+pub struct InvokePlugin;
+
+impl Plugin for InvokePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Invoke>()
+            .register_type::<InvokeInput>()
+            .register_type::<InvokeOutput>()
+            .register_type::<InvokeValue>()
+            .add_system(find_roots)
+            .add_system(run.after(find_roots))
+            .add_system(
+                removed
+                    .in_base_set(CoreSet::PostUpdate)
+                    .after(BehaviorSet::PostUpdate),
+            );
+    }
+}
+
+/// Typed parameter value, evaluated in the invoking tree
+#[derive(Debug, Clone, Reflect, FromReflect, Serialize, Deserialize)]
+pub enum InvokeValue {
+    Bool(BehaviorPropGeneric<bool>),
+    Int(BehaviorPropGeneric<i64>),
+    Float(BehaviorPropGeneric<f64>),
+    Str(BehaviorPropStr),
+}
+
+impl Default for InvokeValue {
+    fn default() -> Self {
+        InvokeValue::Bool(Default::default())
+    }
+}
+
+impl InvokeValue {
+    fn reset(&mut self) {
+        match self {
+            InvokeValue::Bool(prop) => prop.value = BehaviorPropValue::None,
+            InvokeValue::Int(prop) => prop.value = BehaviorPropValue::None,
+            InvokeValue::Float(prop) => prop.value = BehaviorPropValue::None,
+            InvokeValue::Str(prop) => prop.value = BehaviorPropValue::None,
+        }
+    }
+
+    /// Keep working on the value, returns the script error if any
+    fn fetch(&mut self, node: &BehaviorNode, scripts: &mut ScriptQueries) -> Option<String> {
+        match self {
+            InvokeValue::Bool(prop) => match prop.value {
+                BehaviorPropValue::None => prop.fetch(node, scripts).and_then(Result::err),
+                _ => None,
+            }
+            .map(|err| format!("{:?}", err)),
+            InvokeValue::Int(prop) => match prop.value {
+                BehaviorPropValue::None => prop.fetch(node, scripts).and_then(Result::err),
+                _ => None,
+            }
+            .map(|err| format!("{:?}", err)),
+            InvokeValue::Float(prop) => match prop.value {
+                BehaviorPropValue::None => prop.fetch(node, scripts).and_then(Result::err),
+                _ => None,
+            }
+            .map(|err| format!("{:?}", err)),
+            InvokeValue::Str(prop) => match prop.value {
+                BehaviorPropValue::None => prop.fetch(node, scripts).and_then(Result::err),
+                _ => None,
+            }
+            .map(|err| format!("{:?}", err)),
+        }
+    }
+
+    fn value(&self) -> Option<Dynamic> {
+        match self {
+            InvokeValue::Bool(BehaviorPropGeneric {
+                value: BehaviorPropValue::Some(value),
+                ..
+            }) => Some(Dynamic::from(*value)),
+            InvokeValue::Int(BehaviorPropGeneric {
+                value: BehaviorPropValue::Some(value),
+                ..
+            }) => Some(Dynamic::from(*value)),
+            InvokeValue::Float(BehaviorPropGeneric {
+                value: BehaviorPropValue::Some(value),
+                ..
+            }) => Some(Dynamic::from(*value)),
+            InvokeValue::Str(prop) => match &prop.value {
+                BehaviorPropValue::Some(value) => Some(Dynamic::from(value.to_string())),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Blackboard entry written into the invoked tree on start
+#[derive(Debug, Clone, Default, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct InvokeInput {
+    pub name: String,
+    pub value: InvokeValue,
+}
+
+/// Blackboard entry copied back from the invoked tree on completion
+#[derive(Debug, Clone, Default, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct InvokeOutput {
+    /// Entry of the invoked tree
+    pub name: String,
+    /// Entry of the invoking tree, same name if not set
+    #[serde(default)]
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InvokeState {
+    pub tree: Option<Entity>,
+}
+
+/// Invoked NPC behavior tree, owned by an `Invoke` node
+#[derive(Debug, Component)]
+pub struct InvokedTree {
+    pub invoke: Entity,
+    /// Root node, once the asset loaded and the tree spawned it
+    pub root: Option<Entity>,
+}
+
+/// Parameterized counterpart of `NPC(Subtree)`. `Subtree` comes from simula_behavior,
+/// with no room for inputs or outputs, so they live on this node instead.
+#[derive(
+    Debug, Component, Reflect, FromReflect, Clone, Deserialize, Serialize, InspectorOptions, Default,
+)]
+#[reflect(InspectorOptions)]
+pub struct Invoke {
+    /// NPC behavior tree to run, like "bht/u/patrol.bht.ron"
+    pub asset: BehaviorPropStr,
+    #[serde(default)]
+    pub inputs: Vec<InvokeInput>,
+    #[serde(default)]
+    pub outputs: Vec<InvokeOutput>,
+
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub state: InvokeState,
+}
+
+impl BehaviorSpec for Invoke {
+    const TYPE: BehaviorType = BehaviorType::Action;
+    const NAME: &'static str = "Invoke";
+    const ICON: &'static str = "📨";
+    const DESC: &'static str =
+        "Run an NPC tree with input blackboard entries, copying outputs back on completion";
+}
+
+impl BehaviorUI for Invoke {
+    fn ui(
+        &mut self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) -> bool {
+        let mut changed = false;
+        changed |= behavior_ui!(self, asset, state, ui, type_registry);
+        ui.label("inputs");
+        changed |= reflect_inspector::ui_for_value(&mut self.inputs, ui, type_registry);
+        ui.label("outputs");
+        changed |= reflect_inspector::ui_for_value(&mut self.outputs, ui, type_registry);
+        changed
+    }
+
+    fn ui_readonly(
+        &self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) {
+        behavior_ui_readonly!(self, asset, state, ui, type_registry);
+        for input in &self.inputs {
+            let value = input
+                .value
+                .value()
+                .map(|value| value.to_string())
+                .unwrap_or_else(|| "…".into());
+            ui.label(egui::RichText::new(format!("→ {}: {}", input.name, value)).small());
+        }
+        for output in &self.outputs {
+            let to = output.to.as_deref().unwrap_or(&output.name);
+            ui.label(egui::RichText::new(format!("← {} as {}", output.name, to)).small());
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn run(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut invokes: Query<
+        (Entity, &mut Invoke, &BehaviorNode, Option<&BehaviorStarted>),
+        BehaviorRunQuery,
+    >,
+    invoked_trees: Query<(&InvokedTree, &Handle<BehaviorAsset<NPCBehavior>>)>,
+    roots: Query<(Option<&BehaviorSuccess>, Option<&BehaviorFailure>), Without<Invoke>>,
+    mut writes: EventWriter<BlackboardWrite>,
+    mut copies: EventWriter<BlackboardCopy>,
+    mut scripts: ScriptQueries,
+) {
+    for (entity, mut invoke, node, started) in &mut invokes {
+        let invoke = invoke.as_mut();
+
+        if started.is_some() {
+            // reset eval properties
+            invoke.asset.value = BehaviorPropValue::None;
+            for input in invoke.inputs.iter_mut() {
+                input.value.reset();
+            }
+            if let Some(tree) = invoke.state.tree.take() {
+                commands.entity(tree).despawn_recursive();
+            }
+        }
+
+        // check on the invoked tree
+        if let Some(tree) = invoke.state.tree {
+            let Ok((invoked, handle)) = invoked_trees.get(tree) else {
+                continue;
+            };
+            if asset_server.get_load_state(handle) == LoadState::Failed {
+                error!(
+                    "Invoke: cannot load {:?}",
+                    asset_server.get_handle_path(handle)
+                );
+                commands.entity(tree).despawn_recursive();
+                invoke.state.tree = None;
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+            let Some((success, failure)) = invoked
+                .root
+                .and_then(|root| roots.get(root).ok())
+                .map(|(success, failure)| (success.is_some(), failure.is_some()))
+            else {
+                continue;
+            };
+            if !success && !failure {
+                continue;
+            }
+            invoke.state.tree = None;
+            // outputs are copied even on failure, the tree may have reported why
+            if let Some(parent) = node.tree {
+                copies.send(BlackboardCopy {
+                    from: tree,
+                    to: parent,
+                    keys: invoke
+                        .outputs
+                        .iter()
+                        .map(|output| {
+                            let to = output.to.clone().unwrap_or_else(|| output.name.clone());
+                            (output.name.clone(), to)
+                        })
+                        .collect(),
+                    despawn: true,
+                });
+            } else {
+                commands.entity(tree).despawn_recursive();
+            }
+            if success {
+                commands.entity(entity).insert(BehaviorSuccess);
+            } else {
+                commands.entity(entity).insert(BehaviorFailure);
+            }
+            continue;
+        }
+
+        // keep working on eval properties
+        if let BehaviorPropValue::None = invoke.asset.value {
+            let result = invoke.asset.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        let mut errored = false;
+        for input in invoke.inputs.iter_mut() {
+            if let Some(err) = input.value.fetch(node, &mut scripts) {
+                error!("Script errored: {}", err);
+                errored = true;
+                break;
+            }
+        }
+        if errored {
+            commands.entity(entity).insert(BehaviorFailure);
+            continue;
+        }
+
+        let BehaviorPropValue::Some(invoke_asset) = &invoke.asset.value else {
+            continue;
+        };
+        let Some(inputs) = invoke
+            .inputs
+            .iter()
+            .map(|input| input.value.value().map(|value| (input.name.clone(), value)))
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+
+        // start the tree, inputs land once its blackboard is ready
+        let invoke_asset = invoke_asset.to_string();
+        let handle: Handle<BehaviorAsset<NPCBehavior>> = asset_server.load(invoke_asset.as_str());
+        let tree = commands
+            .spawn((
+                Name::new(format!("BHT: {}", invoke_asset)),
+                handle,
+                BehaviorTree::<NPCBehavior>::default(),
+                BehaviorTreeReset::<NPCBehavior>::default(),
+                InvokedTree {
+                    invoke: entity,
+                    root: None,
+                },
+            ))
+            .id();
+        for (name, value) in inputs {
+            writes.send(BlackboardWrite::new(tree, name, value));
+        }
+        invoke.state.tree = Some(tree);
+    }
+}
+
+// Keep track of the root of invoked trees, spawned once their asset loads and again on resets
+fn find_roots(
+    nodes: Query<(Entity, &BehaviorNode, &Parent), Added<BehaviorNode>>,
+    mut invoked: Query<&mut InvokedTree>,
+) {
+    for (entity, node, parent) in &nodes {
+        if node.tree != Some(parent.get()) {
+            continue;
+        }
+        if let Ok(mut invoked) = invoked.get_mut(parent.get()) {
+            invoked.root = Some(entity);
+        }
+    }
+}
+
+// Despawn the invoked tree when the node is removed
+fn removed(
+    mut removals: RemovedComponents<Invoke>,
+    mut commands: Commands,
+    invoked: Query<(Entity, &InvokedTree)>,
+) {
+    for entity in &mut removals {
+        for (tree, invoked) in &invoked {
+            if invoked.invoke == entity {
+                commands.entity(tree).despawn_recursive();
+            }
+        }
+    }
+}
@@ -1,2 +1,12 @@
+pub mod abort;
 pub mod bioma;
+pub mod blackboard;
+pub mod ecosystem;
+pub mod export;
+pub mod invoke;
 pub mod npc;
+pub mod population;
+pub mod utility;
+
+#[cfg(test)]
+mod tests;
@@ -1,3 +1,4 @@
+use crate::trace::NodeEffect;
 use bevy::{prelude::*, reflect::TypeRegistry};
 use bevy_inspector_egui::{egui, prelude::*};
 use serde::{Deserialize, Serialize};
@@ -69,6 +70,7 @@ pub fn run(
         BehaviorRunQuery,
     >,
     mut anim_players: Query<(Entity, &Name, Option<&mut AnimationPlayer>)>,
+    mut effects: EventWriter<NodeEffect>,
     mut scripts: ScriptQueries,
     equeries: EPathQueries,
 ) {
@@ -123,8 +125,15 @@ pub fn run(
 
                 let targets = epath::select(None, &anim_target, &equeries);
                 for target in &targets {
-                    if let Ok((entity, _name, anim_player)) = anim_players.get_mut(target.entity) {
+                    if let Ok((target_entity, name, anim_player)) =
+                        anim_players.get_mut(target.entity)
+                    {
                         successes += 1;
+                        effects.send(NodeEffect::Anim {
+                            node: entity,
+                            target: name.to_string(),
+                            asset: anim_asset.to_string(),
+                        });
                         if let Some(mut anim_player) = anim_player {
                             anim_player.start(clip.clone());
                             if *anim_repeat {
@@ -138,7 +147,7 @@ pub fn run(
                             if *anim_repeat {
                                 anim_player.repeat();
                             }
-                            commands.entity(entity).insert(anim_player);
+                            commands.entity(target_entity).insert(anim_player);
                         }
                     } else {
                         warn!("Invalid anim target: {:?}", target);
@@ -147,7 +156,10 @@ pub fn run(
 
                 anim.clip = Some(clip.clone());
 
-                if successes == targets.len() {
+                if targets.is_empty() {
+                    warn!("No anim target at: {:?}", anim_target);
+                    commands.entity(entity).insert(BehaviorFailure);
+                } else if successes == targets.len() {
                     commands.entity(entity).insert(BehaviorSuccess);
                 } else {
                     commands.entity(entity).insert(BehaviorFailure);
@@ -0,0 +1,130 @@
+use super::{NPCTrees, SpawnOwned};
+use crate::stimulus::Stimulus;
+use bevy::{prelude::*, reflect::TypeRegistry};
+use bevy_inspector_egui::prelude::*;
+use serde::{Deserialize, Serialize};
+use simula_behavior::prelude::*;
+
+#[derive(
+    Debug, Component, Reflect, FromReflect, Clone, Deserialize, Serialize, InspectorOptions, Default,
+)]
+#[reflect(InspectorOptions)]
+pub struct Emit {
+    pub kind: BehaviorPropStr,
+    pub radius: BehaviorPropGeneric<f64>,
+    #[serde(default)]
+    pub intensity: BehaviorPropGeneric<f64>,
+}
+
+impl BehaviorSpec for Emit {
+    const TYPE: BehaviorType = BehaviorType::Action;
+    const NAME: &'static str = "Emit";
+    const ICON: &'static str = "📢";
+    const DESC: &'static str = "Emit a stimulus from the NPC position";
+}
+
+impl BehaviorUI for Emit {
+    fn ui(
+        &mut self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) -> bool {
+        let mut changed = false;
+        changed |= behavior_ui!(self, kind, state, ui, type_registry);
+        changed |= behavior_ui!(self, radius, state, ui, type_registry);
+        changed |= behavior_ui!(self, intensity, state, ui, type_registry);
+        changed
+    }
+
+    fn ui_readonly(
+        &self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) {
+        behavior_ui_readonly!(self, kind, state, ui, type_registry);
+        behavior_ui_readonly!(self, radius, state, ui, type_registry);
+        behavior_ui_readonly!(self, intensity, state, ui, type_registry);
+    }
+}
+
+pub fn run(
+    mut commands: Commands,
+    mut emits: Query<
+        (Entity, &mut Emit, &BehaviorNode, Option<&BehaviorStarted>),
+        BehaviorRunQuery,
+    >,
+    npcs: Query<(&SpawnOwned, &GlobalTransform)>,
+    trees: NPCTrees,
+    mut stimuli: EventWriter<Stimulus>,
+    mut scripts: ScriptQueries,
+) {
+    for (entity, mut emit, node, started) in &mut emits {
+        if started.is_some() {
+            // reset eval properties
+            emit.kind.value = BehaviorPropValue::None;
+            emit.radius.value = BehaviorPropValue::None;
+            emit.intensity.value = BehaviorPropValue::None;
+        }
+
+        // keep working on eval properties
+        if let BehaviorPropValue::None = emit.kind.value {
+            let result = emit.kind.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        if let BehaviorPropValue::None = emit.radius.value {
+            let result = emit.radius.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        if let BehaviorPropValue::None = emit.intensity.value {
+            let result = emit.intensity.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+
+        // if all eval properties are ready, emit from every NPC of this tree
+        if let (
+            BehaviorPropValue::Some(emit_kind),
+            BehaviorPropValue::Some(emit_radius),
+            BehaviorPropValue::Some(emit_intensity),
+        ) = (&emit.kind.value, &emit.radius.value, &emit.intensity.value)
+        {
+            let mut emitted = 0;
+            for (owned, transform) in &npcs {
+                if trees.tree(owned) == node.tree {
+                    stimuli.send(Stimulus {
+                        source: node.tree,
+                        ..Stimulus::new(
+                            emit_kind.to_string(),
+                            transform.translation(),
+                            *emit_radius as f32,
+                            *emit_intensity as f32,
+                        )
+                    });
+                    emitted += 1;
+                }
+            }
+
+            if emitted == 0 {
+                warn!("No NPC to emit stimulus: {:?}", emit_kind);
+                commands.entity(entity).insert(BehaviorFailure);
+            } else {
+                commands.entity(entity).insert(BehaviorSuccess);
+            }
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use super::{anim::Anim, spawn::Spawn, NPCTrees, SpawnOwned};
+use crate::{inspector::ResourceMenuPlugin, perception::npc_forward};
+use bevy::{prelude::*, utils::HashSet};
+use bevy_inspector_egui::prelude::*;
+use serde::{Deserialize, Serialize};
+use simula_action::{action_map, Action, ActionMap, ActionMapInput};
+use simula_behavior::prelude::*;
+use simula_core::epath::{self, EPathQueries};
+use simula_viz::lines::{Lines, LinesBundle};
+
+pub struct NPCGizmosPlugin;
+
+impl Plugin for NPCGizmosPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NPCGizmos>()
+            .register_type::<NPCGizmos>()
+            .add_plugin(ResourceMenuPlugin::<NPCGizmos>::default())
+            .add_startup_system(setup)
+            .add_system(action_map::<NPCGizmosAction, NPCGizmosActionInput>)
+            .add_system(toggle.after(action_map::<NPCGizmosAction, NPCGizmosActionInput>))
+            .add_system(draw.after(toggle));
+    }
+}
+
+/// Debug drawing of what NPC trees are aiming at
+#[derive(Debug, Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct NPCGizmos {
+    pub enabled: bool,
+    pub forward_length: f32,
+    pub marker_size: f32,
+    pub forward_color: Color,
+    pub target_color: Color,
+    pub missing_color: Color,
+}
+
+impl Default for NPCGizmos {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            forward_length: 1.0,
+            marker_size: 0.25,
+            forward_color: Color::CYAN,
+            target_color: Color::YELLOW,
+            missing_color: Color::RED,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum NPCGizmosAction {
+    Toggle,
+}
+
+pub type NPCGizmosActionInput = ActionMapInput<NPCGizmosAction, ()>;
+
+#[derive(Component)]
+struct NPCGizmosLines;
+
+fn setup(mut commands: Commands) {
+    let mut action_map = ActionMap::<NPCGizmosAction, ()>::default();
+    action_map.push(NPCGizmosActionInput {
+        action: NPCGizmosAction::Toggle,
+        button: KeyCode::F2.into(),
+        ..Default::default()
+    });
+
+    commands.spawn((
+        Name::new("NPC Gizmos"),
+        NPCGizmosLines,
+        LinesBundle::default(),
+        Action::<NPCGizmosAction>::default(),
+        action_map,
+    ));
+}
+
+fn toggle(mut gizmos: ResMut<NPCGizmos>, actions: Query<&Action<NPCGizmosAction>>) {
+    for action in &actions {
+        if action.on_enter(NPCGizmosAction::Toggle) {
+            gizmos.enabled = !gizmos.enabled;
+        }
+    }
+}
+
+fn marker(lines: &mut Lines, position: Vec3, size: f32, color: Color) {
+    for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+        lines.line_colored(position - axis * size, position + axis * size, 0.0, color);
+    }
+}
+
+// Draw targets from the NPCs to the entities matched by a node EPath
+fn targets(
+    lines: &mut Lines,
+    gizmos: &NPCGizmos,
+    npcs: &[Vec3],
+    path: &epath::EPath,
+    equeries: &EPathQueries,
+    transforms: &Query<&GlobalTransform>,
+) {
+    let matched: Vec<Vec3> = epath::select(None, path, equeries)
+        .iter()
+        .filter_map(|target| transforms.get(target.entity).ok())
+        .map(|transform| transform.translation())
+        .collect();
+    for npc in npcs {
+        // nothing to aim at, flagged above the NPC so it doesn't read as a target on it
+        if matched.is_empty() {
+            let above = *npc + Vec3::Y * (gizmos.forward_length + gizmos.marker_size);
+            marker(lines, above, gizmos.marker_size, gizmos.missing_color);
+        }
+        for target in &matched {
+            lines.line_colored(*npc, *target, 0.0, gizmos.target_color);
+            marker(
+                lines,
+                *target,
+                gizmos.marker_size * 0.5,
+                gizmos.target_color,
+            );
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw(
+    gizmos: Res<NPCGizmos>,
+    mut lines: Query<&mut Lines, With<NPCGizmosLines>>,
+    running: Query<&BehaviorNode, With<BehaviorRunning>>,
+    spawns: Query<(&Spawn, &BehaviorNode)>,
+    anims: Query<(&Anim, &BehaviorNode)>,
+    npcs: Query<(&SpawnOwned, &GlobalTransform)>,
+    transforms: Query<&GlobalTransform>,
+    trees: NPCTrees,
+    equeries: EPathQueries,
+) {
+    if !gizmos.enabled {
+        return;
+    }
+    let Ok(mut lines) = lines.get_single_mut() else {
+        return;
+    };
+
+    // only trees with an active node
+    let active: HashSet<Entity> = running.iter().filter_map(|node| node.tree).collect();
+
+    let npcs_of = |tree: Option<Entity>| -> Vec<Vec3> {
+        npcs.iter()
+            .filter(|(owned, _)| tree.is_some() && trees.tree(owned) == tree)
+            .map(|(_, transform)| transform.translation())
+            .collect()
+    };
+
+    // forward direction of every NPC root
+    for (owned, transform) in &npcs {
+        if let Some(tree) = trees.tree(owned) {
+            if active.contains(&tree) {
+                let start = transform.translation();
+                let end = start + npc_forward(transform) * gizmos.forward_length;
+                lines.line_colored(start, end, 0.0, gizmos.forward_color);
+            }
+        }
+    }
+
+    for (spawn, node) in &spawns {
+        if !node.tree.map_or(false, |tree| active.contains(&tree)) {
+            continue;
+        }
+        if let Some(target) = &*spawn.target {
+            if let BehaviorPropValue::Some(path) = &target.value {
+                let spawned: Vec<Vec3> = spawn
+                    .scenes
+                    .iter()
+                    .filter_map(|scene| transforms.get(*scene).ok())
+                    .map(|transform| transform.translation())
+                    .collect();
+                let spawned = if spawned.is_empty() {
+                    npcs_of(node.tree)
+                } else {
+                    spawned
+                };
+                targets(&mut lines, &gizmos, &spawned, path, &equeries, &transforms);
+            }
+        }
+    }
+
+    for (anim, node) in &anims {
+        if !node.tree.map_or(false, |tree| active.contains(&tree)) {
+            continue;
+        }
+        if let BehaviorPropValue::Some(path) = &anim.target.value {
+            let npcs = npcs_of(node.tree);
+            targets(&mut lines, &gizmos, &npcs, path, &equeries, &transforms);
+        }
+    }
+}
@@ -1,15 +1,72 @@
+use super::utility::UtilitySelector;
 use anim::Anim;
-use bevy::{ecs::system::EntityCommands, prelude::*, reflect::TypeUuid};
+use bevy::{
+    ecs::system::{EntityCommands, SystemParam},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use emit::Emit;
+use on_stimulus::OnStimulus;
+use plan::Plan;
+use satisfy::Satisfy;
+use schedule::{DailySchedule, SchedulePolicy};
+use sense::Sense;
 use serde::{Deserialize, Serialize};
 use simula_behavior::prelude::*;
 use simula_behavior_macro::BehaviorFactory;
 use spawn::Spawn;
-
-mod anim;
-mod spawn;
+use use_smart_object::UseSmartObject;
+use wander::Wander;
+
+pub mod anim;
+mod emit;
+pub mod gizmos;
+mod on_stimulus;
+mod plan;
+mod satisfy;
+pub mod schedule;
+mod sense;
+pub mod spawn;
+mod use_smart_object;
+mod wander;
 
 #[derive(Component, Debug, Deref)]
-pub struct SpawnOwned(Entity);
+pub struct SpawnOwned(pub Entity);
+
+/// Finds the behavior tree that spawned an NPC
+#[derive(SystemParam)]
+pub struct NPCTrees<'w, 's> {
+    nodes: Query<'w, 's, &'static BehaviorNode>,
+}
+
+impl<'w, 's> NPCTrees<'w, 's> {
+    pub fn tree(&self, owned: &SpawnOwned) -> Option<Entity> {
+        self.nodes.get(**owned).ok().and_then(|node| node.tree)
+    }
+}
+
+/// Default walking speed, in meters per second
+pub const WALK_SPEED: f32 = 1.5;
+
+/// Distance at which a walking NPC has arrived
+pub const ARRIVE_DISTANCE: f32 = 0.1;
+
+/// Moves an NPC root towards a point on the ground plane, facing where it walks.
+/// Returns true once it arrived.
+pub fn walk_towards(transform: &mut Transform, target: Vec3, speed: f32, delta: f32) -> bool {
+    let mut offset = target - transform.translation;
+    offset.y = 0.0;
+    let distance = offset.length();
+    if distance <= ARRIVE_DISTANCE {
+        return true;
+    }
+    let direction = offset / distance;
+    transform.translation += direction * (speed * delta).min(distance);
+    // NPCs face along their back axis
+    let position = transform.translation;
+    transform.look_at(position - direction, Vec3::Y);
+    false
+}
 
 pub struct NPCBehaviorPlugin;
 
@@ -18,14 +75,39 @@ impl Plugin for NPCBehaviorPlugin {
         app.add_plugin(BehaviorTreePlugin::<NPCBehavior>::default())
             .register_type::<Spawn>()
             .register_type::<Anim>()
+            .register_type::<Sense>()
+            .register_type::<Emit>()
+            .register_type::<OnStimulus>()
+            .register_type::<Satisfy>()
+            .register_type::<Plan>()
+            .register_type::<UseSmartObject>()
+            .register_type::<DailySchedule>()
+            .register_type::<Wander>()
+            .register_type::<SchedulePolicy>()
             .register_type::<Subtree<NPCBehavior>>()
             .add_system(spawn::run)
+            .add_system(spawn::adopt)
             .add_system(anim::run)
+            .add_system(sense::run.after(crate::perception::perceive))
+            .add_system(emit::run)
+            .add_system(on_stimulus::run)
+            .add_system(on_stimulus::interrupt)
+            .add_system(satisfy::run)
+            .add_system(plan::run)
+            .add_system(use_smart_object::run)
+            .add_system(schedule::run)
+            .add_system(schedule::interrupt)
+            .add_system(wander::run)
             .add_system(subtree::run::<NPCBehavior>)
             .add_system(
                 spawn::removed
                     .in_base_set(CoreSet::PostUpdate)
                     .after(BehaviorSet::PostUpdate),
+            )
+            .add_system(
+                use_smart_object::release_aborted
+                    .in_base_set(CoreSet::PostUpdate)
+                    .after(BehaviorSet::PostUpdate),
             );
     }
 }
@@ -51,9 +133,18 @@ pub enum NPCBehavior {
     Delay(Delay),
     Guard(Guard),
     Timeout(Timeout),
+    UtilitySelector(UtilitySelector),
 
     Spawn(Spawn),
     Anim(Anim),
+    Sense(Sense),
+    Emit(Emit),
+    OnStimulus(OnStimulus),
+    Satisfy(Satisfy),
+    Plan(Plan),
+    UseSmartObject(UseSmartObject),
+    DailySchedule(DailySchedule),
+    Wander(Wander),
 
     Subtree(Subtree<NPCBehavior>),
 }
@@ -89,9 +180,18 @@ impl BehaviorInspectable for NPCBehavior {
             NPCBehavior::Delay(_) => Color::hex("#440").unwrap(),
             NPCBehavior::Guard(_) => Color::hex("#440").unwrap(),
             NPCBehavior::Timeout(_) => Color::hex("#440").unwrap(),
+            NPCBehavior::UtilitySelector(_) => Color::hex("#522").unwrap(),
 
             NPCBehavior::Spawn(_) => Color::hex("#AA5500").unwrap(),
             NPCBehavior::Anim(_) => Color::hex("#AA5500").unwrap(),
+            NPCBehavior::Sense(_) => Color::hex("#440").unwrap(),
+            NPCBehavior::Emit(_) => Color::hex("#AA5500").unwrap(),
+            NPCBehavior::OnStimulus(_) => Color::hex("#440").unwrap(),
+            NPCBehavior::Satisfy(_) => Color::hex("#AA5500").unwrap(),
+            NPCBehavior::Plan(_) => Color::hex("#440").unwrap(),
+            NPCBehavior::UseSmartObject(_) => Color::hex("#440").unwrap(),
+            NPCBehavior::DailySchedule(_) => Color::hex("#522").unwrap(),
+            NPCBehavior::Wander(_) => Color::hex("#AA5500").unwrap(),
 
             NPCBehavior::Subtree(_) => Color::hex("#440").unwrap(),
         }
@@ -112,9 +212,22 @@ impl BehaviorInspectable for NPCBehavior {
             NPCBehavior::Delay(_) => vec![<Delay as BehaviorSpec>::TYPE.as_ref()],
             NPCBehavior::Guard(_) => vec![<Guard as BehaviorSpec>::TYPE.as_ref()],
             NPCBehavior::Timeout(_) => vec![<Timeout as BehaviorSpec>::TYPE.as_ref()],
+            NPCBehavior::UtilitySelector(_) => vec![<UtilitySelector as BehaviorSpec>::TYPE.as_ref()],
 
             NPCBehavior::Spawn(_) => vec![<Spawn as BehaviorSpec>::TYPE.as_ref(), "NPC"],
             NPCBehavior::Anim(_) => vec![<Anim as BehaviorSpec>::TYPE.as_ref(), "NPC"],
+            NPCBehavior::Sense(_) => vec![<Sense as BehaviorSpec>::TYPE.as_ref(), "NPC"],
+            NPCBehavior::Emit(_) => vec![<Emit as BehaviorSpec>::TYPE.as_ref(), "NPC"],
+            NPCBehavior::OnStimulus(_) => vec![<OnStimulus as BehaviorSpec>::TYPE.as_ref(), "NPC"],
+            NPCBehavior::Satisfy(_) => vec![<Satisfy as BehaviorSpec>::TYPE.as_ref(), "NPC"],
+            NPCBehavior::Plan(_) => vec![<Plan as BehaviorSpec>::TYPE.as_ref(), "NPC"],
+            NPCBehavior::UseSmartObject(_) => {
+                vec![<UseSmartObject as BehaviorSpec>::TYPE.as_ref(), "NPC"]
+            }
+            NPCBehavior::DailySchedule(_) => {
+                vec![<DailySchedule as BehaviorSpec>::TYPE.as_ref(), "NPC"]
+            }
+            NPCBehavior::Wander(_) => vec![<Wander as BehaviorSpec>::TYPE.as_ref(), "NPC"],
 
             NPCBehavior::Subtree(_) => vec![<Subtree<NPCBehavior> as BehaviorSpec>::TYPE.as_ref()],
         }
@@ -0,0 +1,229 @@
+use super::{NPCTrees, SpawnOwned};
+use crate::{
+    behaviors::{abort::SubtreeAbort, blackboard::BlackboardWrite},
+    stimulus::Stimulus,
+};
+use bevy::{prelude::*, reflect::TypeRegistry};
+use bevy_inspector_egui::{egui, prelude::*};
+use rhai::Dynamic;
+use serde::{Deserialize, Serialize};
+use simula_behavior::prelude::*;
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnStimulusPhase {
+    #[default]
+    Idle,
+    Child,
+    Reaction,
+}
+
+#[derive(
+    Debug, Component, Reflect, FromReflect, Clone, Deserialize, Serialize, InspectorOptions, Default,
+)]
+#[reflect(InspectorOptions)]
+pub struct OnStimulus {
+    pub kind: BehaviorPropStr,
+
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub phase: OnStimulusPhase,
+}
+
+impl BehaviorSpec for OnStimulus {
+    const TYPE: BehaviorType = BehaviorType::Decorator;
+    const NAME: &'static str = "OnStimulus";
+    const ICON: &'static str = "⚡";
+    const DESC: &'static str =
+        "Run first child, interrupt it and run second child when a stimulus reaches the NPC";
+}
+
+impl BehaviorUI for OnStimulus {
+    fn ui(
+        &mut self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) -> bool {
+        let mut changed = false;
+        changed |= behavior_ui!(self, kind, state, ui, type_registry);
+        changed
+    }
+
+    fn ui_readonly(
+        &self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) {
+        behavior_ui_readonly!(self, kind, state, ui, type_registry);
+
+        // show if we are reacting
+        if self.phase == OnStimulusPhase::Reaction {
+            ui.label(egui::RichText::new("reacting").small());
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    mut commands: Commands,
+    mut decorators: Query<
+        (
+            Entity,
+            &mut OnStimulus,
+            &BehaviorNode,
+            &BehaviorChildren,
+            Option<&BehaviorStarted>,
+        ),
+        BehaviorRunQuery,
+    >,
+    nodes: Query<(Option<&BehaviorSuccess>, Option<&BehaviorFailure>), With<BehaviorNode>>,
+    npcs: Query<(&SpawnOwned, &GlobalTransform)>,
+    trees: NPCTrees,
+    mut stimuli: EventReader<Stimulus>,
+    mut writes: EventWriter<BlackboardWrite>,
+    mut scripts: ScriptQueries,
+) {
+    let stimuli: Vec<Stimulus> = stimuli.iter().cloned().collect();
+
+    for (entity, mut decorator, node, children, started) in &mut decorators {
+        if started.is_some() {
+            // reset eval properties
+            decorator.kind.value = BehaviorPropValue::None;
+            decorator.phase = OnStimulusPhase::Idle;
+        }
+
+        if children.len() != 2 {
+            error!("OnStimulus node should have two children: child and reaction");
+            commands.entity(entity).insert(BehaviorFailure);
+            continue;
+        }
+        let (child, reaction) = (children[0], children[1]);
+
+        // complete with the result of whatever is running
+        let running = match decorator.phase {
+            OnStimulusPhase::Idle => None,
+            OnStimulusPhase::Child => Some(child),
+            OnStimulusPhase::Reaction => Some(reaction),
+        };
+        if let Some(Ok((child_success, child_failure))) = running.map(|child| nodes.get(child)) {
+            if child_success.is_some() {
+                commands.entity(entity).insert(BehaviorSuccess);
+                continue;
+            }
+            if child_failure.is_some() {
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        // already delegated, `interrupt` watches for stimuli from here
+        if decorator.phase != OnStimulusPhase::Idle {
+            continue;
+        }
+
+        // keep working on eval properties
+        if let BehaviorPropValue::None = decorator.kind.value {
+            let result = decorator.kind.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+
+        let BehaviorPropValue::Some(kind) = &decorator.kind.value else {
+            continue;
+        };
+
+        // a stimulus may already be here before the child starts
+        if let Some(stimulus) = reaching(&stimuli, kind, node, &npcs, &trees) {
+            if let Some(tree) = node.tree {
+                writes.send(BlackboardWrite::new(
+                    tree,
+                    "stimulus",
+                    Dynamic::from(stimulus.to_map()),
+                ));
+            }
+            decorator.phase = OnStimulusPhase::Reaction;
+            commands
+                .entity(entity)
+                .insert(BehaviorCursor::Delegate(reaction));
+            continue;
+        }
+
+        decorator.phase = OnStimulusPhase::Child;
+        commands
+            .entity(entity)
+            .insert(BehaviorCursor::Delegate(child));
+    }
+}
+
+// while the first child runs the decorator doesn't hold the cursor, watch from here
+#[allow(clippy::too_many_arguments)]
+pub fn interrupt(
+    mut commands: Commands,
+    mut decorators: Query<
+        (Entity, &mut OnStimulus, &BehaviorNode, &BehaviorChildren),
+        (
+            With<BehaviorRunning>,
+            Without<BehaviorCursor>,
+            Without<BehaviorSuccess>,
+            Without<BehaviorFailure>,
+        ),
+    >,
+    npcs: Query<(&SpawnOwned, &GlobalTransform)>,
+    trees: NPCTrees,
+    aborts: SubtreeAbort,
+    mut stimuli: EventReader<Stimulus>,
+    mut writes: EventWriter<BlackboardWrite>,
+) {
+    let stimuli: Vec<Stimulus> = stimuli.iter().cloned().collect();
+    if stimuli.is_empty() {
+        return;
+    }
+
+    for (entity, mut decorator, node, children) in &mut decorators {
+        // react only once, the reaction itself can't be interrupted
+        if decorator.phase != OnStimulusPhase::Child || children.len() != 2 {
+            continue;
+        }
+        let BehaviorPropValue::Some(kind) = &decorator.kind.value else {
+            continue;
+        };
+        let Some(stimulus) = reaching(&stimuli, kind, node, &npcs, &trees) else {
+            continue;
+        };
+
+        if let Some(tree) = node.tree {
+            writes.send(BlackboardWrite::new(
+                tree,
+                "stimulus",
+                Dynamic::from(stimulus.to_map()),
+            ));
+        }
+        aborts.abort(&mut commands, children[0]);
+        decorator.phase = OnStimulusPhase::Reaction;
+        commands
+            .entity(entity)
+            .insert(BehaviorCursor::Delegate(children[1]));
+    }
+}
+
+// first stimulus of the kind reaching an NPC of the tree, not sent by the tree itself
+fn reaching<'a>(
+    stimuli: &'a [Stimulus],
+    kind: &str,
+    node: &BehaviorNode,
+    npcs: &Query<(&SpawnOwned, &GlobalTransform)>,
+    trees: &NPCTrees,
+) -> Option<&'a Stimulus> {
+    stimuli.iter().find(|stimulus| {
+        stimulus.kind == kind
+            && (stimulus.source.is_none() || stimulus.source != node.tree)
+            && npcs.iter().any(|(owned, transform)| {
+                trees.tree(owned) == node.tree && stimulus.reaches(transform.translation())
+            })
+    })
+}
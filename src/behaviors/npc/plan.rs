@@ -0,0 +1,243 @@
+use super::NPCBehavior;
+use crate::{
+    behaviors::blackboard::{BlackboardFacts, BlackboardWrite},
+    goap::{self, GoapAction, GoapAsset, GoapState},
+};
+use bevy::{prelude::*, reflect::TypeRegistry};
+use bevy_inspector_egui::{egui, prelude::*};
+use rhai::Dynamic;
+use serde::{Deserialize, Serialize};
+use simula_behavior::prelude::*;
+
+#[derive(Debug, Clone, Default)]
+pub struct PlanState {
+    pub handle: Option<Handle<GoapAsset>>,
+    pub steps: Vec<GoapAction>,
+    pub step: usize,
+    pub running: bool,
+    pub replans: u32,
+}
+
+#[derive(
+    Debug, Component, Reflect, FromReflect, Clone, Deserialize, Serialize, InspectorOptions, Default,
+)]
+#[reflect(InspectorOptions)]
+pub struct Plan {
+    /// Path to a `.goap.ron` asset
+    pub asset: BehaviorPropStr,
+    /// Name of a goal in the asset
+    pub goal: BehaviorPropStr,
+    /// Fail after replanning this many times
+    #[serde(default = "default_max_replans")]
+    pub max_replans: u32,
+    /// Longest plan considered
+    #[serde(default = "default_max_depth")]
+    pub max_depth: u32,
+
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub state: PlanState,
+}
+
+fn default_max_replans() -> u32 {
+    3
+}
+
+fn default_max_depth() -> u32 {
+    8
+}
+
+impl BehaviorSpec for Plan {
+    const TYPE: BehaviorType = BehaviorType::Decorator;
+    const NAME: &'static str = "Plan";
+    const ICON: &'static str = "🗺";
+    const DESC: &'static str = "Plan actions to reach a goal and run them in the child Subtree";
+}
+
+impl BehaviorUI for Plan {
+    fn ui(
+        &mut self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) -> bool {
+        let mut changed = false;
+        changed |= behavior_ui!(self, asset, state, ui, type_registry);
+        changed |= behavior_ui!(self, goal, state, ui, type_registry);
+        changed
+    }
+
+    fn ui_readonly(
+        &self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) {
+        behavior_ui_readonly!(self, asset, state, ui, type_registry);
+        behavior_ui_readonly!(self, goal, state, ui, type_registry);
+
+        // show the plan, the running step is marked
+        for (index, action) in self.state.steps.iter().enumerate() {
+            let marker = if index == self.state.step { "▶" } else { " " };
+            ui.label(egui::RichText::new(format!("{} {}", marker, action.name)).small());
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    goaps: Res<Assets<GoapAsset>>,
+    mut plans: Query<
+        (
+            Entity,
+            &mut Plan,
+            &BehaviorNode,
+            &BehaviorChildren,
+            Option<&BehaviorStarted>,
+        ),
+        BehaviorRunQuery,
+    >,
+    mut subtrees: Query<(
+        &mut Subtree<NPCBehavior>,
+        Option<&BehaviorSuccess>,
+        Option<&BehaviorFailure>,
+    )>,
+    facts: Query<&BlackboardFacts>,
+    mut writes: EventWriter<BlackboardWrite>,
+    mut scripts: ScriptQueries,
+) {
+    for (entity, mut plan, node, children, started) in &mut plans {
+        let plan = plan.as_mut();
+
+        if started.is_some() {
+            // reset eval properties
+            plan.asset.value = BehaviorPropValue::None;
+            plan.goal.value = BehaviorPropValue::None;
+            plan.state = PlanState::default();
+        }
+
+        if children.len() != 1 {
+            error!("Plan node should have one Subtree child");
+            commands.entity(entity).insert(BehaviorFailure);
+            continue;
+        }
+        let child = children[0];
+        let Ok((mut subtree, child_success, child_failure)) = subtrees.get_mut(child) else {
+            error!("Plan node child should be a Subtree");
+            commands.entity(entity).insert(BehaviorFailure);
+            continue;
+        };
+
+        // keep working on eval properties
+        if let BehaviorPropValue::None = plan.asset.value {
+            let result = plan.asset.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        if let BehaviorPropValue::None = plan.goal.value {
+            let result = plan.goal.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        let (BehaviorPropValue::Some(plan_asset), BehaviorPropValue::Some(plan_goal)) =
+            (&plan.asset.value, &plan.goal.value)
+        else {
+            continue;
+        };
+        let (plan_asset, plan_goal) = (plan_asset.to_string(), plan_goal.to_string());
+
+        // wait for the asset to load
+        let handle = plan
+            .state
+            .handle
+            .get_or_insert_with(|| asset_server.load(plan_asset.as_str()));
+        let Some(goap) = goaps.get(handle) else {
+            continue;
+        };
+        let Some(goal) = goap.goals.get(&plan_goal) else {
+            error!("Invalid goal: {:?} in {:?}", plan_goal, plan_asset);
+            commands.entity(entity).insert(BehaviorFailure);
+            continue;
+        };
+
+        // check on the running step
+        if plan.state.running {
+            if child_success.is_some() {
+                plan.state.running = false;
+                let action = &plan.state.steps[plan.state.step];
+                if let Some(tree) = node.tree {
+                    for (fact, value) in &action.effects {
+                        writes.send(BlackboardWrite::new(tree, fact, Dynamic::from(*value)));
+                    }
+                }
+                plan.state.step += 1;
+                if plan.state.step >= plan.state.steps.len() {
+                    commands.entity(entity).insert(BehaviorSuccess);
+                    continue;
+                }
+            } else if child_failure.is_some() {
+                plan.state.running = false;
+                plan.state.replans += 1;
+                if plan.state.replans > plan.max_replans {
+                    warn!("Giving up on goal: {:?}", plan_goal);
+                    commands.entity(entity).insert(BehaviorFailure);
+                    continue;
+                }
+                info!("Replanning goal: {:?}", plan_goal);
+                plan.state.steps.clear();
+            } else {
+                continue;
+            }
+        }
+
+        // make a plan from the blackboard facts
+        if plan.state.steps.is_empty() {
+            let state: GoapState = node
+                .tree
+                .and_then(|tree| facts.get(tree).ok())
+                .map(|facts| {
+                    facts
+                        .iter()
+                        .map(|(fact, value)| (fact.clone(), *value))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            if goap::satisfies(&state, goal) {
+                commands.entity(entity).insert(BehaviorSuccess);
+                continue;
+            }
+
+            let Some(steps) = goap::plan(&state, goal, &goap.actions, plan.max_depth as usize)
+            else {
+                warn!("No plan for goal: {:?}", plan_goal);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            };
+            plan.state.steps = steps
+                .into_iter()
+                .map(|index| goap.actions[index].clone())
+                .collect();
+            plan.state.step = 0;
+        }
+
+        // run the next step in the child subtree
+        let action = &plan.state.steps[plan.state.step];
+        info!("Plan step: {:?}", action.name);
+        subtree.asset = action.subtree.clone().into();
+        plan.state.running = true;
+        commands
+            .entity(entity)
+            .insert(BehaviorCursor::Delegate(child));
+    }
+}
@@ -0,0 +1,151 @@
+use super::{NPCTrees, SpawnOwned};
+use crate::{lod::LodTime, needs::NPCNeeds};
+use bevy::{prelude::*, reflect::TypeRegistry};
+use bevy_inspector_egui::prelude::*;
+use serde::{Deserialize, Serialize};
+use simula_behavior::prelude::*;
+
+#[derive(
+    Debug, Component, Reflect, FromReflect, Clone, Deserialize, Serialize, InspectorOptions, Default,
+)]
+#[reflect(InspectorOptions)]
+pub struct Satisfy {
+    pub need: BehaviorPropStr,
+    /// Value restored per second
+    pub rate: BehaviorPropGeneric<f64>,
+    /// Value at which the need is satisfied
+    #[serde(default)]
+    pub until: BehaviorPropOption<BehaviorPropGeneric<f64>>,
+}
+
+impl BehaviorSpec for Satisfy {
+    const TYPE: BehaviorType = BehaviorType::Action;
+    const NAME: &'static str = "Satisfy";
+    const ICON: &'static str = "🍔";
+    const DESC: &'static str = "Restore a need of the NPC over time";
+}
+
+impl BehaviorUI for Satisfy {
+    fn ui(
+        &mut self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) -> bool {
+        let mut changed = false;
+        changed |= behavior_ui!(self, need, state, ui, type_registry);
+        changed |= behavior_ui!(self, rate, state, ui, type_registry);
+        changed |= behavior_ui!(self, until, state, ui, type_registry);
+        changed
+    }
+
+    fn ui_readonly(
+        &self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) {
+        behavior_ui_readonly!(self, need, state, ui, type_registry);
+        behavior_ui_readonly!(self, rate, state, ui, type_registry);
+        behavior_ui_readonly!(self, until, state, ui, type_registry);
+    }
+}
+
+pub fn run(
+    mut commands: Commands,
+    lod_time: LodTime,
+    mut satisfies: Query<
+        (
+            Entity,
+            &mut Satisfy,
+            &BehaviorNode,
+            Option<&BehaviorStarted>,
+        ),
+        BehaviorRunQuery,
+    >,
+    mut npcs: Query<(&SpawnOwned, &mut NPCNeeds)>,
+    trees: NPCTrees,
+    mut scripts: ScriptQueries,
+) {
+    for (entity, mut satisfy, node, started) in &mut satisfies {
+        let delta = lod_time.delta(node.tree);
+        if started.is_some() {
+            // reset eval properties
+            satisfy.need.value = BehaviorPropValue::None;
+            satisfy.rate.value = BehaviorPropValue::None;
+            if let Some(until) = &mut *satisfy.until {
+                until.value = BehaviorPropValue::None;
+            }
+        }
+
+        // keep working on eval properties
+        if let BehaviorPropValue::None = satisfy.need.value {
+            let result = satisfy.need.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        if let BehaviorPropValue::None = satisfy.rate.value {
+            let result = satisfy.rate.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        if let Some(prop) = &mut satisfy.until.as_mut() {
+            if let BehaviorPropValue::None = prop.value {
+                let result = prop.fetch(node, &mut scripts);
+                if let Some(Err(err)) = result {
+                    error!("Script errored: {:?}", err);
+                    commands.entity(entity).insert(BehaviorFailure);
+                    continue;
+                }
+            }
+        }
+
+        let satisfy_until = if let Some(prop) = &*satisfy.until {
+            if let BehaviorPropValue::Some(value) = &prop.value {
+                Some(*value as f32)
+            } else {
+                None
+            }
+        } else {
+            Some(1.0)
+        };
+
+        // if all eval properties are ready, restore the need of every NPC of this tree
+        if let (
+            BehaviorPropValue::Some(satisfy_need),
+            BehaviorPropValue::Some(satisfy_rate),
+            Some(satisfy_until),
+        ) = (&satisfy.need.value, &satisfy.rate.value, satisfy_until)
+        {
+            let mut found = 0;
+            let mut satisfied = 0;
+            for (owned, mut needs) in &mut npcs {
+                if trees.tree(owned) != node.tree {
+                    continue;
+                }
+                if let Some(need) = needs.need_mut(&satisfy_need.to_string()) {
+                    found += 1;
+                    need.value = (need.value + *satisfy_rate as f32 * delta).clamp(0.0, 1.0);
+                    if need.value >= satisfy_until {
+                        satisfied += 1;
+                    }
+                }
+            }
+
+            if found == 0 {
+                warn!("No NPC with need: {:?}", satisfy_need);
+                commands.entity(entity).insert(BehaviorFailure);
+            } else if satisfied == found {
+                commands.entity(entity).insert(BehaviorSuccess);
+            }
+        }
+    }
+}
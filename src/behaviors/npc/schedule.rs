@@ -0,0 +1,247 @@
+use super::NPCBehavior;
+use crate::{behaviors::abort::SubtreeAbort, clock::SimClock, schedule::ScheduleAsset};
+use bevy::{prelude::*, reflect::TypeRegistry};
+use bevy_inspector_egui::{egui, prelude::*, reflect_inspector};
+use serde::{Deserialize, Serialize};
+use simula_behavior::prelude::*;
+
+/// What to do with the running subtree when the clock crosses into another entry
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, FromReflect, Serialize, Deserialize,
+)]
+pub enum SchedulePolicy {
+    /// Switch right away when another entry's subtree is due
+    #[default]
+    Interrupt,
+    /// Let the running subtree complete, then switch
+    FinishFirst,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleState {
+    pub handle: Option<Handle<ScheduleAsset>>,
+    pub running: bool,
+    pub entry: Option<usize>,
+    pub subtree: Option<String>,
+}
+
+#[derive(
+    Debug, Component, Reflect, FromReflect, Clone, Deserialize, Serialize, InspectorOptions, Default,
+)]
+#[reflect(InspectorOptions)]
+pub struct DailySchedule {
+    /// Path to a `.schedule.ron` asset
+    pub asset: BehaviorPropStr,
+    #[serde(default)]
+    pub policy: SchedulePolicy,
+
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub state: ScheduleState,
+}
+
+impl BehaviorSpec for DailySchedule {
+    const TYPE: BehaviorType = BehaviorType::Composite;
+    const NAME: &'static str = "DailySchedule";
+    const ICON: &'static str = "📅";
+    const DESC: &'static str = "Run the subtree of the current schedule entry in the child Subtree";
+}
+
+impl BehaviorUI for DailySchedule {
+    fn ui(
+        &mut self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) -> bool {
+        let mut changed = false;
+        changed |= behavior_ui!(self, asset, state, ui, type_registry);
+        ui.label("policy");
+        changed |= reflect_inspector::ui_for_value(&mut self.policy, ui, type_registry);
+        changed
+    }
+
+    fn ui_readonly(
+        &self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) {
+        behavior_ui_readonly!(self, asset, state, ui, type_registry);
+        ui.label(egui::RichText::new(format!("policy: {:?}", self.policy)).small());
+
+        // show the running subtree
+        if let Some(subtree) = &self.state.subtree {
+            ui.label(egui::RichText::new(format!("▶ {}", subtree)).small());
+        }
+    }
+}
+
+pub fn run(
+    mut commands: Commands,
+    clock: Res<SimClock>,
+    asset_server: Res<AssetServer>,
+    schedule_assets: Res<Assets<ScheduleAsset>>,
+    mut schedules: Query<
+        (
+            Entity,
+            &mut DailySchedule,
+            &BehaviorNode,
+            &BehaviorChildren,
+            Option<&BehaviorStarted>,
+        ),
+        BehaviorRunQuery,
+    >,
+    mut subtrees: Query<(
+        &mut Subtree<NPCBehavior>,
+        Option<&BehaviorSuccess>,
+        Option<&BehaviorFailure>,
+    )>,
+    mut scripts: ScriptQueries,
+) {
+    let hour = clock.hour();
+    for (entity, mut schedule, node, children, started) in &mut schedules {
+        let schedule = schedule.as_mut();
+
+        if started.is_some() {
+            // reset eval properties
+            schedule.asset.value = BehaviorPropValue::None;
+            schedule.state = ScheduleState::default();
+        }
+
+        if children.len() != 1 {
+            error!("DailySchedule node should have one Subtree child");
+            commands.entity(entity).insert(BehaviorFailure);
+            continue;
+        }
+        let child = children[0];
+        let Ok((mut subtree, child_success, child_failure)) = subtrees.get_mut(child) else {
+            error!("DailySchedule node child should be a Subtree");
+            commands.entity(entity).insert(BehaviorFailure);
+            continue;
+        };
+
+        // keep working on eval properties
+        if let BehaviorPropValue::None = schedule.asset.value {
+            let result = schedule.asset.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        let BehaviorPropValue::Some(schedule_asset) = &schedule.asset.value else {
+            continue;
+        };
+        let schedule_asset = schedule_asset.to_string();
+
+        // wait for the asset to load
+        let handle = schedule
+            .state
+            .handle
+            .get_or_insert_with(|| asset_server.load(schedule_asset.as_str()));
+        let Some(routine) = schedule_assets.get(handle) else {
+            continue;
+        };
+        let due = routine.entry_at(hour);
+
+        // check on the running subtree
+        if schedule.state.running {
+            if child_failure.is_some() {
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+            // crossings while it runs are handled by `interrupt`
+            if child_success.is_some() {
+                schedule.state.running = false;
+            } else {
+                continue;
+            }
+        }
+
+        // nothing to do at this hour, wait for the next entry
+        let Some(due_subtree) = routine.subtree_at(hour) else {
+            schedule.state.entry = None;
+            schedule.state.subtree = None;
+            continue;
+        };
+
+        if due != schedule.state.entry {
+            match due {
+                Some(index) => info!("Schedule: {} at {}", routine.entries[index].name, *clock),
+                None => info!("Schedule: idle at {}", *clock),
+            }
+        }
+        subtree.asset = due_subtree.to_owned().into();
+        schedule.state.running = true;
+        schedule.state.entry = due;
+        schedule.state.subtree = Some(due_subtree.to_owned());
+        commands
+            .entity(entity)
+            .insert(BehaviorCursor::Delegate(child));
+    }
+}
+
+// while the subtree runs the schedule doesn't hold the cursor, watch the clock from here
+pub fn interrupt(
+    mut commands: Commands,
+    clock: Res<SimClock>,
+    schedule_assets: Res<Assets<ScheduleAsset>>,
+    mut schedules: Query<
+        (Entity, &mut DailySchedule, &BehaviorChildren),
+        (
+            With<BehaviorRunning>,
+            Without<BehaviorCursor>,
+            Without<BehaviorSuccess>,
+            Without<BehaviorFailure>,
+        ),
+    >,
+    mut subtrees: Query<&mut Subtree<NPCBehavior>>,
+    aborts: SubtreeAbort,
+) {
+    let hour = clock.hour();
+    for (entity, mut schedule, children) in &mut schedules {
+        let schedule = schedule.as_mut();
+        if schedule.policy != SchedulePolicy::Interrupt || !schedule.state.running {
+            continue;
+        }
+        let Some(routine) = schedule
+            .state
+            .handle
+            .as_ref()
+            .and_then(|handle| schedule_assets.get(handle))
+        else {
+            continue;
+        };
+        let due = routine.entry_at(hour);
+        if due == schedule.state.entry {
+            continue;
+        }
+        // crossing into a gap lets the running subtree finish
+        let Some(due_subtree) = routine.subtree_at(hour) else {
+            continue;
+        };
+        let Some(child) = children.first().copied() else {
+            continue;
+        };
+        let Ok(mut subtree) = subtrees.get_mut(child) else {
+            continue;
+        };
+
+        if let Some(index) = due {
+            info!(
+                "Schedule: {} at {}, interrupting",
+                routine.entries[index].name, *clock
+            );
+        }
+        aborts.abort(&mut commands, child);
+        subtree.asset = due_subtree.to_owned().into();
+        schedule.state.entry = due;
+        schedule.state.subtree = Some(due_subtree.to_owned());
+        commands
+            .entity(entity)
+            .insert(BehaviorCursor::Delegate(child));
+    }
+}
@@ -0,0 +1,198 @@
+use super::{NPCTrees, SpawnOwned};
+use crate::perception::{Perceived, Perceptions};
+use bevy::{prelude::*, reflect::TypeRegistry};
+use bevy_inspector_egui::prelude::*;
+use serde::{Deserialize, Serialize};
+use simula_behavior::prelude::*;
+
+#[derive(
+    Debug, Component, Reflect, FromReflect, Clone, Deserialize, Serialize, InspectorOptions, Default,
+)]
+#[reflect(InspectorOptions)]
+pub struct Sense {
+    /// "sight", "hearing" or "any"
+    pub sense: BehaviorPropStr,
+    #[serde(default)]
+    pub target: BehaviorPropOption<BehaviorPropStr>,
+    #[serde(default)]
+    pub within: BehaviorPropGeneric<f64>,
+}
+
+impl BehaviorSpec for Sense {
+    const TYPE: BehaviorType = BehaviorType::Decorator;
+    const NAME: &'static str = "Sense";
+    const ICON: &'static str = "👀";
+    const DESC: &'static str = "Run child if the NPC perceives a target";
+}
+
+impl BehaviorUI for Sense {
+    fn ui(
+        &mut self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) -> bool {
+        let mut changed = false;
+        changed |= behavior_ui!(self, sense, state, ui, type_registry);
+        changed |= behavior_ui!(self, target, state, ui, type_registry);
+        changed |= behavior_ui!(self, within, state, ui, type_registry);
+        changed
+    }
+
+    fn ui_readonly(
+        &self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) {
+        behavior_ui_readonly!(self, sense, state, ui, type_registry);
+        behavior_ui_readonly!(self, target, state, ui, type_registry);
+        behavior_ui_readonly!(self, within, state, ui, type_registry);
+    }
+}
+
+fn matches(perceived: &Perceived, target: Option<&str>, now: f64, within: f64) -> bool {
+    if now - perceived.time > within.max(0.0) {
+        return false;
+    }
+    match target {
+        Some(target) => perceived.name.as_deref() == Some(target),
+        None => true,
+    }
+}
+
+pub fn run(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut senses: Query<
+        (
+            Entity,
+            &mut Sense,
+            &BehaviorNode,
+            &BehaviorChildren,
+            Option<&BehaviorStarted>,
+        ),
+        BehaviorRunQuery,
+    >,
+    nodes: Query<(Option<&BehaviorSuccess>, Option<&BehaviorFailure>), With<BehaviorNode>>,
+    perceivers: Query<(&SpawnOwned, &Perceptions)>,
+    trees: NPCTrees,
+    mut scripts: ScriptQueries,
+) {
+    let now = time.elapsed_seconds_f64();
+    for (entity, mut sense, node, children, started) in &mut senses {
+        if started.is_some() {
+            // reset eval properties
+            sense.sense.value = BehaviorPropValue::None;
+            sense.within.value = BehaviorPropValue::None;
+            if let Some(target) = &mut *sense.target {
+                target.value = BehaviorPropValue::None;
+            }
+        }
+
+        if children.len() > 1 {
+            error!("Sense node should have at most one child");
+            commands.entity(entity).insert(BehaviorFailure);
+            continue;
+        }
+
+        // if child already ran, complete with its result
+        if let Some(child) = children.first() {
+            if let Ok((child_success, child_failure)) = nodes.get(*child) {
+                if child_success.is_some() {
+                    commands.entity(entity).insert(BehaviorSuccess);
+                    continue;
+                }
+                if child_failure.is_some() {
+                    commands.entity(entity).insert(BehaviorFailure);
+                    continue;
+                }
+            }
+        }
+
+        // keep working on eval properties
+        if let BehaviorPropValue::None = sense.sense.value {
+            let result = sense.sense.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        if let BehaviorPropValue::None = sense.within.value {
+            let result = sense.within.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        if let Some(prop) = &mut sense.target.as_mut() {
+            if let BehaviorPropValue::None = prop.value {
+                let result = prop.fetch(node, &mut scripts);
+                if let Some(Err(err)) = result {
+                    error!("Script errored: {:?}", err);
+                    commands.entity(entity).insert(BehaviorFailure);
+                    continue;
+                }
+            }
+        }
+
+        let sense_target = if let Some(prop) = &*sense.target {
+            if let BehaviorPropValue::Some(value) = &prop.value {
+                Some(Some(value.to_string()))
+            } else {
+                None
+            }
+        } else {
+            Some(None)
+        };
+
+        // if all eval properties are ready, check what the NPCs of this tree perceive
+        if let (
+            BehaviorPropValue::Some(sense_kind),
+            BehaviorPropValue::Some(sense_within),
+            Some(sense_target),
+        ) = (&sense.sense.value, &sense.within.value, &sense_target)
+        {
+            let (sight, hearing) = match sense_kind.to_string().as_str() {
+                "sight" => (true, false),
+                "hearing" => (false, true),
+                "any" => (true, true),
+                _ => {
+                    error!("Invalid sense: {:?}", sense_kind);
+                    commands.entity(entity).insert(BehaviorFailure);
+                    continue;
+                }
+            };
+
+            let sensed = perceivers
+                .iter()
+                .filter(|(owned, _)| trees.tree(owned) == node.tree)
+                .any(|(_, perceptions)| {
+                    (sight
+                        && perceptions
+                            .seen
+                            .iter()
+                            .any(|p| matches(p, sense_target.as_deref(), now, *sense_within)))
+                        || (hearing
+                            && perceptions
+                                .heard
+                                .iter()
+                                .any(|p| matches(p, sense_target.as_deref(), now, *sense_within)))
+                });
+
+            if !sensed {
+                commands.entity(entity).insert(BehaviorFailure);
+            } else if let Some(child) = children.first() {
+                commands
+                    .entity(entity)
+                    .insert(BehaviorCursor::Delegate(*child));
+            } else {
+                commands.entity(entity).insert(BehaviorSuccess);
+            }
+        }
+    }
+}
@@ -1,4 +1,13 @@
 use super::SpawnOwned;
+use crate::{
+    ground::GroundSnap,
+    memory::NPCMemory,
+    needs::NPCNeeds,
+    perception::{Perceivable, Perception},
+    random::RandomSeed,
+    region::RegionQuery,
+    trace::NodeEffect,
+};
 use bevy::{prelude::*, reflect::TypeRegistry, scene::SceneInstance};
 use bevy_inspector_egui::{egui, prelude::*};
 use serde::{Deserialize, Serialize};
@@ -14,10 +23,18 @@ pub struct Spawn {
     pub name: BehaviorPropStr,
     #[serde(default)]
     pub target: BehaviorPropOption<BehaviorPropEPath>,
+    #[serde(default)]
+    pub snap: BehaviorPropGeneric<bool>,
+    /// Region to spawn in, when there is no target
+    #[serde(default)]
+    pub region: BehaviorPropOption<BehaviorPropEPath>,
 
     #[serde(skip)]
     #[reflect(ignore)]
     pub scenes: Vec<Entity>,
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub spawned: u64,
 }
 
 impl BehaviorSpec for Spawn {
@@ -39,6 +56,8 @@ impl BehaviorUI for Spawn {
         changed |= behavior_ui!(self, asset, state, ui, type_registry);
         changed |= behavior_ui!(self, name, state, ui, type_registry);
         changed |= behavior_ui!(self, target, state, ui, type_registry);
+        changed |= behavior_ui!(self, snap, state, ui, type_registry);
+        changed |= behavior_ui!(self, region, state, ui, type_registry);
         changed
     }
 
@@ -52,6 +71,8 @@ impl BehaviorUI for Spawn {
         behavior_ui_readonly!(self, asset, state, ui, type_registry);
         behavior_ui_readonly!(self, name, state, ui, type_registry);
         behavior_ui_readonly!(self, target, state, ui, type_registry);
+        behavior_ui_readonly!(self, snap, state, ui, type_registry);
+        behavior_ui_readonly!(self, region, state, ui, type_registry);
 
         // show if we have scenes
         for scene in &self.scenes {
@@ -60,25 +81,33 @@ impl BehaviorUI for Spawn {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn run(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    random: Res<RandomSeed>,
     mut spawns: Query<
         (Entity, &mut Spawn, &BehaviorNode, Option<&BehaviorStarted>),
         BehaviorRunQuery,
     >,
     owned_spawns: Query<(Entity, Option<&Children>), (With<SpawnOwned>, With<SceneInstance>)>,
+    mut effects: EventWriter<NodeEffect>,
     mut scripts: ScriptQueries,
     equeries: EPathQueries,
+    regions: RegionQuery,
 ) {
     for (entity, mut spawn, node, started) in &mut spawns {
         if started.is_some() {
             // reset eval properties
             spawn.asset.value = BehaviorPropValue::None;
             spawn.name.value = BehaviorPropValue::None;
+            spawn.snap.value = BehaviorPropValue::None;
             if let Some(target) = &mut *spawn.target {
                 target.value = BehaviorPropValue::None;
             }
+            if let Some(region) = &mut *spawn.region {
+                region.value = BehaviorPropValue::None;
+            }
 
             // despawn scenes if they already exists
             for scene in &spawn.scenes {
@@ -123,6 +152,14 @@ pub fn run(
                         continue;
                     }
                 }
+                if let BehaviorPropValue::None = spawn.snap.value {
+                    let result = spawn.snap.fetch(node, &mut scripts);
+                    if let Some(Err(err)) = result {
+                        error!("Script errored: {:?}", err);
+                        commands.entity(entity).insert(BehaviorFailure);
+                        continue;
+                    }
+                }
                 if let Some(prop) = &mut spawn.target.as_mut() {
                     if let BehaviorPropValue::None = prop.value {
                         let result = prop.fetch(node, &mut scripts);
@@ -133,6 +170,16 @@ pub fn run(
                         }
                     }
                 }
+                if let Some(prop) = &mut spawn.region.as_mut() {
+                    if let BehaviorPropValue::None = prop.value {
+                        let result = prop.fetch(node, &mut scripts);
+                        if let Some(Err(err)) = result {
+                            error!("Script errored: {:?}", err);
+                            commands.entity(entity).insert(BehaviorFailure);
+                            continue;
+                        }
+                    }
+                }
 
                 // if we have a spawn target, check if ready
                 let spawn_target = if let Some(prop) = &*spawn.target {
@@ -145,13 +192,31 @@ pub fn run(
                     Some(None)
                 };
 
+                // if we have a spawn region, check if ready
+                let spawn_region = if let Some(prop) = &*spawn.region {
+                    if let BehaviorPropValue::Some(value) = &prop.value {
+                        Some(Some(value.clone()))
+                    } else {
+                        None
+                    }
+                } else {
+                    Some(None)
+                };
+
                 // if all eval properties are ready, spawn the NPC
                 if let (
                     BehaviorPropValue::Some(spawn_asset),
                     BehaviorPropValue::Some(spawn_name),
+                    BehaviorPropValue::Some(spawn_snap),
                     Some(spawn_target),
-                ) = (&spawn.asset.value, &spawn.name.value, &spawn_target)
-                {
+                    Some(spawn_region),
+                ) = (
+                    &spawn.asset.value,
+                    &spawn.name.value,
+                    &spawn.snap.value,
+                    &spawn_target,
+                    &spawn_region,
+                ) {
                     let mut scenes = vec![];
 
                     let targets = if let Some(spawn_target) = spawn_target {
@@ -163,6 +228,17 @@ pub fn run(
                         vec![None]
                     };
 
+                    let region = match spawn_region {
+                        Some(spawn_region) => {
+                            let region = regions.select(spawn_region, &equeries);
+                            if region.is_none() {
+                                warn!("No region at: {:?}", spawn_region);
+                            }
+                            region
+                        }
+                        None => None,
+                    };
+
                     for target in &targets {
                         // spawn the scene
                         let scene_id = commands
@@ -172,6 +248,12 @@ pub fn run(
                             })
                             .insert(Name::new(spawn_name.to_owned()))
                             .insert(SpawnOwned(entity))
+                            .insert((
+                                Perception::default(),
+                                Perceivable::default(),
+                                NPCMemory::default(),
+                                NPCNeeds::default(),
+                            ))
                             .id();
 
                         if let Some(target) = target {
@@ -182,8 +264,26 @@ pub fn run(
                             commands.entity(target.entity).add_child(scene_id);
                         } else {
                             info!("spawning scene: {:?}", scene_id);
+                            if let Some((region, transform)) = region {
+                                let seed = random.mix(entity.to_bits().wrapping_add(spawn.spawned));
+                                let position = region.random_point(transform, seed);
+                                commands
+                                    .entity(scene_id)
+                                    .insert(Transform::from_translation(position));
+                            }
+                            // only scenes in world space are kept on the ground
+                            if *spawn_snap {
+                                commands.entity(scene_id).insert(GroundSnap::default());
+                            }
                         }
 
+                        effects.send(NodeEffect::Spawn {
+                            node: entity,
+                            scene: scene_id,
+                            name: spawn_name.to_string(),
+                            asset: spawn_asset.to_string(),
+                        });
+
                         // keep track of the spawned scene
                         scenes.push(scene_id);
                     }
@@ -194,6 +294,7 @@ pub fn run(
                         commands.entity(entity).insert(BehaviorFailure);
                     }
 
+                    spawn.spawned += scenes.len() as u64;
                     for scene in &scenes {
                         spawn.scenes.push(*scene);
                     }
@@ -203,6 +304,20 @@ pub fn run(
     }
 }
 
+// Keep track of scenes given to a spawn from elsewhere, like a restored snapshot
+pub fn adopt(
+    owned: Query<(Entity, &SpawnOwned), Added<SpawnOwned>>,
+    mut spawns: Query<&mut Spawn>,
+) {
+    for (scene, owner) in &owned {
+        if let Ok(mut spawn) = spawns.get_mut(**owner) {
+            if !spawn.scenes.contains(&scene) {
+                spawn.scenes.push(scene);
+            }
+        }
+    }
+}
+
 // Remove spawned entities when the behavior is removed
 pub fn removed(
     mut removals: RemovedComponents<Spawn>,
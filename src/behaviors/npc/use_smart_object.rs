@@ -0,0 +1,327 @@
+use super::{walk_towards, NPCBehavior, NPCTrees, SpawnOwned, WALK_SPEED};
+use crate::{lod::LodTime, smart_object::SmartObject};
+use bevy::{prelude::*, reflect::TypeRegistry, utils::HashMap};
+use bevy_inspector_egui::{egui, prelude::*};
+use serde::{Deserialize, Serialize};
+use simula_behavior::prelude::*;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UsePhase {
+    #[default]
+    Find,
+    Walk,
+    Use,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UseState {
+    pub phase: UsePhase,
+    pub npc: Option<Entity>,
+    pub object: Option<Entity>,
+    pub slot: usize,
+}
+
+#[derive(
+    Debug, Component, Reflect, FromReflect, Clone, Deserialize, Serialize, InspectorOptions, Default,
+)]
+#[reflect(InspectorOptions)]
+pub struct UseSmartObject {
+    pub interaction: BehaviorPropStr,
+    /// Prefer objects restoring this need the most
+    #[serde(default)]
+    pub need: BehaviorPropOption<BehaviorPropStr>,
+    /// Walking speed in meters per second
+    #[serde(default)]
+    pub speed: BehaviorPropOption<BehaviorPropGeneric<f64>>,
+
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub state: UseState,
+}
+
+impl BehaviorSpec for UseSmartObject {
+    const TYPE: BehaviorType = BehaviorType::Decorator;
+    const NAME: &'static str = "UseSmartObject";
+    const ICON: &'static str = "🪑";
+    const DESC: &'static str =
+        "Find, reserve and walk to a smart object, then run its subtree in the child Subtree";
+}
+
+impl BehaviorUI for UseSmartObject {
+    fn ui(
+        &mut self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) -> bool {
+        let mut changed = false;
+        changed |= behavior_ui!(self, interaction, state, ui, type_registry);
+        changed |= behavior_ui!(self, need, state, ui, type_registry);
+        changed |= behavior_ui!(self, speed, state, ui, type_registry);
+        changed
+    }
+
+    fn ui_readonly(
+        &self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) {
+        behavior_ui_readonly!(self, interaction, state, ui, type_registry);
+        behavior_ui_readonly!(self, need, state, ui, type_registry);
+        behavior_ui_readonly!(self, speed, state, ui, type_registry);
+
+        // show the reserved object
+        if let Some(object) = self.state.object {
+            ui.label(
+                egui::RichText::new(format!(
+                    "{:?} {:?} slot {}",
+                    self.state.phase, object, self.state.slot
+                ))
+                .small(),
+            );
+        }
+    }
+}
+
+fn release(objects: &mut Query<(Entity, &mut SmartObject, &GlobalTransform)>, state: &UseState) {
+    if let (Some(npc), Some(object)) = (state.npc, state.object) {
+        if let Ok((_, mut object, _)) = objects.get_mut(object) {
+            object.release(npc);
+        }
+    }
+}
+
+// free slots of nodes that stop without completing, like on a switch, an interrupt or a
+// tree reset, and of nodes that go away
+pub fn release_aborted(
+    mut objects: Query<(Entity, &mut SmartObject, &GlobalTransform)>,
+    mut uses: Query<(Entity, &mut UseSmartObject)>,
+    mut stopped: RemovedComponents<BehaviorRunning>,
+    mut removed: RemovedComponents<UseSmartObject>,
+    mut reserved: Local<HashMap<Entity, UseState>>,
+) {
+    for entity in stopped.iter() {
+        if let Ok((_, mut use_object)) = uses.get_mut(entity) {
+            if use_object.state.object.is_some() {
+                release(&mut objects, &use_object.state);
+                use_object.state = UseState::default();
+            }
+        }
+    }
+    for entity in removed.iter() {
+        if let Some(state) = reserved.remove(&entity) {
+            release(&mut objects, &state);
+        }
+    }
+    // remember reservations, the component is gone by the time it is removed
+    for (entity, use_object) in uses.iter_mut() {
+        if !use_object.is_changed() {
+            continue;
+        }
+        if use_object.state.object.is_some() {
+            reserved.insert(entity, use_object.state.clone());
+        } else {
+            reserved.remove(&entity);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    mut commands: Commands,
+    lod_time: LodTime,
+    mut uses: Query<
+        (
+            Entity,
+            &mut UseSmartObject,
+            &BehaviorNode,
+            &BehaviorChildren,
+            Option<&BehaviorStarted>,
+        ),
+        BehaviorRunQuery,
+    >,
+    mut subtrees: Query<(
+        &mut Subtree<NPCBehavior>,
+        Option<&BehaviorSuccess>,
+        Option<&BehaviorFailure>,
+    )>,
+    mut objects: Query<(Entity, &mut SmartObject, &GlobalTransform)>,
+    // attached parts, such as hair, are parented to their NPC
+    mut npcs: Query<(Entity, &SpawnOwned, &mut Transform, &GlobalTransform), Without<Parent>>,
+    trees: NPCTrees,
+    mut scripts: ScriptQueries,
+) {
+    for (entity, mut use_object, node, children, started) in &mut uses {
+        let delta = lod_time.delta(node.tree);
+        let use_object = use_object.as_mut();
+
+        if started.is_some() {
+            // reset eval properties
+            use_object.interaction.value = BehaviorPropValue::None;
+            if let Some(need) = &mut *use_object.need {
+                need.value = BehaviorPropValue::None;
+            }
+            if let Some(speed) = &mut *use_object.speed {
+                speed.value = BehaviorPropValue::None;
+            }
+            release(&mut objects, &use_object.state);
+            use_object.state = UseState::default();
+        }
+
+        if children.len() != 1 {
+            error!("UseSmartObject node should have one Subtree child");
+            commands.entity(entity).insert(BehaviorFailure);
+            continue;
+        }
+        let child = children[0];
+        let Ok((mut subtree, child_success, child_failure)) = subtrees.get_mut(child) else {
+            error!("UseSmartObject node child should be a Subtree");
+            commands.entity(entity).insert(BehaviorFailure);
+            continue;
+        };
+
+        // if the object subtree already ran, free the slot and complete with its result
+        if use_object.state.phase == UsePhase::Use {
+            if child_success.is_some() || child_failure.is_some() {
+                release(&mut objects, &use_object.state);
+                if child_success.is_some() {
+                    commands.entity(entity).insert(BehaviorSuccess);
+                } else {
+                    commands.entity(entity).insert(BehaviorFailure);
+                }
+            }
+            continue;
+        }
+
+        // keep working on eval properties
+        if let BehaviorPropValue::None = use_object.interaction.value {
+            let result = use_object.interaction.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        if let Some(prop) = &mut use_object.need.as_mut() {
+            if let BehaviorPropValue::None = prop.value {
+                let result = prop.fetch(node, &mut scripts);
+                if let Some(Err(err)) = result {
+                    error!("Script errored: {:?}", err);
+                    commands.entity(entity).insert(BehaviorFailure);
+                    continue;
+                }
+            }
+        }
+        if let Some(prop) = &mut use_object.speed.as_mut() {
+            if let BehaviorPropValue::None = prop.value {
+                let result = prop.fetch(node, &mut scripts);
+                if let Some(Err(err)) = result {
+                    error!("Script errored: {:?}", err);
+                    commands.entity(entity).insert(BehaviorFailure);
+                    continue;
+                }
+            }
+        }
+
+        let use_need = if let Some(prop) = &*use_object.need {
+            if let BehaviorPropValue::Some(value) = &prop.value {
+                Some(Some(value.to_string()))
+            } else {
+                None
+            }
+        } else {
+            Some(None)
+        };
+        let use_speed = if let Some(prop) = &*use_object.speed {
+            if let BehaviorPropValue::Some(value) = &prop.value {
+                Some(*value as f32)
+            } else {
+                None
+            }
+        } else {
+            Some(WALK_SPEED)
+        };
+        let (BehaviorPropValue::Some(use_interaction), Some(use_need), Some(use_speed)) =
+            (&use_object.interaction.value, use_need, use_speed)
+        else {
+            continue;
+        };
+        let use_interaction = use_interaction.to_string();
+
+        // find the NPC of this tree
+        let Some((npc, _, mut npc_transform, npc_global)) = npcs
+            .iter_mut()
+            .find(|(_, owned, _, _)| trees.tree(owned) == node.tree)
+        else {
+            warn!("No NPC to use smart object");
+            commands.entity(entity).insert(BehaviorFailure);
+            continue;
+        };
+        let npc_position = npc_global.translation();
+
+        // find and reserve the best object with a free slot
+        if use_object.state.phase == UsePhase::Find {
+            let best = objects
+                .iter()
+                .filter(|(_, object, _)| {
+                    object.interaction == use_interaction
+                        && (object.has_free_slot() || object.reserved_by(npc).is_some())
+                })
+                .map(|(object_entity, object, transform)| {
+                    let distance = transform.translation().distance(npc_position);
+                    let amount = match &use_need {
+                        Some(need) => object.advert(need).unwrap_or(0.0),
+                        None => 1.0,
+                    };
+                    (object_entity, amount / (1.0 + distance))
+                })
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(object_entity, _)| object_entity);
+
+            let reserved = best.and_then(|object_entity| {
+                let (_, mut object, _) = objects.get_mut(object_entity).ok()?;
+                object.reserve(npc).map(|slot| (object_entity, slot))
+            });
+            let Some((object_entity, slot)) = reserved else {
+                warn!("No free smart object for: {:?}", use_interaction);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            };
+            use_object.state = UseState {
+                phase: UsePhase::Walk,
+                npc: Some(npc),
+                object: Some(object_entity),
+                slot,
+            };
+        }
+
+        // walk to the reserved slot
+        let target = use_object.state.object.and_then(|object_entity| {
+            let (_, object, transform) = objects.get(object_entity).ok()?;
+            object.slot_position(transform, use_object.state.slot)
+        });
+        let Some(target) = target else {
+            warn!("Smart object is gone");
+            use_object.state = UseState::default();
+            commands.entity(entity).insert(BehaviorFailure);
+            continue;
+        };
+        if !walk_towards(&mut npc_transform, target, use_speed, delta) {
+            continue;
+        }
+
+        // arrived, run the object subtree
+        let Some((_, object, _)) = use_object.state.object.and_then(|o| objects.get(o).ok()) else {
+            continue;
+        };
+        info!("Using smart object: {:?}", use_interaction);
+        subtree.asset = object.subtree.clone().into();
+        use_object.state.phase = UsePhase::Use;
+        commands
+            .entity(entity)
+            .insert(BehaviorCursor::Delegate(child));
+    }
+}
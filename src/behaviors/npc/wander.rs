@@ -0,0 +1,166 @@
+use super::{walk_towards, NPCTrees, SpawnOwned, WALK_SPEED};
+use crate::{lod::LodTime, random::RandomSeed, region::RegionQuery};
+use bevy::{prelude::*, reflect::TypeRegistry};
+use bevy_inspector_egui::{egui, prelude::*};
+use serde::{Deserialize, Serialize};
+use simula_behavior::prelude::*;
+use simula_core::epath::EPathQueries;
+
+#[derive(
+    Debug, Component, Reflect, FromReflect, Clone, Deserialize, Serialize, InspectorOptions, Default,
+)]
+#[reflect(InspectorOptions)]
+pub struct Wander {
+    /// Region to wander in
+    pub region: BehaviorPropEPath,
+    /// Walking speed in meters per second
+    #[serde(default)]
+    pub speed: BehaviorPropOption<BehaviorPropGeneric<f64>>,
+
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub target: Option<Vec3>,
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub wandered: u64,
+}
+
+impl BehaviorSpec for Wander {
+    const TYPE: BehaviorType = BehaviorType::Action;
+    const NAME: &'static str = "Wander";
+    const ICON: &'static str = "🚶";
+    const DESC: &'static str = "Walk the NPC to a random point of a region";
+}
+
+impl BehaviorUI for Wander {
+    fn ui(
+        &mut self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) -> bool {
+        let mut changed = false;
+        changed |= behavior_ui!(self, region, state, ui, type_registry);
+        changed |= behavior_ui!(self, speed, state, ui, type_registry);
+        changed
+    }
+
+    fn ui_readonly(
+        &self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) {
+        behavior_ui_readonly!(self, region, state, ui, type_registry);
+        behavior_ui_readonly!(self, speed, state, ui, type_registry);
+
+        // show where we are going
+        if let Some(target) = self.target {
+            ui.label(egui::RichText::new(format!("target: {:.2}", target)).small());
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    mut commands: Commands,
+    lod_time: LodTime,
+    random: Res<RandomSeed>,
+    mut wanders: Query<
+        (Entity, &mut Wander, &BehaviorNode, Option<&BehaviorStarted>),
+        BehaviorRunQuery,
+    >,
+    // attached parts, such as hair, are parented to their NPC
+    mut npcs: Query<(&SpawnOwned, &mut Transform), Without<Parent>>,
+    trees: NPCTrees,
+    regions: RegionQuery,
+    equeries: EPathQueries,
+    mut scripts: ScriptQueries,
+) {
+    for (entity, mut wander, node, started) in &mut wanders {
+        // longer steps for trees that skipped frames
+        let delta = lod_time.delta(node.tree);
+        let wander = wander.as_mut();
+
+        if started.is_some() {
+            // reset eval properties
+            wander.region.value = BehaviorPropValue::None;
+            if let Some(speed) = &mut *wander.speed {
+                speed.value = BehaviorPropValue::None;
+            }
+            wander.target = None;
+        }
+
+        // keep working on eval properties
+        if let BehaviorPropValue::None = wander.region.value {
+            let result = wander.region.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        if let Some(prop) = &mut wander.speed.as_mut() {
+            if let BehaviorPropValue::None = prop.value {
+                let result = prop.fetch(node, &mut scripts);
+                if let Some(Err(err)) = result {
+                    error!("Script errored: {:?}", err);
+                    commands.entity(entity).insert(BehaviorFailure);
+                    continue;
+                }
+            }
+        }
+
+        let wander_speed = if let Some(prop) = &*wander.speed {
+            if let BehaviorPropValue::Some(value) = &prop.value {
+                Some(*value as f32)
+            } else {
+                None
+            }
+        } else {
+            Some(WALK_SPEED)
+        };
+        let (BehaviorPropValue::Some(wander_region), Some(wander_speed)) =
+            (&wander.region.value, wander_speed)
+        else {
+            continue;
+        };
+
+        // pick a point of the region
+        if wander.target.is_none() {
+            let Some((region, transform)) = regions.select(wander_region, &equeries) else {
+                warn!("No region at: {:?}", wander_region);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            };
+            let seed = random.mix(entity.to_bits().wrapping_add(wander.wandered));
+            wander.wandered += 1;
+            wander.target = Some(region.random_point(transform, seed));
+        }
+        let Some(target) = wander.target else {
+            continue;
+        };
+
+        // walk every NPC of this tree there
+        let mut found = 0;
+        let mut arrived = 0;
+        for (owned, mut transform) in &mut npcs {
+            if trees.tree(owned) != node.tree {
+                continue;
+            }
+            found += 1;
+            if walk_towards(&mut transform, target, wander_speed, delta) {
+                arrived += 1;
+            }
+        }
+
+        if found == 0 {
+            warn!("No NPC to wander");
+            commands.entity(entity).insert(BehaviorFailure);
+        } else if arrived == found {
+            commands.entity(entity).insert(BehaviorSuccess);
+        }
+    }
+}
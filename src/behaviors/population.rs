@@ -0,0 +1,353 @@
+use super::{
+    blackboard::BlackboardWrite,
+    npc::{NPCBehavior, NPCTrees, SpawnOwned},
+};
+use crate::{random::RandomSeed, region::RegionQuery};
+use bevy::{prelude::*, reflect::TypeRegistry, utils::HashSet};
+use bevy_inspector_egui::{egui, prelude::*, reflect_inspector};
+use rhai::Dynamic;
+use serde::{Deserialize, Serialize};
+use simula_behavior::prelude::*;
+use simula_core::epath::EPathQueries;
+
+pub struct PopulationPlugin;
+
+impl Plugin for PopulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Population>()
+            .add_system(run)
+            .add_system(place.after(run))
+            .add_system(
+                removed
+                    .in_base_set(CoreSet::PostUpdate)
+                    .after(BehaviorSet::PostUpdate),
+            );
+    }
+}
+
+/// NPC behavior tree spawned by a `Population` node
+#[derive(Debug, Component)]
+pub struct PopulationMember {
+    pub population: Entity,
+    pub position: Vec3,
+    pub had_npc: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PopulationState {
+    pub members: Vec<Entity>,
+    /// Times when replacements can be spawned
+    pub respawns: Vec<f64>,
+    pub spawned: u32,
+    pub reported: Option<i64>,
+}
+
+#[derive(
+    Debug, Component, Reflect, FromReflect, Clone, Deserialize, Serialize, InspectorOptions, Default,
+)]
+#[reflect(InspectorOptions)]
+pub struct Population {
+    /// NPC behavior tree to run for each member, like "bht/u/spawn_fox.bht.ron"
+    pub tree: BehaviorPropStr,
+    pub count: BehaviorPropGeneric<i64>,
+    /// Seconds before a lost member is replaced
+    #[serde(default)]
+    pub respawn_delay: BehaviorPropGeneric<f64>,
+    /// Region to spawn in, instead of the center and radius
+    #[serde(default)]
+    pub region: BehaviorPropOption<BehaviorPropEPath>,
+    /// Center of the spawn area
+    #[serde(default)]
+    pub center: Vec3,
+    /// Radius of the spawn area
+    #[serde(default)]
+    pub radius: f32,
+    /// Blackboard entry the member count is written to
+    #[serde(default = "default_key")]
+    pub key: String,
+
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub state: PopulationState,
+}
+
+fn default_key() -> String {
+    "population".into()
+}
+
+impl BehaviorSpec for Population {
+    const TYPE: BehaviorType = BehaviorType::Action;
+    const NAME: &'static str = "Population";
+    const ICON: &'static str = "👪";
+    const DESC: &'static str = "Keep a number of NPC trees running, replacing the ones lost";
+}
+
+impl BehaviorUI for Population {
+    fn ui(
+        &mut self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) -> bool {
+        let mut changed = false;
+        changed |= behavior_ui!(self, tree, state, ui, type_registry);
+        changed |= behavior_ui!(self, count, state, ui, type_registry);
+        changed |= behavior_ui!(self, respawn_delay, state, ui, type_registry);
+        changed |= behavior_ui!(self, region, state, ui, type_registry);
+        ui.label("center");
+        changed |= reflect_inspector::ui_for_value(&mut self.center, ui, type_registry);
+        ui.label("radius");
+        changed |= reflect_inspector::ui_for_value(&mut self.radius, ui, type_registry);
+        ui.label("key");
+        changed |= reflect_inspector::ui_for_value(&mut self.key, ui, type_registry);
+        changed
+    }
+
+    fn ui_readonly(
+        &self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) {
+        behavior_ui_readonly!(self, tree, state, ui, type_registry);
+        behavior_ui_readonly!(self, count, state, ui, type_registry);
+        behavior_ui_readonly!(self, respawn_delay, state, ui, type_registry);
+        behavior_ui_readonly!(self, region, state, ui, type_registry);
+
+        ui.label(
+            egui::RichText::new(format!(
+                "members: {} respawning: {}",
+                self.state.members.len(),
+                self.state.respawns.len()
+            ))
+            .small(),
+        );
+    }
+}
+
+// Spread spawn points over the area without clustering, golden angle spiral
+pub fn spawn_point(center: Vec3, radius: f32, index: u32) -> Vec3 {
+    const GOLDEN_ANGLE: f32 = 2.399_963;
+    let angle = index as f32 * GOLDEN_ANGLE;
+    let distance = radius * ((index as f32 * 0.618_034).fract()).sqrt();
+    center + Vec3::new(angle.cos() * distance, 0.0, angle.sin() * distance)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    random: Res<RandomSeed>,
+    mut populations: Query<
+        (
+            Entity,
+            &mut Population,
+            &BehaviorNode,
+            Option<&BehaviorStarted>,
+        ),
+        BehaviorRunQuery,
+    >,
+    mut members: Query<&mut PopulationMember>,
+    npcs: Query<&SpawnOwned, Without<Parent>>,
+    trees: NPCTrees,
+    mut writes: EventWriter<BlackboardWrite>,
+    regions: RegionQuery,
+    equeries: EPathQueries,
+    mut scripts: ScriptQueries,
+) {
+    if populations.is_empty() {
+        return;
+    }
+    let now = time.elapsed_seconds_f64();
+    // trees with at least one NPC
+    let alive: HashSet<Entity> = npcs.iter().filter_map(|owned| trees.tree(owned)).collect();
+    for (entity, mut population, node, started) in &mut populations {
+        let population = population.as_mut();
+
+        if started.is_some() {
+            // reset eval properties
+            population.tree.value = BehaviorPropValue::None;
+            population.count.value = BehaviorPropValue::None;
+            population.respawn_delay.value = BehaviorPropValue::None;
+            if let Some(region) = &mut *population.region {
+                region.value = BehaviorPropValue::None;
+            }
+            for member in population.state.members.drain(..) {
+                commands.entity(member).despawn_recursive();
+            }
+            population.state = PopulationState::default();
+        }
+
+        // keep working on eval properties
+        if let BehaviorPropValue::None = population.tree.value {
+            let result = population.tree.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        if let BehaviorPropValue::None = population.count.value {
+            let result = population.count.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        if let BehaviorPropValue::None = population.respawn_delay.value {
+            let result = population.respawn_delay.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        if let Some(prop) = &mut population.region.as_mut() {
+            if let BehaviorPropValue::None = prop.value {
+                let result = prop.fetch(node, &mut scripts);
+                if let Some(Err(err)) = result {
+                    error!("Script errored: {:?}", err);
+                    commands.entity(entity).insert(BehaviorFailure);
+                    continue;
+                }
+            }
+        }
+
+        let population_region = if let Some(prop) = &*population.region {
+            if let BehaviorPropValue::Some(value) = &prop.value {
+                Some(Some(value.clone()))
+            } else {
+                None
+            }
+        } else {
+            Some(None)
+        };
+        let (
+            BehaviorPropValue::Some(population_tree),
+            BehaviorPropValue::Some(population_count),
+            BehaviorPropValue::Some(population_delay),
+            Some(population_region),
+        ) = (
+            &population.tree.value,
+            &population.count.value,
+            &population.respawn_delay.value,
+            population_region,
+        )
+        else {
+            continue;
+        };
+        let population_tree = population_tree.to_string();
+        let population_count = (*population_count).max(0) as usize;
+        let population_delay = *population_delay;
+        let region = population_region.and_then(|path| regions.select(&path, &equeries));
+
+        // drop members whose tree is gone or whose NPCs all despawned
+        let state = &mut population.state;
+        let mut lost = 0;
+        state.members.retain(|member| {
+            let Ok(mut membership) = members.get_mut(*member) else {
+                lost += 1;
+                return false;
+            };
+            if alive.contains(member) {
+                membership.had_npc = true;
+                true
+            } else if membership.had_npc {
+                commands.entity(*member).despawn_recursive();
+                lost += 1;
+                false
+            } else {
+                true
+            }
+        });
+        for _ in 0..lost {
+            state.respawns.push(now + population_delay);
+        }
+
+        // too many members, the target count went down
+        while state.members.len() > population_count {
+            if let Some(member) = state.members.pop() {
+                commands.entity(member).despawn_recursive();
+            }
+        }
+        let waiting = population_count - state.members.len();
+        state.respawns.truncate(waiting);
+
+        // fill up right away, replacements wait for their delay
+        let ready = state.respawns.iter().filter(|at| **at <= now).count();
+        state.respawns.retain(|at| *at > now);
+        let missing = waiting - state.respawns.len() - ready;
+        for _ in 0..(ready + missing) {
+            let position = match region {
+                Some((region, transform)) => {
+                    let seed = random.mix(entity.to_bits().wrapping_add(state.spawned as u64));
+                    region.random_point(transform, seed)
+                }
+                None => spawn_point(population.center, population.radius, state.spawned),
+            };
+            state.spawned += 1;
+            let handle: Handle<BehaviorAsset<NPCBehavior>> =
+                asset_server.load(population_tree.as_str());
+            let member = commands
+                .spawn((
+                    Name::new(format!("BHT: {} #{}", population_tree, state.spawned)),
+                    handle,
+                    BehaviorTree::<NPCBehavior>::default(),
+                    BehaviorTreeReset::<NPCBehavior>::default(),
+                    PopulationMember {
+                        population: entity,
+                        position,
+                        had_npc: false,
+                    },
+                ))
+                .id();
+            state.members.push(member);
+        }
+
+        // report the count
+        let count = state.members.len() as i64;
+        if state.reported != Some(count) {
+            state.reported = Some(count);
+            if let Some(tree) = node.tree {
+                writes.send(BlackboardWrite::new(
+                    tree,
+                    population.key.clone(),
+                    Dynamic::from(count),
+                ));
+            }
+        }
+    }
+}
+
+// Move NPCs spawned by member trees to the member spawn point
+fn place(
+    mut npcs: Query<(&SpawnOwned, &mut Transform), (Added<SpawnOwned>, Without<Parent>)>,
+    members: Query<&PopulationMember>,
+    trees: NPCTrees,
+) {
+    for (owned, mut transform) in &mut npcs {
+        let Some(member) = trees.tree(owned).and_then(|tree| members.get(tree).ok()) else {
+            continue;
+        };
+        transform.translation = member.position;
+    }
+}
+
+// Despawn the member trees when the population node is removed
+fn removed(
+    mut removals: RemovedComponents<Population>,
+    mut commands: Commands,
+    members: Query<(Entity, &PopulationMember)>,
+) {
+    for entity in &mut removals {
+        for (member_entity, member) in &members {
+            if member.population == entity {
+                commands.entity(member_entity).despawn_recursive();
+            }
+        }
+    }
+}
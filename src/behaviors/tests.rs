@@ -0,0 +1,957 @@
+use super::{
+    bioma::BiomaBehavior,
+    blackboard::BlackboardWrite,
+    invoke::InvokedTree,
+    npc::{schedule::DailySchedule, NPCBehavior, SpawnOwned},
+};
+use crate::{
+    clock::{SimClock, SECONDS_PER_HOUR},
+    random::RandomSeed,
+    region::{Region, RegionShape},
+    smart_object::{SmartObject, SmartSlot},
+    snapshot::{SnapshotPlugin, SnapshotRestore, SnapshotSave},
+    stimulus::Stimulus,
+    testing::{temp_asset, NodeState, TestApp},
+};
+use bevy::prelude::*;
+use rhai::Dynamic;
+use simula_behavior::prelude::*;
+
+#[test]
+fn spawn_test_succeeds() {
+    let mut test = TestApp::new();
+    let tree = test.spawn_tree::<NPCBehavior>("bht/u/spawn_test.bht.ron");
+
+    let frames = test.run_until(120, |test| {
+        test.node_state(tree, "➡ Sequencer") == NodeState::Success
+    });
+    assert!(frames.is_some(), "spawn_test did not succeed in 120 frames");
+    assert_eq!(test.count::<SpawnOwned>(), 2);
+}
+
+#[test]
+fn anim_fails_with_invalid_target() {
+    let mut test = TestApp::new();
+    let tree = test.spawn_tree_str::<NPCBehavior>(
+        "anim_invalid_target",
+        r#"("🏋 Anim", Anim((
+    asset: (
+        prop: Value("models/fox/Fox.glb#Animation0"),
+    ),
+    target: (
+        prop: Value("/Nobody"),
+    ),
+)), [], (
+    pos: (0.0, 0.0),
+))"#,
+    );
+
+    let frames = test.run_until(120, |test| {
+        test.node_state(tree, "🏋 Anim") == NodeState::Failure
+    });
+    assert!(frames.is_some(), "anim did not fail in 120 frames");
+}
+
+#[test]
+fn guard_reads_blackboard() {
+    let mut test = TestApp::new();
+    let tree = test.spawn_tree_str::<NPCBehavior>(
+        "guard_blackboard",
+        r#"("Ready?", Guard((
+    condition: (
+        prop: Eval(
+            eval: "blackboard.ready == true",
+        ),
+    ),
+)), [
+    ("Go", Debug((
+        message: (
+            prop: Value("go"),
+        ),
+        fail: (
+            prop: Value(false),
+        ),
+    )), [], (
+        pos: (0.0, 200.0),
+    )),
+], (
+    pos: (0.0, 0.0),
+))"#,
+    );
+    test.send(BlackboardWrite::new(tree, "ready", Dynamic::from(true)));
+
+    let frames = test.run_until(120, |test| {
+        test.node_state(tree, "Ready?") == NodeState::Success
+    });
+    assert!(frames.is_some(), "guard did not succeed in 120 frames");
+    assert_eq!(test.node_state(tree, "Go"), NodeState::Success);
+    let ready = test
+        .blackboard_value(tree, "ready")
+        .and_then(|v| v.try_cast::<bool>());
+    assert_eq!(ready, Some(true));
+}
+
+#[test]
+fn utility_switches_on_better_score() {
+    let mut test = TestApp::new();
+    let tree = test.spawn_tree_str::<NPCBehavior>(
+        "utility_switch",
+        r#"("⚖ Utility", UtilitySelector((
+    scores: [
+        (
+            score: (
+                prop: Value(0.5),
+            ),
+            curve: Linear,
+        ),
+        (
+            score: (
+                prop: Eval(
+                    eval: "blackboard.urge",
+                ),
+            ),
+            curve: Linear,
+        ),
+    ],
+    interval: (
+        prop: Value(0.1),
+    ),
+    hysteresis: (
+        prop: Value(0.2),
+    ),
+)), [
+    ("Stay", Wait((
+        duration: (
+            prop: Value(30.0),
+        ),
+        fail: (
+            prop: Value(false),
+        ),
+    )), [], (
+        pos: (0.0, 200.0),
+    )),
+    ("Switch", Wait((
+        duration: (
+            prop: Value(30.0),
+        ),
+        fail: (
+            prop: Value(false),
+        ),
+    )), [], (
+        pos: (200.0, 200.0),
+    )),
+], (
+    pos: (0.0, 0.0),
+))"#,
+    );
+    test.send(BlackboardWrite::new(tree, "urge", Dynamic::from(0.0)));
+
+    let frames = test.run_until(120, |test| {
+        test.node_state(tree, "Stay") == NodeState::Running
+    });
+    assert!(frames.is_some(), "utility did not start Stay in 120 frames");
+
+    // within the hysteresis, keeps running Stay
+    test.send(BlackboardWrite::new(tree, "urge", Dynamic::from(0.6)));
+    test.step(30);
+    assert_eq!(test.node_state(tree, "Stay"), NodeState::Running);
+
+    test.send(BlackboardWrite::new(tree, "urge", Dynamic::from(0.9)));
+    let frames = test.run_until(60, |test| {
+        test.node_state(tree, "Switch") == NodeState::Running
+    });
+    assert!(
+        frames.is_some(),
+        "utility did not switch to Switch in 60 frames"
+    );
+    assert_eq!(test.node_state(tree, "Stay"), NodeState::Idle);
+    assert_eq!(test.node_state(tree, "⚖ Utility"), NodeState::Running);
+}
+
+// states of a random sequencer and its children, frame by frame once the tree is built
+fn random_sequencer_states(seed: u64) -> Vec<Vec<NodeState>> {
+    let mut test = TestApp::new();
+    test.app.insert_resource(RandomSeed(seed));
+    let tree = test.spawn_tree_str::<NPCBehavior>(
+        "random_sequencer",
+        r#"("🎲 Random", Sequencer((
+    random: true,
+)), [
+    ("A", Debug((
+        message: (
+            prop: Value("A"),
+        ),
+        fail: (
+            prop: Value(false),
+        ),
+    )), [], (
+        pos: (0.0, 200.0),
+    )),
+    ("B", Debug((
+        message: (
+            prop: Value("B"),
+        ),
+        fail: (
+            prop: Value(false),
+        ),
+    )), [], (
+        pos: (200.0, 200.0),
+    )),
+    ("C", Debug((
+        message: (
+            prop: Value("C"),
+        ),
+        fail: (
+            prop: Value(false),
+        ),
+    )), [], (
+        pos: (400.0, 200.0),
+    )),
+    ("D", Debug((
+        message: (
+            prop: Value("D"),
+        ),
+        fail: (
+            prop: Value(false),
+        ),
+    )), [], (
+        pos: (600.0, 200.0),
+    )),
+    ("E", Debug((
+        message: (
+            prop: Value("E"),
+        ),
+        fail: (
+            prop: Value(false),
+        ),
+    )), [], (
+        pos: (800.0, 200.0),
+    )),
+], (
+    pos: (0.0, 0.0),
+))"#,
+    );
+
+    let names = ["🎲 Random", "A", "B", "C", "D", "E"];
+    let mut states = vec![];
+    let frames = test.run_until(120, |test| {
+        let frame: Vec<NodeState> = names
+            .iter()
+            .map(|name| test.node_state(tree, name))
+            .collect();
+        let done = frame[0] == NodeState::Success;
+        if frame[0] != NodeState::Missing {
+            states.push(frame);
+        }
+        done
+    });
+    assert!(
+        frames.is_some(),
+        "random sequencer did not succeed in 120 frames"
+    );
+    states
+}
+
+#[test]
+fn same_seed_gives_same_run() {
+    let first = random_sequencer_states(42);
+    let second = random_sequencer_states(42);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn restore_restarts_running_leaf() {
+    let path = std::env::temp_dir()
+        .join("autonpcs-tests")
+        .join("restore_leaf.snapshot.ron");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let mut test = TestApp::new();
+    test.app.add_plugin(SnapshotPlugin {
+        path: path.to_string_lossy().into(),
+        restore: false,
+        save_on_exit: false,
+    });
+    let tree = test.spawn_tree_str::<NPCBehavior>(
+        "restore_leaf",
+        r#"("➡ Sequencer", Sequencer((
+    random: false,
+)), [
+    ("First", Debug((
+        message: (
+            prop: Value("first"),
+        ),
+        fail: (
+            prop: Value(false),
+        ),
+    )), [], (
+        pos: (0.0, 200.0),
+    )),
+    ("Long", Wait((
+        duration: (
+            prop: Value(1.0),
+        ),
+        fail: (
+            prop: Value(false),
+        ),
+    )), [], (
+        pos: (200.0, 200.0),
+    )),
+], (
+    pos: (0.0, 0.0),
+))"#,
+    );
+
+    let frames = test.run_until(120, |test| {
+        test.node_state(tree, "Long") == NodeState::Running
+    });
+    assert!(frames.is_some(), "Long did not start in 120 frames");
+    // halfway through the wait
+    test.step(30);
+    test.send(SnapshotSave);
+    test.step(1);
+    test.send(SnapshotRestore);
+    test.step(1);
+
+    let restored = test
+        .app
+        .world
+        .query_filtered::<Entity, With<BehaviorTree<NPCBehavior>>>()
+        .single(&test.app.world);
+    assert_ne!(restored, tree);
+    let frames = test.run_until(120, |test| {
+        test.node_state(restored, "Long") == NodeState::Running
+    });
+    assert!(frames.is_some(), "Long was not restored in 120 frames");
+    assert_eq!(test.node_state(restored, "First"), NodeState::Success);
+
+    // the whole second again, not the half that was left
+    let frames = test.run_until(120, |test| {
+        test.node_state(restored, "➡ Sequencer") == NodeState::Success
+    });
+    assert!(
+        frames.map_or(false, |frames| frames > 45),
+        "Long did not start over, finished after {:?} frames",
+        frames
+    );
+}
+
+// subtree the schedule node is running
+fn scheduled_subtree(test: &mut TestApp) -> Option<String> {
+    test.app
+        .world
+        .query::<&DailySchedule>()
+        .iter(&test.app.world)
+        .find_map(|schedule| schedule.state.subtree.clone())
+}
+
+#[test]
+fn restore_restarts_parallel_leaves() {
+    let path = std::env::temp_dir()
+        .join("autonpcs-tests")
+        .join("restore_parallel.snapshot.ron");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let mut test = TestApp::new();
+    test.app.add_plugin(SnapshotPlugin {
+        path: path.to_string_lossy().into(),
+        restore: false,
+        save_on_exit: false,
+    });
+    let tree = test.spawn_tree_str::<NPCBehavior>(
+        "restore_parallel",
+        r#"("⇉ All", All(()), [
+    ("Short", Wait((
+        duration: (
+            prop: Value(0.1),
+        ),
+        fail: (
+            prop: Value(false),
+        ),
+    )), [], (
+        pos: (0.0, 200.0),
+    )),
+    ("Long A", Wait((
+        duration: (
+            prop: Value(1.0),
+        ),
+        fail: (
+            prop: Value(false),
+        ),
+    )), [], (
+        pos: (200.0, 200.0),
+    )),
+    ("Long B", Wait((
+        duration: (
+            prop: Value(1.0),
+        ),
+        fail: (
+            prop: Value(false),
+        ),
+    )), [], (
+        pos: (400.0, 200.0),
+    )),
+], (
+    pos: (0.0, 0.0),
+))"#,
+    );
+
+    let frames = test.run_until(120, |test| {
+        test.node_state(tree, "Short") == NodeState::Success
+            && test.node_state(tree, "Long A") == NodeState::Running
+    });
+    assert!(frames.is_some(), "Long A did not start in 120 frames");
+    test.step(10);
+    test.send(SnapshotSave);
+    test.step(1);
+    test.send(SnapshotRestore);
+    test.step(1);
+
+    let restored = test
+        .app
+        .world
+        .query_filtered::<Entity, With<BehaviorTree<NPCBehavior>>>()
+        .single(&test.app.world);
+    assert_ne!(restored, tree);
+    let frames = test.run_until(120, |test| {
+        test.node_state(restored, "⇉ All") == NodeState::Running
+    });
+    assert!(frames.is_some(), "All was not restored in 120 frames");
+
+    // every running wait is picked up again, none is left running without a cursor
+    let frames = test.run_until(240, |test| {
+        test.node_state(restored, "⇉ All") == NodeState::Success
+    });
+    assert!(frames.is_some(), "All did not complete in 240 frames");
+    for name in ["Short", "Long A", "Long B"] {
+        assert_eq!(test.node_state(restored, name), NodeState::Success);
+    }
+}
+
+#[test]
+fn schedule_switches_at_entry_boundary() {
+    let routine = |name: &str| {
+        temp_asset(
+            &format!("{}.bht.ron", name),
+            &format!(
+                r#"("{}", Wait((
+    duration: (
+        prop: Value(3600.0),
+    ),
+    fail: (
+        prop: Value(false),
+    ),
+)), [], (
+    pos: (0.0, 0.0),
+))"#,
+                name
+            ),
+        )
+    };
+    let (morning, afternoon) = (routine("schedule_morning"), routine("schedule_afternoon"));
+    let schedule = temp_asset(
+        "boundary.schedule.ron",
+        &format!(
+            r#"(
+    entries: [
+        (name: "morning", start: 6.0, end: 12.0, subtree: "{}"),
+        (name: "afternoon", start: 12.0, end: 18.0, subtree: "{}"),
+    ],
+)"#,
+            morning, afternoon
+        ),
+    );
+
+    let mut test = TestApp::new();
+    // the test moves the clock by hand
+    test.app.insert_resource(SimClock {
+        elapsed: 11.9 * SECONDS_PER_HOUR,
+        time_scale: 0.0,
+        paused: true,
+    });
+    let tree = test.spawn_tree_str::<NPCBehavior>(
+        "schedule_boundary",
+        &format!(
+            r#"("📅 Schedule", DailySchedule((
+    asset: (
+        prop: Value("{}"),
+    ),
+    policy: Interrupt,
+)), [
+    ("Routine", Subtree((
+        asset: "{}",
+        unload: true,
+    )), [], (
+        pos: (200.0, 0.0),
+    )),
+], (
+    pos: (0.0, 0.0),
+))"#,
+            schedule, morning
+        ),
+    );
+
+    let frames = test.run_until(120, |test| {
+        scheduled_subtree(test).as_deref() == Some(morning.as_str())
+    });
+    assert!(
+        frames.is_some(),
+        "morning entry did not start in 120 frames"
+    );
+    test.step(10);
+    assert_eq!(scheduled_subtree(test), Some(morning.clone()));
+
+    // crossing noon interrupts the morning subtree
+    test.app.world.resource_mut::<SimClock>().elapsed = 12.1 * SECONDS_PER_HOUR;
+    let frames = test.run_until(60, |test| {
+        scheduled_subtree(test).as_deref() == Some(afternoon.as_str())
+    });
+    assert!(
+        frames.is_some(),
+        "afternoon entry did not take over in 60 frames"
+    );
+    assert_eq!(test.node_state(tree, "📅 Schedule"), NodeState::Running);
+}
+
+// tree succeeding right away, for plan steps and invoked trees
+fn succeed(name: &str) -> String {
+    temp_asset(
+        &format!("{}.bht.ron", name),
+        &format!(
+            r#"("{}", Debug((
+    message: (
+        prop: Value("{}"),
+    ),
+    fail: (
+        prop: Value(false),
+    ),
+)), [], (
+    pos: (0.0, 0.0),
+))"#,
+            name, name
+        ),
+    )
+}
+
+#[test]
+fn plan_runs_cheapest_steps() {
+    let goap = temp_asset(
+        "plan_test.goap.ron",
+        &format!(
+            r#"(
+    goals: {{
+        "be fed": {{
+            "fed": true,
+        }},
+    }},
+    actions: [
+        (
+            name: "buy food",
+            cost: 10.0,
+            effects: {{
+                "fed": true,
+            }},
+            subtree: "{}",
+        ),
+        (
+            name: "find food",
+            cost: 2.0,
+            effects: {{
+                "knows_food": true,
+            }},
+            subtree: "{}",
+        ),
+        (
+            name: "eat",
+            preconditions: {{
+                "knows_food": true,
+            }},
+            effects: {{
+                "fed": true,
+            }},
+            subtree: "{}",
+        ),
+    ],
+)"#,
+            succeed("plan_buy"),
+            succeed("plan_find"),
+            succeed("plan_eat")
+        ),
+    );
+    let mut test = TestApp::new();
+    let tree = test.spawn_tree_str::<NPCBehavior>(
+        "plan",
+        &format!(
+            r#"("🗺 Plan", Plan((
+    asset: (
+        prop: Value("{}"),
+    ),
+    goal: (
+        prop: Value("be fed"),
+    ),
+)), [
+    ("Plan step", Subtree((
+        asset: "{}",
+        unload: true,
+    )), [], (
+        pos: (200.0, 0.0),
+    )),
+], (
+    pos: (0.0, 0.0),
+))"#,
+            goap,
+            succeed("plan_find")
+        ),
+    );
+
+    let frames = test.run_until(240, |test| {
+        test.node_state(tree, "🗺 Plan") == NodeState::Success
+    });
+    assert!(frames.is_some(), "plan did not succeed in 240 frames");
+    // step effects land on the blackboard, the cheap way went through finding food
+    for fact in ["knows_food", "fed"] {
+        let value = test
+            .blackboard_value(tree, fact)
+            .and_then(|v| v.try_cast::<bool>());
+        assert_eq!(value, Some(true), "{} is not set", fact);
+    }
+}
+
+// NPCs at the root of the world, attached parts left out
+fn npc_roots(test: &mut TestApp) -> Vec<Entity> {
+    test.app
+        .world
+        .query_filtered::<Entity, (With<SpawnOwned>, Without<Parent>)>()
+        .iter(&test.app.world)
+        .collect()
+}
+
+#[test]
+fn population_respawns_after_delay() {
+    let mut test = TestApp::new();
+    let tree = test.spawn_tree_str::<BiomaBehavior>(
+        "population",
+        r#"("👪 Foxes", Population((
+    tree: (
+        prop: Value("bht/u/spawn_fox.bht.ron"),
+    ),
+    count: (
+        prop: Value(2),
+    ),
+    respawn_delay: (
+        prop: Value(1.0),
+    ),
+    center: (0.0, 0.0, 0.0),
+    radius: 3.0,
+    key: "foxes",
+)), [], (
+    pos: (0.0, 0.0),
+))"#,
+    );
+
+    let frames = test.run_until(240, |test| npc_roots(test).len() == 2);
+    assert!(frames.is_some(), "population did not fill up in 240 frames");
+
+    let lost = npc_roots(&mut test)[0];
+    test.app.world.entity_mut(lost).despawn_recursive();
+    test.step(1);
+    assert_eq!(npc_roots(&mut test).len(), 1);
+
+    // the replacement waits for the delay, about 60 frames
+    let frames = test.run_until(240, |test| npc_roots(test).len() == 2);
+    assert!(
+        frames.map_or(false, |frames| frames > 45),
+        "replacement did not wait for the delay, came after {:?} frames",
+        frames
+    );
+    let count = test
+        .blackboard_value(tree, "foxes")
+        .and_then(|v| v.try_cast::<i64>());
+    assert_eq!(count, Some(2));
+}
+
+#[test]
+fn region_contains_and_random_point() {
+    let mut test = TestApp::new();
+    let region = Region {
+        shape: RegionShape::Box {
+            size: Vec2::new(4.0, 2.0),
+        },
+        color: Color::WHITE,
+    };
+    test.app.world.spawn((
+        Name::new("market"),
+        region.clone(),
+        TransformBundle::from_transform(Transform::from_xyz(10.0, 0.0, 0.0)),
+    ));
+    let tree = test.spawn_tree_str::<NPCBehavior>(
+        "region",
+        r##"("➡ Sequencer", Sequencer((
+    random: false,
+)), [
+    ("Settle", Wait((
+        duration: (
+            prop: Value(0.1),
+        ),
+        fail: (
+            prop: Value(false),
+        ),
+    )), [], (
+        pos: (-200.0, 200.0),
+    )),
+    ("Inside?", Guard((
+        condition: (
+            prop: Eval(
+                eval: "regions.market.contains(#{x: 11.5, y: 0.0, z: 0.5}) && !regions.market.contains(#{x: 0.0, y: 0.0, z: 0.0})",
+            ),
+        ),
+    )), [
+        ("Pick", Debug((
+            message: (
+                prop: Eval(
+                    eval: "blackboard.point = regions.market.random_point(7); \"picked\"",
+                ),
+            ),
+            fail: (
+                prop: Value(false),
+            ),
+        )), [], (
+            pos: (0.0, 400.0),
+        )),
+    ], (
+        pos: (0.0, 200.0),
+    )),
+], (
+    pos: (0.0, 0.0),
+))"##,
+    );
+
+    let frames = test.run_until(120, |test| {
+        test.node_state(tree, "➡ Sequencer") == NodeState::Success
+    });
+    assert!(
+        frames.is_some(),
+        "region checks did not succeed in 120 frames"
+    );
+    let point = test
+        .blackboard_value(tree, "point")
+        .and_then(|v| v.try_cast::<rhai::Map>())
+        .expect("no random point on the blackboard");
+    let coordinate = |key: &str| point[key].clone().try_cast::<f64>().unwrap() as f32;
+    let point = Vec3::new(coordinate("x"), coordinate("y"), coordinate("z"));
+    let transform = GlobalTransform::from(Transform::from_xyz(10.0, 0.0, 0.0));
+    assert!(
+        region.contains(&transform, point),
+        "{:?} is outside the region",
+        point
+    );
+}
+
+#[test]
+fn invoke_passes_inputs_and_outputs() {
+    let invoked = temp_asset(
+        "invoke_double.bht.ron",
+        r#"("Double", Debug((
+    message: (
+        prop: Eval(
+            eval: "blackboard.doubled = blackboard.value * 2; \"doubled\"",
+        ),
+    ),
+    fail: (
+        prop: Value(false),
+    ),
+)), [], (
+    pos: (0.0, 0.0),
+))"#,
+    );
+    let mut test = TestApp::new();
+    let tree = test.spawn_tree_str::<BiomaBehavior>(
+        "invoke",
+        &format!(
+            r#"("📨 Invoke", Invoke((
+    asset: (
+        prop: Value("{}"),
+    ),
+    inputs: [
+        (
+            name: "value",
+            value: Int((
+                prop: Value(21),
+            )),
+        ),
+    ],
+    outputs: [
+        (
+            name: "doubled",
+            to: Some("answer"),
+        ),
+    ],
+)), [], (
+    pos: (0.0, 0.0),
+))"#,
+            invoked
+        ),
+    );
+
+    let frames = test.run_until(240, |test| {
+        test.node_state(tree, "📨 Invoke") == NodeState::Success
+    });
+    assert!(frames.is_some(), "invoke did not succeed in 240 frames");
+    // outputs are copied through a blackboard event
+    let frames = test.run_until(10, |test| {
+        test.blackboard_value(tree, "answer")
+            .and_then(|v| v.try_cast::<i64>())
+            == Some(42)
+    });
+    assert!(frames.is_some(), "output did not reach the blackboard");
+    // the invoked tree is gone once its outputs are copied
+    assert_eq!(test.count::<InvokedTree>(), 0);
+}
+
+#[test]
+fn use_smart_object_reserves_and_releases() {
+    let sit = temp_asset(
+        "smart_sit.bht.ron",
+        r#"("Sitting", Wait((
+    duration: (
+        prop: Value(1.0),
+    ),
+    fail: (
+        prop: Value(false),
+    ),
+)), [], (
+    pos: (0.0, 0.0),
+))"#,
+    );
+    let mut test = TestApp::new();
+    let bench = test
+        .app
+        .world
+        .spawn((
+            Name::new("Bench"),
+            SmartObject {
+                interaction: "sit".into(),
+                adverts: vec![],
+                subtree: sit.clone(),
+                slots: vec![SmartSlot::new(Vec3::ZERO)],
+            },
+            TransformBundle::from_transform(Transform::from_xyz(1.0, 0.0, 0.0)),
+        ))
+        .id();
+    let tree = test.spawn_tree_str::<NPCBehavior>(
+        "use_smart_object",
+        &format!(
+            r#"("➡ Sequencer", Sequencer((
+    random: false,
+)), [
+    ("🏄 Spawn", Spawn((
+        asset: (
+            prop: Value("models/fox/Fox.glb#Scene0"),
+        ),
+        name: (
+            prop: Value("🦊 Fox"),
+        ),
+    )), [], (
+        pos: (0.0, 200.0),
+    )),
+    ("🪑 Sit", UseSmartObject((
+        interaction: (
+            prop: Value("sit"),
+        ),
+    )), [
+        ("Bench subtree", Subtree((
+            asset: "{}",
+            unload: true,
+        )), [], (
+            pos: (200.0, 400.0),
+        )),
+    ], (
+        pos: (200.0, 200.0),
+    )),
+], (
+    pos: (0.0, 0.0),
+))"#,
+            sit
+        ),
+    );
+    let reserved = |test: &TestApp| {
+        test.app.world.get::<SmartObject>(bench).unwrap().slots[0]
+            .reserved
+            .is_some()
+    };
+
+    let frames = test.run_until(240, |test| {
+        test.node_state(tree, "🪑 Sit") == NodeState::Running && reserved(test)
+    });
+    assert!(frames.is_some(), "bench was not reserved in 240 frames");
+
+    let frames = test.run_until(240, |test| {
+        test.node_state(tree, "➡ Sequencer") == NodeState::Success
+    });
+    assert!(frames.is_some(), "sitting did not complete in 240 frames");
+    assert!(!reserved(&test), "bench is still reserved");
+}
+
+#[test]
+fn on_stimulus_interrupts_child() {
+    let mut test = TestApp::new();
+    let tree = test.spawn_tree_str::<NPCBehavior>(
+        "on_stimulus",
+        r#"("➡ Sequencer", Sequencer((
+    random: false,
+)), [
+    ("🏄 Spawn", Spawn((
+        asset: (
+            prop: Value("models/fox/Fox.glb#Scene0"),
+        ),
+        name: (
+            prop: Value("🦊 Fox"),
+        ),
+    )), [], (
+        pos: (0.0, 200.0),
+    )),
+    ("⚡ On noise", OnStimulus((
+        kind: (
+            prop: Value("noise"),
+        ),
+    )), [
+        ("Busy", Wait((
+            duration: (
+                prop: Value(30.0),
+            ),
+            fail: (
+                prop: Value(false),
+            ),
+        )), [], (
+            pos: (200.0, 400.0),
+        )),
+        ("React", Debug((
+            message: (
+                prop: Value("what was that?"),
+            ),
+            fail: (
+                prop: Value(false),
+            ),
+        )), [], (
+            pos: (400.0, 400.0),
+        )),
+    ], (
+        pos: (200.0, 200.0),
+    )),
+], (
+    pos: (0.0, 0.0),
+))"#,
+    );
+
+    let frames = test.run_until(240, |test| {
+        test.node_state(tree, "Busy") == NodeState::Running
+    });
+    assert!(frames.is_some(), "Busy did not start in 240 frames");
+
+    test.send(Stimulus::new("noise", Vec3::ZERO, 5.0, 1.0));
+    let frames = test.run_until(60, |test| {
+        test.node_state(tree, "⚡ On noise") == NodeState::Success
+    });
+    assert!(frames.is_some(), "stimulus was not reacted to in 60 frames");
+    assert_eq!(test.node_state(tree, "React"), NodeState::Success);
+    assert_eq!(test.node_state(tree, "Busy"), NodeState::Idle);
+    assert!(test.blackboard_value(tree, "stimulus").is_some());
+}
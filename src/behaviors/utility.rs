@@ -0,0 +1,334 @@
+use super::abort::SubtreeAbort;
+use bevy::{prelude::*, reflect::TypeRegistry};
+use bevy_inspector_egui::{egui, prelude::*, reflect_inspector};
+use serde::{Deserialize, Serialize};
+use simula_behavior::prelude::*;
+
+pub struct UtilityPlugin;
+
+impl Plugin for UtilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<UtilitySelector>()
+            .register_type::<UtilityScore>()
+            .register_type::<UtilityCurve>()
+            .add_system(run)
+            .add_system(rescore);
+    }
+}
+
+/// Response curve applied to a raw score, inputs are clamped to [0, 1]
+#[derive(Debug, Clone, Default, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+pub enum UtilityCurve {
+    #[default]
+    Linear,
+    Inverse,
+    Quadratic,
+    Step(f64),
+    Logistic {
+        steepness: f64,
+        midpoint: f64,
+    },
+}
+
+impl UtilityCurve {
+    pub fn eval(&self, x: f64) -> f64 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            UtilityCurve::Linear => x,
+            UtilityCurve::Inverse => 1.0 - x,
+            UtilityCurve::Quadratic => x * x,
+            UtilityCurve::Step(threshold) => {
+                if x >= *threshold {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            UtilityCurve::Logistic {
+                steepness,
+                midpoint,
+            } => 1.0 / (1.0 + (-steepness * (x - midpoint)).exp()),
+        }
+    }
+}
+
+/// Score of one child of a `UtilitySelector`
+#[derive(Debug, Clone, Default, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct UtilityScore {
+    pub score: BehaviorPropGeneric<f64>,
+    #[serde(default)]
+    pub curve: UtilityCurve,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UtilityState {
+    pub current: Option<usize>,
+    pub values: Vec<f64>,
+    pub scored_at: Option<f64>,
+}
+
+#[derive(
+    Debug, Component, Reflect, FromReflect, Clone, Deserialize, Serialize, InspectorOptions, Default,
+)]
+#[reflect(InspectorOptions)]
+pub struct UtilitySelector {
+    /// One score per child, in the same order
+    pub scores: Vec<UtilityScore>,
+    /// Seconds between re-scoring, zero scores only when starting
+    #[serde(default)]
+    pub interval: BehaviorPropGeneric<f64>,
+    /// How much better another child must score to switch to it
+    #[serde(default)]
+    pub hysteresis: BehaviorPropGeneric<f64>,
+
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub state: UtilityState,
+}
+
+impl BehaviorSpec for UtilitySelector {
+    const TYPE: BehaviorType = BehaviorType::Composite;
+    const NAME: &'static str = "UtilitySelector";
+    const ICON: &'static str = "⚖";
+    const DESC: &'static str = "Run the child with the best score";
+}
+
+impl BehaviorUI for UtilitySelector {
+    fn ui(
+        &mut self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) -> bool {
+        let mut changed = false;
+        changed |= behavior_ui!(self, interval, state, ui, type_registry);
+        changed |= behavior_ui!(self, hysteresis, state, ui, type_registry);
+        ui.label("scores");
+        changed |= reflect_inspector::ui_for_value(&mut self.scores, ui, type_registry);
+        changed
+    }
+
+    fn ui_readonly(
+        &self,
+        _label: Option<&str>,
+        state: Option<protocol::BehaviorState>,
+        ui: &mut bevy_inspector_egui::egui::Ui,
+        type_registry: &TypeRegistry,
+    ) {
+        behavior_ui_readonly!(self, interval, state, ui, type_registry);
+        behavior_ui_readonly!(self, hysteresis, state, ui, type_registry);
+
+        // show latest scores, the running child is marked
+        for (index, value) in self.state.values.iter().enumerate() {
+            let marker = if self.state.current == Some(index) {
+                "▶"
+            } else {
+                " "
+            };
+            ui.label(egui::RichText::new(format!("{} [{}]: {:.3}", marker, index, value)).small());
+        }
+    }
+}
+
+pub fn run(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut selectors: Query<
+        (
+            Entity,
+            &mut UtilitySelector,
+            &BehaviorNode,
+            &BehaviorChildren,
+            Option<&BehaviorStarted>,
+        ),
+        BehaviorRunQuery,
+    >,
+    nodes: Query<(Option<&BehaviorSuccess>, Option<&BehaviorFailure>), With<BehaviorNode>>,
+    mut scripts: ScriptQueries,
+) {
+    let now = time.elapsed_seconds_f64();
+    for (entity, mut selector, node, children, started) in &mut selectors {
+        let selector = selector.as_mut();
+
+        if started.is_some() {
+            // reset eval properties
+            selector.interval.value = BehaviorPropValue::None;
+            selector.hysteresis.value = BehaviorPropValue::None;
+            for score in selector.scores.iter_mut() {
+                score.score.value = BehaviorPropValue::None;
+            }
+            selector.state = UtilityState::default();
+        }
+
+        if children.is_empty() || children.len() != selector.scores.len() {
+            error!("UtilitySelector needs one score per child");
+            commands.entity(entity).insert(BehaviorFailure);
+            continue;
+        }
+
+        // if the running child is done, complete with its result
+        if let Some(current) = selector.state.current {
+            if let Ok((child_success, child_failure)) = nodes.get(children[current]) {
+                if child_success.is_some() {
+                    commands.entity(entity).insert(BehaviorSuccess);
+                    continue;
+                }
+                if child_failure.is_some() {
+                    commands.entity(entity).insert(BehaviorFailure);
+                    continue;
+                }
+            }
+            // already delegated, `rescore` takes it from here
+            continue;
+        }
+
+        // keep working on eval properties
+        if let BehaviorPropValue::None = selector.interval.value {
+            let result = selector.interval.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        if let BehaviorPropValue::None = selector.hysteresis.value {
+            let result = selector.hysteresis.fetch(node, &mut scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        }
+        let (BehaviorPropValue::Some(_), BehaviorPropValue::Some(_)) =
+            (&selector.interval.value, &selector.hysteresis.value)
+        else {
+            continue;
+        };
+
+        let values = match fetch_scores(selector, node, &mut scripts) {
+            Ok(Some(values)) => values,
+            Ok(None) => continue,
+            Err(()) => {
+                commands.entity(entity).insert(BehaviorFailure);
+                continue;
+            }
+        };
+        let next = pick(&values, None, 0.0);
+        selector.state.values = values;
+        selector.state.scored_at = Some(now);
+        selector.state.current = Some(next);
+        commands
+            .entity(entity)
+            .insert(BehaviorCursor::Delegate(children[next]));
+    }
+}
+
+// while a child runs the selector doesn't hold the cursor, score again from here
+pub fn rescore(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut selectors: Query<
+        (
+            Entity,
+            &mut UtilitySelector,
+            &BehaviorNode,
+            &BehaviorChildren,
+        ),
+        (
+            With<BehaviorRunning>,
+            Without<BehaviorCursor>,
+            Without<BehaviorSuccess>,
+            Without<BehaviorFailure>,
+        ),
+    >,
+    aborts: SubtreeAbort,
+    mut scripts: ScriptQueries,
+) {
+    let now = time.elapsed_seconds_f64();
+    for (entity, mut selector, node, children) in &mut selectors {
+        let selector = selector.as_mut();
+        let Some(current) = selector.state.current else {
+            continue;
+        };
+        let (BehaviorPropValue::Some(interval), BehaviorPropValue::Some(hysteresis)) =
+            (&selector.interval.value, &selector.hysteresis.value)
+        else {
+            continue;
+        };
+        let (interval, hysteresis) = (*interval, *hysteresis);
+        if interval <= 0.0 || children.len() != selector.scores.len() {
+            continue;
+        }
+
+        // time to score again
+        if let Some(scored_at) = selector.state.scored_at {
+            if now - scored_at < interval {
+                continue;
+            }
+            for score in selector.scores.iter_mut() {
+                score.score.value = BehaviorPropValue::None;
+            }
+            selector.state.scored_at = None;
+        }
+
+        let values = match fetch_scores(selector, node, &mut scripts) {
+            Ok(Some(values)) => values,
+            Ok(None) => continue,
+            // keep the running child, try again next interval
+            Err(()) => {
+                selector.state.scored_at = Some(now);
+                continue;
+            }
+        };
+        let next = pick(&values, Some(current), hysteresis);
+        selector.state.values = values;
+        selector.state.scored_at = Some(now);
+        if next != current {
+            aborts.abort(&mut commands, children[current]);
+            selector.state.current = Some(next);
+            commands
+                .entity(entity)
+                .insert(BehaviorCursor::Delegate(children[next]));
+        }
+    }
+}
+
+// curved score of every child, once all of them are evaluated
+fn fetch_scores(
+    selector: &mut UtilitySelector,
+    node: &BehaviorNode,
+    scripts: &mut ScriptQueries,
+) -> Result<Option<Vec<f64>>, ()> {
+    for score in selector.scores.iter_mut() {
+        if let BehaviorPropValue::None = score.score.value {
+            let result = score.score.fetch(node, scripts);
+            if let Some(Err(err)) = result {
+                error!("Script errored: {:?}", err);
+                return Err(());
+            }
+        }
+    }
+    Ok(selector
+        .scores
+        .iter()
+        .map(|score| match &score.score.value {
+            BehaviorPropValue::Some(value) => Some(score.curve.eval(*value)),
+            _ => None,
+        })
+        .collect())
+}
+
+// best child, unless it doesn't beat the current one by the hysteresis
+fn pick(values: &[f64], current: Option<usize>, hysteresis: f64) -> usize {
+    let best = values
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(index, _)| index)
+        .unwrap_or_default();
+    match current {
+        Some(current) if values[best] <= values[current] + hysteresis => current,
+        _ => best,
+    }
+}
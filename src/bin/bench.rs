@@ -0,0 +1,182 @@
+//! Spawns growing numbers of NPC behavior trees headlessly and reports where frame time goes
+
+use autonpcs::{
+    behaviors::{blackboard::BlackboardWrite, npc::NPCBehavior},
+    deterministic::DeterministicPlugin,
+    headless::HeadlessPlugin,
+    profile::{ProfilePlugin, SystemProfile},
+    region::{Region, RegionShape},
+    sandbox::SandboxPlugin,
+};
+use bevy::{asset::LoadState, ecs::schedule::ExecutorKind, prelude::*};
+use clap::Parser;
+use rhai::Dynamic;
+use simula_behavior::prelude::*;
+use std::{
+    fmt::Write as _,
+    time::{Duration, Instant},
+};
+
+const TREE: &str = "bht/u/bench_npc.bht.ron";
+
+/// NPC scaling benchmark
+#[derive(Debug, Parser)]
+struct Args {
+    /// Numbers of NPC trees to run, one pass each
+    #[arg(long, value_delimiter = ',', default_values_t = [100, 1000, 10000])]
+    npcs: Vec<usize>,
+    /// Frames to run before measuring, while scenes load and trees get going
+    #[arg(long, default_value_t = 120)]
+    warmup: u32,
+    /// Frames measured per pass
+    #[arg(long, default_value_t = 300, value_parser = clap::value_parser!(u32).range(1..))]
+    frames: u32,
+    /// Give up on loading assets after this many seconds
+    #[arg(long, default_value_t = 60)]
+    timeout: u64,
+    /// CSV report
+    #[arg(long, default_value = "bench_report.csv")]
+    out: String,
+}
+
+/// Per frame statistics of one measure, in milliseconds
+struct Stats {
+    mean: f64,
+    p50: f64,
+    p95: f64,
+    max: f64,
+}
+
+impl Stats {
+    fn new(mut samples: Vec<Duration>) -> Self {
+        samples.sort();
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let at = |fraction: f64| {
+            let index = ((samples.len() - 1) as f64 * fraction).round() as usize;
+            ms(samples[index])
+        };
+        Self {
+            mean: samples.iter().copied().map(ms).sum::<f64>() / samples.len() as f64,
+            p50: at(0.5),
+            p95: at(0.95),
+            max: ms(*samples.last().unwrap()),
+        }
+    }
+}
+
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugin(DeterministicPlugin::default())
+        .add_plugin(HeadlessPlugin::default())
+        .add_plugin(SandboxPlugin)
+        .add_plugin(ProfilePlugin)
+        .edit_schedule(CoreSchedule::Main, |schedule| {
+            // markers around systems are only meaningful when nothing runs alongside
+            schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+        });
+    app.world
+        .spawn(SpatialBundle::default())
+        .insert(Region {
+            shape: RegionShape::Box {
+                size: Vec2::new(40.0, 40.0),
+            },
+            color: Color::ORANGE,
+        })
+        .insert(Name::new("bench"));
+    app
+}
+
+fn run(args: &Args, npcs: usize) -> Result<Vec<(&'static str, Stats)>, String> {
+    let mut app = app();
+    let handle: Handle<BehaviorAsset<NPCBehavior>> = app.world.resource::<AssetServer>().load(TREE);
+    for index in 0..npcs {
+        let tree = app
+            .world
+            .spawn((
+                Name::new(format!("BHT: bench {}", index)),
+                handle.clone(),
+                BehaviorTree::<NPCBehavior>::default(),
+                BehaviorTreeReset::<NPCBehavior>::default(),
+            ))
+            .id();
+        // unique names, so anim targets only find their own NPC
+        app.world.send_event(BlackboardWrite::new(
+            tree,
+            "name",
+            Dynamic::from(format!("npc_{}", index)),
+        ));
+    }
+
+    // asset IO runs on other threads, wait for it
+    let started = Instant::now();
+    loop {
+        app.update();
+        let state = app.world.resource::<AssetServer>().get_load_state(&handle);
+        match state {
+            LoadState::Loaded => break,
+            LoadState::Failed => return Err(format!("cannot load {}", TREE)),
+            _ if started.elapsed() > Duration::from_secs(args.timeout) => {
+                return Err(format!("timed out loading {}", TREE))
+            }
+            _ => std::thread::sleep(Duration::from_millis(10)),
+        }
+    }
+    for _ in 0..args.warmup {
+        app.update();
+    }
+
+    let mut profile = app.world.resource_mut::<SystemProfile>();
+    profile.capacity = args.frames as usize;
+    profile.frames.clear();
+    let mut frames = vec![];
+    for _ in 0..args.frames {
+        let start = Instant::now();
+        app.update();
+        frames.push(start.elapsed());
+    }
+
+    let profile = app.world.resource::<SystemProfile>();
+    let mut stats = vec![("frame", Stats::new(frames))];
+    for name in ["spawn::run", "anim::run", "subtree::run", "behavior core"] {
+        let samples = profile
+            .frames
+            .iter()
+            .map(|frame| frame.get(name).copied().unwrap_or_default())
+            .collect();
+        stats.push((name, Stats::new(samples)));
+    }
+    Ok(stats)
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let mut report = String::from("npcs,measure,mean_ms,p50_ms,p95_ms,max_ms\n");
+    for npcs in &args.npcs {
+        info!("Bench: {} NPCs", npcs);
+        let stats = match run(&args, *npcs) {
+            Ok(stats) => stats,
+            Err(err) => {
+                error!("Bench: {} NPCs failed: {}", npcs, err);
+                continue;
+            }
+        };
+        for (name, stats) in stats {
+            info!(
+                "Bench: {} NPCs {}: mean {:.3} ms p95 {:.3} ms",
+                npcs, name, stats.mean, stats.p95
+            );
+            writeln!(
+                report,
+                "{},{},{:.4},{:.4},{:.4},{:.4}",
+                npcs, name, stats.mean, stats.p50, stats.p95, stats.max
+            )
+            .unwrap();
+        }
+    }
+
+    match std::fs::write(&args.out, report) {
+        Ok(()) => info!("Bench: report written to {}", args.out),
+        Err(err) => error!("Bench: cannot write {}: {}", args.out, err),
+    }
+}
@@ -0,0 +1,65 @@
+use bevy::prelude::*;
+use clap::Parser;
+
+/// Autonomous NPCs sandbox
+#[derive(Debug, Clone, Parser, Resource)]
+#[command(version, about)]
+pub struct Cli {
+    /// Manifest listing the behaviors to bring up, relative to the assets folder
+    #[arg(long, default_value = "default.sandbox.ron")]
+    pub manifest: String,
+    /// NPC behavior tree to autoload, like `bht/u/spawn_test`, can be repeated
+    #[arg(long = "run", value_name = "TREE")]
+    pub run: Vec<String>,
+    /// Bioma behavior tree to autoload, like `bht/u/population_test`, can be repeated
+    #[arg(long = "run-bioma", value_name = "TREE")]
+    pub run_bioma: Vec<String>,
+    /// Window width
+    #[arg(long, default_value_t = 1920.0)]
+    pub width: f32,
+    /// Window height
+    #[arg(long, default_value_t = 1080.0)]
+    pub height: f32,
+    /// Render as fast as possible instead of waiting for vsync
+    #[arg(long)]
+    pub no_vsync: bool,
+    /// Seed for everything random in the simulation
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+    /// Simulated seconds per real second, 60 if not set
+    #[arg(long)]
+    pub time_scale: Option<f64>,
+    /// Advance a fixed step every frame and seed all randomness, so runs can be replayed
+    #[arg(long)]
+    pub deterministic: bool,
+    /// With `--deterministic`, simulated steps per second
+    #[arg(long, default_value_t = 60.0, requires = "deterministic")]
+    pub step_rate: f64,
+    /// Record node states, blackboard writes and side effects to this file
+    #[arg(long, value_name = "FILE")]
+    pub trace: Option<String>,
+    /// Open a recorded trace in the replay window
+    #[arg(long, value_name = "FILE", conflicts_with = "headless")]
+    pub replay: Option<String>,
+    /// Snapshot file saved with F6 and restored with F7
+    #[arg(long, value_name = "FILE", default_value = "snapshot.ron")]
+    pub snapshot: String,
+    /// Restore the snapshot at startup
+    #[arg(long)]
+    pub restore: bool,
+    /// Save the snapshot when exiting, like at the end of a headless run
+    #[arg(long)]
+    pub save_on_exit: bool,
+    /// Tick every behavior tree every frame, however far from the camera
+    #[arg(long)]
+    pub no_lod: bool,
+    /// Leave out the inspector windows
+    #[arg(long)]
+    pub no_inspector: bool,
+    /// Run without window or renderer
+    #[arg(long)]
+    pub headless: bool,
+    /// With `--headless`, exit after this many frames
+    #[arg(long, requires = "headless")]
+    pub frames: Option<u32>,
+}
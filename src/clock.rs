@@ -0,0 +1,147 @@
+use crate::inspector::ResourceMenuPlugin;
+use bevy::prelude::*;
+use bevy_inspector_egui::prelude::*;
+use serde::{Deserialize, Serialize};
+use simula_action::{action_map, Action, ActionMap, ActionMapInput};
+use std::fmt;
+
+pub const SECONDS_PER_HOUR: f64 = 3600.0;
+pub const HOURS_PER_DAY: f64 = 24.0;
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimClock>()
+            .register_type::<SimClock>()
+            .add_system(tick.in_base_set(CoreSet::PreUpdate));
+
+        // controls and display, not available headless
+        if app.is_plugin_added::<WindowPlugin>() {
+            app.add_plugin(ResourceMenuPlugin::<SimClock>::default())
+                .add_startup_system(setup)
+                .add_system(action_map::<ClockAction, ClockActionInput>)
+                .add_system(control.after(action_map::<ClockAction, ClockActionInput>))
+                .add_system(hud);
+        }
+    }
+}
+
+/// Simulated time, independent from real time
+#[derive(Debug, Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct SimClock {
+    /// Simulated seconds since midnight of the first day
+    pub elapsed: f64,
+    /// Simulated seconds per real second
+    #[inspector(min = 0.0)]
+    pub time_scale: f64,
+    pub paused: bool,
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        Self {
+            elapsed: 8.0 * SECONDS_PER_HOUR,
+            time_scale: 60.0,
+            paused: false,
+        }
+    }
+}
+
+impl SimClock {
+    /// Hour of the day, in [0, 24)
+    pub fn hour(&self) -> f64 {
+        (self.elapsed / SECONDS_PER_HOUR).rem_euclid(HOURS_PER_DAY)
+    }
+
+    /// Day number, starting at 1
+    pub fn day(&self) -> u64 {
+        (self.elapsed / SECONDS_PER_HOUR / HOURS_PER_DAY).floor() as u64 + 1
+    }
+
+    pub fn advance(&mut self, real_seconds: f64) {
+        if !self.paused {
+            self.elapsed += real_seconds * self.time_scale.max(0.0);
+        }
+    }
+}
+
+impl fmt::Display for SimClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hour = self.hour();
+        let minutes = (hour * 60.0).floor() as u64;
+        write!(
+            f,
+            "Day {} {:02}:{:02}{}",
+            self.day(),
+            minutes / 60,
+            minutes % 60,
+            if self.paused { " ⏸" } else { "" }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum ClockAction {
+    Pause,
+    Slower,
+    Faster,
+}
+
+pub type ClockActionInput = ActionMapInput<ClockAction, ()>;
+
+/// Text showing the clock, as its second section
+#[derive(Component)]
+pub struct ClockText;
+
+fn setup(mut commands: Commands) {
+    let mut action_map = ActionMap::<ClockAction, ()>::default();
+    action_map.push(ClockActionInput {
+        action: ClockAction::Pause,
+        button: KeyCode::F3.into(),
+        ..Default::default()
+    });
+    action_map.push(ClockActionInput {
+        action: ClockAction::Slower,
+        button: KeyCode::F4.into(),
+        ..Default::default()
+    });
+    action_map.push(ClockActionInput {
+        action: ClockAction::Faster,
+        button: KeyCode::F5.into(),
+        ..Default::default()
+    });
+
+    commands.spawn((
+        Name::new("Sim Clock"),
+        Action::<ClockAction>::default(),
+        action_map,
+    ));
+}
+
+fn control(mut clock: ResMut<SimClock>, actions: Query<&Action<ClockAction>>) {
+    for action in &actions {
+        if action.on_enter(ClockAction::Pause) {
+            clock.paused = !clock.paused;
+        }
+        if action.on_enter(ClockAction::Slower) {
+            clock.time_scale /= 2.0;
+        }
+        if action.on_enter(ClockAction::Faster) {
+            clock.time_scale *= 2.0;
+        }
+    }
+}
+
+fn tick(time: Res<Time>, mut clock: ResMut<SimClock>) {
+    clock.advance(time.delta_seconds_f64());
+}
+
+fn hud(clock: Res<SimClock>, mut texts: Query<&mut Text, With<ClockText>>) {
+    for mut text in &mut texts {
+        if let Some(section) = text.sections.get_mut(1) {
+            section.value = format!("  {} x{}", *clock, clock.time_scale);
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{
+    behaviors::blackboard::BlackboardWrite,
+    clock::{SimClock, HOURS_PER_DAY, SECONDS_PER_HOUR},
+    inspector::ResourceMenuPlugin,
+};
+use bevy::prelude::*;
+use bevy_inspector_egui::prelude::*;
+use rhai::Dynamic;
+use simula_script::ScriptContext;
+use std::f64::consts::PI;
+
+pub struct DayNightPlugin;
+
+impl Plugin for DayNightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimeOfDay>()
+            .register_type::<TimeOfDay>()
+            .add_system(sync_day_length)
+            .add_system(update.after(sync_day_length))
+            .add_system(write_scope.after(update));
+
+        // lighting, not needed headless
+        if app.is_plugin_added::<WindowPlugin>() {
+            app.add_plugin(ResourceMenuPlugin::<TimeOfDay>::default())
+                .add_system(environment.after(update));
+        }
+    }
+}
+
+/// Sun position and lighting, derived from the `SimClock`
+#[derive(Debug, Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct TimeOfDay {
+    /// Real seconds per simulated day, kept in sync with the clock time scale
+    #[inspector(min = 1.0)]
+    pub day_length: f64,
+    /// Hour the sun rises
+    pub sunrise: f64,
+    /// Hour the sun sets
+    pub sunset: f64,
+    pub day_illuminance: f32,
+    pub night_illuminance: f32,
+    pub noon_color: Color,
+    pub horizon_color: Color,
+    pub night_color: Color,
+    pub day_clear_color: Color,
+    pub night_clear_color: Color,
+
+    /// Radians, 0 at sunrise, PI at sunset, over PI at night
+    #[reflect(ignore)]
+    pub sun_angle: f64,
+    /// Sun elevation factor, 0 at night, 1 at noon
+    #[reflect(ignore)]
+    pub daylight: f32,
+    #[reflect(ignore)]
+    pub is_night: bool,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self {
+            day_length: 24.0 * 60.0,
+            sunrise: 6.0,
+            sunset: 20.0,
+            day_illuminance: 5000.0,
+            night_illuminance: 300.0,
+            noon_color: Color::rgb(1.0, 1.0, 1.0),
+            horizon_color: Color::rgb(1.0, 0.6, 0.35),
+            night_color: Color::rgb(0.45, 0.5, 0.8),
+            day_clear_color: Color::rgb(0.105, 0.10, 0.13),
+            night_clear_color: Color::rgb(0.02, 0.02, 0.05),
+            sun_angle: 0.0,
+            daylight: 0.0,
+            is_night: false,
+        }
+    }
+}
+
+impl TimeOfDay {
+    pub fn sun_angle_at(&self, hour: f64) -> f64 {
+        let day = (self.sunset - self.sunrise).rem_euclid(HOURS_PER_DAY);
+        let since_sunrise = (hour - self.sunrise).rem_euclid(HOURS_PER_DAY);
+        if since_sunrise < day {
+            PI * since_sunrise / day.max(f64::EPSILON)
+        } else {
+            let night = (HOURS_PER_DAY - day).max(f64::EPSILON);
+            PI + PI * (since_sunrise - day) / night
+        }
+    }
+}
+
+/// Directional light moved by the day/night cycle
+#[derive(Component)]
+pub struct Sun;
+
+fn sync_day_length(
+    mut clock: ResMut<SimClock>,
+    mut time_of_day: ResMut<TimeOfDay>,
+    mut last: Local<Option<(f64, f64)>>,
+) {
+    let seconds_per_day = SECONDS_PER_HOUR * HOURS_PER_DAY;
+    let current = (clock.time_scale, time_of_day.day_length);
+    match *last {
+        Some(last) if last == current => return,
+        // day length changed from the inspector
+        Some((time_scale, _)) if time_scale == current.0 => {
+            clock.time_scale = seconds_per_day / time_of_day.day_length.max(1.0);
+        }
+        // time scale changed from the clock controls, or set at startup
+        _ => {
+            if clock.time_scale > 0.0 {
+                time_of_day.day_length = seconds_per_day / clock.time_scale;
+            }
+        }
+    }
+    *last = Some((clock.time_scale, time_of_day.day_length));
+}
+
+fn update(clock: Res<SimClock>, mut time_of_day: ResMut<TimeOfDay>) {
+    let sun_angle = time_of_day.sun_angle_at(clock.hour());
+    time_of_day.sun_angle = sun_angle;
+    time_of_day.daylight = sun_angle.sin().max(0.0) as f32;
+    time_of_day.is_night = sun_angle >= PI;
+}
+
+fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    let (a, b) = (a.as_rgba_f32(), b.as_rgba_f32());
+    Color::rgba(
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+        a[3] + (b[3] - a[3]) * t,
+    )
+}
+
+fn environment(
+    time_of_day: Res<TimeOfDay>,
+    mut clear_color: ResMut<ClearColor>,
+    mut suns: Query<(&mut Transform, &mut DirectionalLight), With<Sun>>,
+) {
+    let daylight = time_of_day.daylight;
+    for (mut transform, mut light) in &mut suns {
+        if time_of_day.is_night {
+            // moonlight comes from high above
+            *transform = Transform::from_rotation(Quat::from_euler(
+                EulerRot::ZYX,
+                0.0,
+                std::f32::consts::FRAC_PI_2,
+                -std::f32::consts::FRAC_PI_3,
+            ));
+            light.illuminance = time_of_day.night_illuminance;
+            light.color = time_of_day.night_color;
+        } else {
+            let elevation = time_of_day.sun_angle as f32;
+            *transform = Transform::from_rotation(Quat::from_euler(
+                EulerRot::ZYX,
+                0.0,
+                std::f32::consts::FRAC_PI_2,
+                -elevation,
+            ));
+            light.illuminance = time_of_day.night_illuminance
+                + (time_of_day.day_illuminance - time_of_day.night_illuminance) * daylight;
+            light.color = lerp_color(time_of_day.horizon_color, time_of_day.noon_color, daylight);
+        }
+    }
+    clear_color.0 = lerp_color(
+        time_of_day.night_clear_color,
+        time_of_day.day_clear_color,
+        daylight.sqrt(),
+    );
+}
+
+/// Scripts see the sun angle in steps of a degree, so it only gets published as often
+pub const SUN_ANGLE_STEP: f64 = PI / 180.0;
+
+// Publish `is_night` and `sun_angle` to every tree when they change, and to new trees
+fn write_scope(
+    time_of_day: Res<TimeOfDay>,
+    trees: Query<Entity, With<Handle<ScriptContext>>>,
+    added: Query<Entity, Added<Handle<ScriptContext>>>,
+    mut writes: EventWriter<BlackboardWrite>,
+    mut published: Local<Option<(bool, f64)>>,
+) {
+    let sun_angle = (time_of_day.sun_angle / SUN_ANGLE_STEP).floor() * SUN_ANGLE_STEP;
+    let current = (time_of_day.is_night, sun_angle);
+    let targets: Vec<Entity> = if *published != Some(current) {
+        *published = Some(current);
+        trees.iter().collect()
+    } else {
+        added.iter().collect()
+    };
+    for tree in targets {
+        writes.send(BlackboardWrite::variable(
+            tree,
+            "is_night",
+            Dynamic::from(time_of_day.is_night),
+        ));
+        writes.send(BlackboardWrite::variable(
+            tree,
+            "sun_angle",
+            Dynamic::from(sun_angle),
+        ));
+    }
+}
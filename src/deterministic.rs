@@ -0,0 +1,226 @@
+use crate::random::{shuffle, RandomSeed};
+use bevy::{
+    prelude::*,
+    time::{TimeSystem, TimeUpdateStrategy},
+};
+use simula_behavior::prelude::*;
+use std::time::{Duration, Instant};
+
+/// Runs the simulation in fixed steps and takes the randomness of composite nodes from
+/// `RandomSeed`. With the same seed and inputs, runs play out step by step the same way.
+pub struct DeterministicPlugin {
+    pub step: Duration,
+    /// Run a step when real time calls for it, instead of one per update. For windowed apps.
+    pub realtime: bool,
+}
+
+impl Default for DeterministicPlugin {
+    fn default() -> Self {
+        Self {
+            step: Duration::from_nanos(16_666_667),
+            realtime: false,
+        }
+    }
+}
+
+impl Plugin for DeterministicPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Deterministic { step: self.step })
+            .insert_resource(TimeUpdateStrategy::ManualDuration(self.step))
+            .add_system(seed_composites.in_base_set(CoreSet::PreUpdate))
+            .add_system(run.in_base_set(SimSet))
+            // nodes spawned during the frame are taken over before the behavior core starts them
+            .add_system(
+                seed_composites
+                    .in_base_set(CoreSet::PostUpdate)
+                    .before(BehaviorSet::PostUpdate),
+            );
+
+        if self.realtime {
+            // the frame still runs as usual, only the simulation waits for its steps
+            app.init_resource::<StepClock>()
+                .add_system(step_clock.in_base_set(CoreSet::First).before(TimeSystem))
+                .configure_set(SimSet.run_if(step_due))
+                .configure_set(BehaviorSet::PreUpdate.run_if(step_due))
+                .configure_set(BehaviorSet::PostUpdate.run_if(step_due));
+        }
+    }
+}
+
+/// Present when the simulation runs deterministically
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct Deterministic {
+    /// Simulated time per step
+    pub step: Duration,
+}
+
+/// Base set of the behavior and simulation systems, runs right after `CoreSet::Update`.
+/// In realtime deterministic mode it only runs on frames a step is due.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[system_set(base)]
+pub struct SimSet;
+
+/// Real time not simulated yet, in realtime mode
+#[derive(Debug, Default, Resource)]
+struct StepClock {
+    last: Option<Instant>,
+    behind: Duration,
+    due: bool,
+}
+
+// decide before time updates if the frame runs a step, time only advances on steps
+fn step_clock(
+    deterministic: Res<Deterministic>,
+    mut clock: ResMut<StepClock>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+) {
+    let step = deterministic.step;
+    let now = Instant::now();
+    // the first frame runs a step
+    let elapsed = clock.last.map_or(step, |last| now - last);
+    clock.last = Some(now);
+    clock.behind += elapsed;
+
+    clock.due = clock.behind >= step;
+    if clock.due {
+        // a frame runs one step, time beyond the next one is dropped so a slow frame can't snowball
+        clock.behind = (clock.behind - step).min(step);
+    }
+    *strategy = TimeUpdateStrategy::ManualDuration(if clock.due { step } else { Duration::ZERO });
+}
+
+fn step_due(clock: Res<StepClock>) -> bool {
+    clock.due
+}
+
+/// How a seeded composite treats the results of its children
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeededKind {
+    /// Like `Sequencer`, fails on the first child failing
+    Sequence,
+    /// Like `Selector`, succeeds on the first child succeeding
+    Select,
+}
+
+/// Random composite, taking over from its `Sequencer` or `Selector`. Each run goes through the
+/// children in an order drawn from the seed, `BehaviorChildren` keeps the order of the asset.
+#[derive(Debug, Component)]
+pub struct SeededComposite {
+    pub kind: SeededKind,
+    runs: u64,
+    /// Indices of children, in the order of this run
+    pub order: Vec<usize>,
+    /// Place in `order` of the running child
+    pub position: usize,
+}
+
+impl SeededComposite {
+    fn new(kind: SeededKind) -> Self {
+        Self {
+            kind,
+            runs: 0,
+            order: vec![],
+            position: 0,
+        }
+    }
+}
+
+// take over random sequencers and selectors before they first run
+fn seed_composites(
+    mut commands: Commands,
+    sequencers: Query<(Entity, &Sequencer)>,
+    selectors: Query<(Entity, &Selector)>,
+) {
+    for (entity, sequencer) in &sequencers {
+        if sequencer.random {
+            commands
+                .entity(entity)
+                .remove::<Sequencer>()
+                .insert(SeededComposite::new(SeededKind::Sequence));
+        }
+    }
+    for (entity, selector) in &selectors {
+        if selector.random {
+            commands
+                .entity(entity)
+                .remove::<Selector>()
+                .insert(SeededComposite::new(SeededKind::Select));
+        }
+    }
+}
+
+pub fn run(
+    mut commands: Commands,
+    random: Res<RandomSeed>,
+    mut composites: Query<
+        (
+            Entity,
+            &mut SeededComposite,
+            &BehaviorChildren,
+            Option<&BehaviorStarted>,
+        ),
+        BehaviorRunQuery,
+    >,
+    nodes: Query<(Option<&BehaviorSuccess>, Option<&BehaviorFailure>), With<BehaviorNode>>,
+) {
+    for (entity, mut composite, children, started) in &mut composites {
+        let composite = composite.as_mut();
+        // result when a child ends the run early, and when all children ran
+        let (ended, exhausted) = match composite.kind {
+            SeededKind::Sequence => (false, true),
+            SeededKind::Select => (true, false),
+        };
+
+        // restored from a snapshot without an order, starts over too
+        if started.is_some() || composite.order.len() != children.len() {
+            // order of children for this run, from the seed and the number of runs so far
+            composite.order = (0..children.len()).collect();
+            let seed = random.mix(entity.to_bits().wrapping_add(composite.runs));
+            shuffle(seed, &mut composite.order);
+            composite.runs += 1;
+            composite.position = 0;
+        } else if let Some(child) = composite
+            .order
+            .get(composite.position)
+            .and_then(|index| children.get(*index))
+        {
+            // the running child is done
+            let Ok((success, failure)) = nodes.get(*child) else {
+                continue;
+            };
+            let ends = match composite.kind {
+                SeededKind::Sequence => failure.is_some(),
+                SeededKind::Select => success.is_some(),
+            };
+            if ends {
+                insert_result(&mut commands, entity, ended);
+                continue;
+            }
+            if success.is_none() && failure.is_none() {
+                continue;
+            }
+            composite.position += 1;
+        }
+
+        match composite
+            .order
+            .get(composite.position)
+            .and_then(|index| children.get(*index))
+        {
+            Some(child) => {
+                commands
+                    .entity(entity)
+                    .insert(BehaviorCursor::Delegate(*child));
+            }
+            None => insert_result(&mut commands, entity, exhausted),
+        }
+    }
+}
+
+fn insert_result(commands: &mut Commands, entity: Entity, success: bool) {
+    if success {
+        commands.entity(entity).insert(BehaviorSuccess);
+    } else {
+        commands.entity(entity).insert(BehaviorFailure);
+    }
+}
@@ -0,0 +1,503 @@
+use crate::{
+    behaviors::npc::walk_towards,
+    inspector::ResourceMenuPlugin,
+    random::{hash, unit, RandomSeed},
+};
+use bevy::{prelude::*, utils::HashMap};
+use bevy_inspector_egui::{
+    bevy_egui::EguiContexts,
+    egui::{self, plot},
+    prelude::*,
+};
+use std::collections::VecDeque;
+
+pub struct EcosystemPlugin;
+
+impl Plugin for EcosystemPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Ecosystem>()
+            .init_resource::<EcosystemCensus>()
+            .register_type::<Ecosystem>()
+            .register_type::<Organism>()
+            .register_type::<ResourcePatch>()
+            .add_system(regrow)
+            .add_system(live)
+            .add_system(forage.after(live).after(regrow))
+            .add_system(reproduce.after(forage))
+            .add_system(census.after(reproduce));
+
+        // tuning and chart, not available headless
+        if app.is_plugin_added::<WindowPlugin>() {
+            app.add_plugin(ResourceMenuPlugin::<Ecosystem>::default())
+                .add_system(chart.after(census));
+        }
+    }
+}
+
+/// Distance to food at which an organism can eat it
+pub const EAT_DISTANCE: f32 = 0.3;
+
+/// Rules for one kind of creature
+#[derive(Debug, Clone, Reflect, FromReflect)]
+pub struct SpeciesDef {
+    pub name: String,
+    pub scene: String,
+    pub scale: f32,
+    /// Color of the population curve
+    pub color: Color,
+    /// Meters per second
+    pub speed: f32,
+    /// Species or resources it eats
+    pub diet: Vec<String>,
+    pub max_energy: f32,
+    /// Energy lost per second
+    pub metabolism: f32,
+    /// Energy given to whatever eats it
+    pub food_value: f32,
+    /// Fraction of max energy at which it splits in two
+    pub reproduce_at: f32,
+    /// Seconds until it dies of old age
+    pub max_age: f32,
+    /// Distance it can find food from
+    pub sight: f32,
+    /// No more births above this count
+    pub capacity: usize,
+}
+
+/// Rules for one kind of food that grows back
+#[derive(Debug, Clone, Reflect, FromReflect)]
+pub struct ResourceDef {
+    pub name: String,
+    pub scene: String,
+    pub scale: f32,
+    pub max_amount: f32,
+    /// Amount grown back per second
+    pub regrow: f32,
+    /// Amount, and energy, taken per bite
+    pub bite: f32,
+}
+
+/// Species and resources of the simulation
+#[derive(Debug, Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct Ecosystem {
+    pub species: Vec<SpeciesDef>,
+    pub resources: Vec<ResourceDef>,
+    /// Seconds between census samples
+    #[inspector(min = 0.1)]
+    pub sample_interval: f32,
+    /// Census samples kept for the chart
+    pub history: usize,
+    pub show_chart: bool,
+}
+
+impl Default for Ecosystem {
+    fn default() -> Self {
+        Self {
+            species: vec![
+                SpeciesDef {
+                    name: "rabbit".into(),
+                    scene: "models/sphere/sphere.gltf#Scene0".into(),
+                    scale: 0.15,
+                    color: Color::WHITE,
+                    speed: 1.0,
+                    diet: vec!["grass".into()],
+                    max_energy: 10.0,
+                    metabolism: 0.4,
+                    food_value: 8.0,
+                    reproduce_at: 0.9,
+                    max_age: 120.0,
+                    sight: 3.0,
+                    capacity: 40,
+                },
+                SpeciesDef {
+                    name: "fox".into(),
+                    scene: "models/fox/Fox.glb#Scene0".into(),
+                    scale: 0.01,
+                    color: Color::ORANGE,
+                    speed: 1.4,
+                    diet: vec!["rabbit".into()],
+                    max_energy: 20.0,
+                    metabolism: 0.5,
+                    food_value: 10.0,
+                    reproduce_at: 0.9,
+                    max_age: 240.0,
+                    sight: 4.0,
+                    capacity: 10,
+                },
+            ],
+            resources: vec![ResourceDef {
+                name: "grass".into(),
+                scene: "models/cube/cube.gltf#Scene0".into(),
+                scale: 0.2,
+                max_amount: 6.0,
+                regrow: 0.2,
+                bite: 2.0,
+            }],
+            sample_interval: 1.0,
+            history: 300,
+            show_chart: true,
+        }
+    }
+}
+
+impl Ecosystem {
+    pub fn species(&self, name: &str) -> Option<&SpeciesDef> {
+        self.species.iter().find(|species| species.name == name)
+    }
+
+    pub fn resource(&self, name: &str) -> Option<&ResourceDef> {
+        self.resources.iter().find(|resource| resource.name == name)
+    }
+}
+
+/// Creature living by the rules of its species
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
+pub struct Organism {
+    pub species: String,
+    pub energy: f32,
+    /// Seconds alive
+    pub age: f32,
+    /// Seconds until it can eat again
+    pub cooldown: f32,
+    #[reflect(ignore)]
+    pub target: Option<Vec3>,
+    #[reflect(ignore)]
+    pub wandered: u64,
+    /// Offspring so far, each one lands in its own direction
+    #[reflect(ignore)]
+    pub offspring: u64,
+}
+
+/// Food source that is eaten down and grows back
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
+pub struct ResourcePatch {
+    pub kind: String,
+    pub amount: f32,
+}
+
+/// Counts per species, sampled over time
+#[derive(Debug, Default, Resource)]
+pub struct EcosystemCensus {
+    pub counts: HashMap<String, usize>,
+    /// Elapsed seconds and counts of each sample
+    pub samples: VecDeque<(f64, HashMap<String, usize>)>,
+    pub last_sample: Option<f64>,
+}
+
+impl EcosystemCensus {
+    pub fn count(&self, species: &str) -> usize {
+        self.counts.get(species).copied().unwrap_or_default()
+    }
+}
+
+pub fn spawn_organism(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    species: &SpeciesDef,
+    position: Vec3,
+    energy: f32,
+) -> Entity {
+    commands
+        .spawn(SceneBundle {
+            scene: asset_server.load(species.scene.as_str()),
+            transform: Transform::from_translation(position).with_scale(Vec3::splat(species.scale)),
+            ..default()
+        })
+        .insert(Organism {
+            species: species.name.clone(),
+            energy,
+            ..default()
+        })
+        .insert(Name::new(format!("Organism: {}", species.name)))
+        .id()
+}
+
+pub fn spawn_patch(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    resource: &ResourceDef,
+    position: Vec3,
+) -> Entity {
+    commands
+        .spawn(SceneBundle {
+            scene: asset_server.load(resource.scene.as_str()),
+            transform: Transform::from_translation(position)
+                .with_scale(Vec3::splat(resource.scale)),
+            ..default()
+        })
+        .insert(ResourcePatch {
+            kind: resource.name.clone(),
+            amount: resource.max_amount,
+        })
+        .insert(Name::new(format!("Resource: {}", resource.name)))
+        .id()
+}
+
+fn regrow(
+    time: Res<Time>,
+    ecosystem: Res<Ecosystem>,
+    mut patches: Query<(&mut ResourcePatch, &mut Transform)>,
+) {
+    let delta = time.delta_seconds();
+    for (mut patch, mut transform) in &mut patches {
+        let Some(resource) = ecosystem.resource(&patch.kind) else {
+            continue;
+        };
+        patch.amount = (patch.amount + resource.regrow * delta).min(resource.max_amount);
+        // eaten patches shrink
+        let fill = patch.amount / resource.max_amount.max(f32::EPSILON);
+        transform.scale = Vec3::splat(resource.scale * (0.3 + 0.7 * fill));
+    }
+}
+
+// Starvation and old age
+fn live(
+    mut commands: Commands,
+    time: Res<Time>,
+    ecosystem: Res<Ecosystem>,
+    mut organisms: Query<(Entity, &mut Organism)>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut organism) in &mut organisms {
+        let Some(species) = ecosystem.species(&organism.species) else {
+            continue;
+        };
+        organism.age += delta;
+        organism.energy -= species.metabolism * delta;
+        organism.cooldown = (organism.cooldown - delta).max(0.0);
+        if organism.energy <= 0.0 || organism.age >= species.max_age {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+enum Food {
+    Prey(Entity),
+    Patch(Entity),
+}
+
+// Walk to the nearest food in sight and eat it, or wander around
+fn forage(
+    mut commands: Commands,
+    time: Res<Time>,
+    random: Res<RandomSeed>,
+    ecosystem: Res<Ecosystem>,
+    mut organisms: Query<(Entity, &mut Organism, &mut Transform)>,
+    mut patches: Query<(Entity, &mut ResourcePatch, &Transform), Without<Organism>>,
+) {
+    let delta = time.delta_seconds();
+    let prey: Vec<(Entity, String, Vec3)> = organisms
+        .iter()
+        .map(|(entity, organism, transform)| {
+            (entity, organism.species.clone(), transform.translation)
+        })
+        .collect();
+    let mut eaten: Vec<Entity> = vec![];
+
+    for (entity, mut organism, mut transform) in &mut organisms {
+        if eaten.contains(&entity) {
+            continue;
+        }
+        let Some(species) = ecosystem.species(&organism.species) else {
+            continue;
+        };
+        let position = transform.translation;
+        let in_sight = |other: Vec3| {
+            let distance = position.distance(other);
+            (distance <= species.sight).then_some(distance)
+        };
+
+        // nearest food, only when hungry
+        let mut food: Option<(Food, Vec3, f32)> = None;
+        if organism.energy < species.max_energy * species.reproduce_at {
+            for (other, other_species, other_position) in &prey {
+                if *other == entity
+                    || eaten.contains(other)
+                    || !species.diet.contains(other_species)
+                {
+                    continue;
+                }
+                if let Some(distance) = in_sight(*other_position) {
+                    if food.as_ref().map_or(true, |(_, _, best)| distance < *best) {
+                        food = Some((Food::Prey(*other), *other_position, distance));
+                    }
+                }
+            }
+            for (patch_entity, patch, patch_transform) in &patches {
+                let Some(resource) = ecosystem.resource(&patch.kind) else {
+                    continue;
+                };
+                if !species.diet.contains(&patch.kind) || patch.amount < resource.bite {
+                    continue;
+                }
+                if let Some(distance) = in_sight(patch_transform.translation) {
+                    if food.as_ref().map_or(true, |(_, _, best)| distance < *best) {
+                        food = Some((
+                            Food::Patch(patch_entity),
+                            patch_transform.translation,
+                            distance,
+                        ));
+                    }
+                }
+            }
+        }
+
+        let Some((food, food_position, distance)) = food else {
+            // wander to random points nearby
+            let target = *organism.target.get_or_insert_with(|| {
+                let seed = random.mix(entity.to_bits().wrapping_add(organism.wandered));
+                let angle = unit(seed) * std::f32::consts::TAU;
+                let reach = species.sight * unit(seed ^ 0xA5A5_A5A5);
+                position + Vec3::new(angle.cos() * reach, 0.0, angle.sin() * reach)
+            });
+            if walk_towards(&mut transform, target, species.speed * 0.5, delta) {
+                organism.target = None;
+                organism.wandered += 1;
+            }
+            continue;
+        };
+        organism.target = None;
+
+        if distance > EAT_DISTANCE {
+            walk_towards(&mut transform, food_position, species.speed, delta);
+            continue;
+        }
+        if organism.cooldown > 0.0 {
+            continue;
+        }
+        organism.cooldown = 1.0;
+        match food {
+            Food::Prey(other) => {
+                let value = prey
+                    .iter()
+                    .find(|(prey_entity, _, _)| *prey_entity == other)
+                    .and_then(|(_, other_species, _)| ecosystem.species(other_species))
+                    .map(|other_species| other_species.food_value)
+                    .unwrap_or_default();
+                organism.energy = (organism.energy + value).min(species.max_energy);
+                eaten.push(other);
+                commands.entity(other).despawn_recursive();
+            }
+            Food::Patch(patch_entity) => {
+                let Ok((_, mut patch, _)) = patches.get_mut(patch_entity) else {
+                    continue;
+                };
+                let Some(resource) = ecosystem.resource(&patch.kind) else {
+                    continue;
+                };
+                patch.amount -= resource.bite;
+                organism.energy = (organism.energy + resource.bite).min(species.max_energy);
+            }
+        }
+    }
+}
+
+// Well fed organisms split their energy with an offspring
+fn reproduce(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    random: Res<RandomSeed>,
+    ecosystem: Res<Ecosystem>,
+    mut organisms: Query<(Entity, &mut Organism, &Transform)>,
+) {
+    let mut counts: HashMap<String, usize> = HashMap::default();
+    for (_, organism, _) in &organisms {
+        *counts.entry(organism.species.clone()).or_default() += 1;
+    }
+    for (entity, mut organism, transform) in &mut organisms {
+        let Some(species) = ecosystem.species(&organism.species) else {
+            continue;
+        };
+        if organism.energy < species.max_energy * species.reproduce_at {
+            continue;
+        }
+        let count = counts.entry(species.name.clone()).or_default();
+        if *count >= species.capacity {
+            continue;
+        }
+        *count += 1;
+        organism.energy *= 0.5;
+        // hashed once more so it doesn't follow the wander targets, which count from the same entity
+        let seed = random.mix(hash(entity.to_bits().wrapping_add(organism.offspring)));
+        organism.offspring += 1;
+        let angle = unit(seed) * std::f32::consts::TAU;
+        let position = transform.translation + Vec3::new(angle.cos(), 0.0, angle.sin()) * 0.3;
+        spawn_organism(
+            &mut commands,
+            &asset_server,
+            species,
+            position,
+            organism.energy,
+        );
+    }
+}
+
+fn census(
+    time: Res<Time>,
+    ecosystem: Res<Ecosystem>,
+    mut census: ResMut<EcosystemCensus>,
+    organisms: Query<&Organism>,
+) {
+    let mut counts: HashMap<String, usize> = ecosystem
+        .species
+        .iter()
+        .map(|species| (species.name.clone(), 0))
+        .collect();
+    for organism in &organisms {
+        *counts.entry(organism.species.clone()).or_default() += 1;
+    }
+    census.counts = counts;
+
+    let now = time.elapsed_seconds_f64();
+    let due = census
+        .last_sample
+        .map_or(true, |last| now - last >= ecosystem.sample_interval as f64);
+    if due {
+        census.last_sample = Some(now);
+        let sample = (now, census.counts.clone());
+        census.samples.push_back(sample);
+        while census.samples.len() > ecosystem.history {
+            census.samples.pop_front();
+        }
+    }
+}
+
+// Population curves of every species
+fn chart(mut contexts: EguiContexts, ecosystem: Res<Ecosystem>, census: Res<EcosystemCensus>) {
+    if !ecosystem.show_chart {
+        return;
+    }
+    egui::Window::new("Ecosystem")
+        .default_size([320.0, 200.0])
+        .show(contexts.ctx_mut(), |ui| {
+            for species in &ecosystem.species {
+                ui.label(format!("{}: {}", species.name, census.count(&species.name)));
+            }
+            plot::Plot::new("ecosystem_census")
+                .height(160.0)
+                .legend(plot::Legend::default())
+                .show(ui, |plot_ui| {
+                    for species in &ecosystem.species {
+                        let points: Vec<[f64; 2]> = census
+                            .samples
+                            .iter()
+                            .map(|(at, counts)| {
+                                [
+                                    *at,
+                                    counts.get(&species.name).copied().unwrap_or_default() as f64,
+                                ]
+                            })
+                            .collect();
+                        let [r, g, b, _] = species.color.as_rgba_u8();
+                        plot_ui.line(
+                            plot::Line::new(points)
+                                .name(&species.name)
+                                .color(egui::Color32::from_rgb(r, g, b)),
+                        );
+                    }
+                });
+        });
+}
@@ -0,0 +1,277 @@
+use crate::{
+    behaviors::npc::SpawnOwned,
+    ecosystem::Organism,
+    ground::GroundSnap,
+    memory::NPCMemory,
+    needs::NPCNeeds,
+    perception::{Perceivable, Perception, Perceptions},
+};
+use bevy::{
+    asset::FileAssetIo,
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_inspector_egui::{
+    bevy_egui::{EguiContexts, EguiPlugin},
+    egui,
+};
+use std::path::{self, Path, PathBuf};
+
+/// Freezes NPC hierarchies into `.scn.ron` files under the assets folder
+pub struct ExportPlugin;
+
+impl Plugin for ExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ExportedScene>()
+            .init_resource::<ExportedSceneAssets>()
+            .add_event::<SceneExport>()
+            .add_system(export.in_base_set(CoreSet::Last))
+            .add_system(keep_loaded);
+    }
+}
+
+/// Window to pick NPCs and export them
+pub struct ExportInspectorPlugin;
+
+impl Plugin for ExportInspectorPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugin(EguiPlugin);
+        }
+        app.add_system(export_ui);
+    }
+}
+
+/// Request to write the hierarchies under `roots` to a scene, `path` is relative to the assets folder
+#[derive(Debug, Clone)]
+pub struct SceneExport {
+    pub roots: Vec<Entity>,
+    pub path: String,
+    /// Gets the result as a `SceneExported`, like the behavior node waiting for it
+    pub requester: Option<Entity>,
+}
+
+/// Result of a `SceneExport` on its requester, the number of entities written or why nothing was
+#[derive(Debug, Clone, Component)]
+pub struct SceneExported {
+    pub path: String,
+    pub result: Result<usize, String>,
+}
+
+/// Simulation state of NPCs, left out so exported scenes stay static
+fn simulated(type_name: &str) -> bool {
+    [
+        std::any::type_name::<NPCNeeds>(),
+        std::any::type_name::<NPCMemory>(),
+        std::any::type_name::<Perception>(),
+        std::any::type_name::<Perceivable>(),
+        std::any::type_name::<Perceptions>(),
+        std::any::type_name::<GroundSnap>(),
+        std::any::type_name::<Organism>(),
+    ]
+    .contains(&type_name)
+}
+
+/// Relative path staying inside the assets folder
+fn inside_assets(file: &str) -> bool {
+    Path::new(file).components().all(|component| {
+        matches!(
+            component,
+            path::Component::Normal(_) | path::Component::CurDir
+        )
+    })
+}
+
+/// Scene asset an exported NPC was spawned from, loaded again with the exported scene
+/// so its meshes, materials and clips resolve
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component)]
+pub struct ExportedScene {
+    pub scene: String,
+}
+
+#[derive(Default, Resource)]
+pub struct ExportedSceneAssets(HashMap<String, HandleUntyped>);
+
+fn export(world: &mut World) {
+    let requests: Vec<SceneExport> = world
+        .resource_mut::<Events<SceneExport>>()
+        .drain()
+        .collect();
+    for request in requests {
+        let result = write_scene(world, &request);
+        match &result {
+            Ok(count) => info!("Export: {} entities to {}", count, request.path),
+            Err(err) => error!("Export: cannot write {}: {}", request.path, err),
+        }
+        if let Some(mut requester) = request
+            .requester
+            .and_then(|entity| world.get_entity_mut(entity))
+        {
+            requester.insert(SceneExported {
+                path: request.path,
+                result,
+            });
+        }
+    }
+}
+
+fn write_scene(world: &mut World, request: &SceneExport) -> Result<usize, String> {
+    if !inside_assets(&request.path) {
+        return Err("path should stay inside the assets folder".into());
+    }
+
+    // roots and everything under them, once
+    let mut entities = vec![];
+    let mut seen = HashSet::default();
+    let mut stack = request.roots.clone();
+    while let Some(entity) = stack.pop() {
+        if world.get_entity(entity).is_none() || !seen.insert(entity) {
+            continue;
+        }
+        entities.push(entity);
+        if let Some(children) = world.get::<Children>(entity) {
+            stack.extend(children.iter().copied());
+        }
+    }
+
+    // scene of each root, to load it back with the export
+    let scenes: HashMap<u32, ExportedScene> = request
+        .roots
+        .iter()
+        .filter_map(|root| {
+            let handle = world.get::<Handle<Scene>>(*root)?;
+            let path = world.resource::<AssetServer>().get_handle_path(handle)?;
+            // the whole file, without the `#Scene0` label
+            Some((
+                root.index(),
+                ExportedScene {
+                    scene: path.path().display().to_string(),
+                },
+            ))
+        })
+        .collect();
+    // roots attached to something left out are exported detached
+    let detached: HashSet<u32> = request
+        .roots
+        .iter()
+        .filter(|root| {
+            world
+                .get::<Parent>(**root)
+                .map_or(false, |parent| !seen.contains(&parent.get()))
+        })
+        .map(|root| root.index())
+        .collect();
+
+    let mut builder = DynamicSceneBuilder::from_world(world);
+    builder.extract_entities(entities.iter().copied());
+    let mut scene = builder.build();
+    for entity in &mut scene.entities {
+        // the scene handle would spawn the scene a second time, and simulation state
+        // doesn't belong in a static scene
+        entity.components.retain(|component| {
+            let type_name = component.type_name();
+            type_name != std::any::type_name::<Handle<Scene>>()
+                && !simulated(type_name)
+                && !(detached.contains(&entity.entity)
+                    && type_name == std::any::type_name::<Parent>())
+        });
+        if let Some(exported) = scenes.get(&entity.entity) {
+            entity.components.push(Box::new(exported.clone()));
+        }
+    }
+
+    // the folder `AssetPlugin` loads from, so the export can be loaded back
+    let path = asset_root(world.resource::<AssetServer>())
+        .map(|root| root.join(&request.path))
+        .ok_or("assets aren't read from files")?;
+    let type_registry = world.resource::<AppTypeRegistry>();
+    let source = scene
+        .serialize_ron(type_registry)
+        .map_err(|err| err.to_string())?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    std::fs::write(&path, source).map_err(|err| err.to_string())?;
+    Ok(scene.entities.len())
+}
+
+fn asset_root(asset_server: &AssetServer) -> Option<PathBuf> {
+    asset_server
+        .asset_io()
+        .downcast_ref::<FileAssetIo>()
+        .map(|io| io.root_path().clone())
+}
+
+// hold on to the scenes exported NPCs came from
+fn keep_loaded(
+    asset_server: Res<AssetServer>,
+    mut assets: ResMut<ExportedSceneAssets>,
+    exported: Query<&ExportedScene, Added<ExportedScene>>,
+) {
+    for exported in &exported {
+        if !assets.0.contains_key(&exported.scene) {
+            let handle = asset_server.load_untyped(exported.scene.as_str());
+            assets.0.insert(exported.scene.clone(), handle);
+        }
+    }
+}
+
+struct ExportSelection {
+    roots: HashSet<Entity>,
+    path: String,
+}
+
+impl Default for ExportSelection {
+    fn default() -> Self {
+        Self {
+            roots: HashSet::default(),
+            path: "scenes/export.scn.ron".into(),
+        }
+    }
+}
+
+fn export_ui(
+    mut contexts: EguiContexts,
+    mut selection: Local<ExportSelection>,
+    npcs: Query<(Entity, &Name, Option<&Parent>), With<SpawnOwned>>,
+    mut exports: EventWriter<SceneExport>,
+) {
+    let selection = selection.as_mut();
+    selection.roots.retain(|root| npcs.contains(*root));
+    egui::Window::new("Export")
+        .default_size([240.0, 240.0])
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(160.0)
+                .show(ui, |ui| {
+                    for (entity, name, parent) in &npcs {
+                        // attached NPCs go with their parent
+                        if parent.map_or(false, |parent| npcs.contains(parent.get())) {
+                            continue;
+                        }
+                        let mut selected = selection.roots.contains(&entity);
+                        if ui
+                            .checkbox(&mut selected, format!("{} {:?}", name, entity))
+                            .changed()
+                        {
+                            if selected {
+                                selection.roots.insert(entity);
+                            } else {
+                                selection.roots.remove(&entity);
+                            }
+                        }
+                    }
+                });
+            ui.text_edit_singleline(&mut selection.path);
+            let export = ui.add_enabled(!selection.roots.is_empty(), egui::Button::new("Export"));
+            if export.clicked() {
+                exports.send(SceneExport {
+                    roots: selection.roots.iter().copied().collect(),
+                    path: selection.path.clone(),
+                    requester: None,
+                });
+            }
+        });
+}
@@ -0,0 +1,311 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BinaryHeap},
+};
+
+pub struct GoapPlugin;
+
+impl Plugin for GoapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<GoapAsset>()
+            .init_asset_loader::<GoapAssetLoader>();
+    }
+}
+
+/// World state, facts not present are false
+pub type GoapState = BTreeMap<String, bool>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoapAction {
+    pub name: String,
+    #[serde(default = "default_cost")]
+    pub cost: f32,
+    #[serde(default)]
+    pub preconditions: GoapState,
+    #[serde(default)]
+    pub effects: GoapState,
+    /// Behavior tree run to perform the action
+    pub subtree: String,
+}
+
+fn default_cost() -> f32 {
+    1.0
+}
+
+impl GoapAction {
+    pub fn applies(&self, state: &GoapState) -> bool {
+        satisfies(state, &self.preconditions)
+    }
+
+    fn apply(&self, state: &GoapState) -> GoapState {
+        let mut state = state.clone();
+        for (fact, value) in &self.effects {
+            state.insert(fact.clone(), *value);
+        }
+        state
+    }
+}
+
+/// Actions and goals for the `Plan` node, loaded from `.goap.ron` files
+#[derive(Debug, Clone, Serialize, Deserialize, TypeUuid)]
+#[uuid = "3C1B7E0A-6D0E-4C52-9F8B-2E6C4B1A9D57"]
+pub struct GoapAsset {
+    pub goals: HashMap<String, GoapState>,
+    pub actions: Vec<GoapAction>,
+}
+
+#[derive(Default)]
+pub struct GoapAssetLoader;
+
+impl AssetLoader for GoapAssetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let asset = ron::de::from_bytes::<GoapAsset>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(asset));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["goap.ron"]
+    }
+}
+
+pub fn satisfies(state: &GoapState, conditions: &GoapState) -> bool {
+    conditions
+        .iter()
+        .all(|(fact, value)| state.get(fact).copied().unwrap_or(false) == *value)
+}
+
+struct Candidate {
+    cost: f32,
+    estimate: f32,
+    state: GoapState,
+    actions: Vec<usize>,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    // lowest cost first
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.cost + other.estimate).total_cmp(&(self.cost + self.estimate))
+    }
+}
+
+/// Lower bound of the cost left, so A* finds the cheapest plan
+struct Heuristic {
+    /// Cheapest action
+    min_cost: f32,
+    /// Most facts a single action sets
+    max_effects: usize,
+}
+
+impl Heuristic {
+    fn new(actions: &[GoapAction]) -> Self {
+        Self {
+            min_cost: actions
+                .iter()
+                .map(|action| action.cost.max(0.0))
+                .fold(f32::INFINITY, f32::min),
+            max_effects: actions
+                .iter()
+                .map(|action| action.effects.len())
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    // unmet goal facts need at least this many actions, each costing at least `min_cost`
+    fn estimate(&self, state: &GoapState, goal: &GoapState) -> f32 {
+        let unmet = goal
+            .iter()
+            .filter(|(fact, value)| state.get(*fact).copied().unwrap_or(false) != **value)
+            .count();
+        if unmet == 0 || self.max_effects == 0 {
+            return 0.0;
+        }
+        let actions = (unmet + self.max_effects - 1) / self.max_effects;
+        actions as f32 * self.min_cost
+    }
+}
+
+/// Cheapest sequence of actions (indices into `actions`) reaching `goal`, using A*
+pub fn plan(
+    state: &GoapState,
+    goal: &GoapState,
+    actions: &[GoapAction],
+    max_depth: usize,
+) -> Option<Vec<usize>> {
+    let heuristic = Heuristic::new(actions);
+    let mut open = BinaryHeap::new();
+    let mut visited: HashMap<GoapState, f32> = HashMap::default();
+    open.push(Candidate {
+        cost: 0.0,
+        estimate: heuristic.estimate(state, goal),
+        state: state.clone(),
+        actions: vec![],
+    });
+
+    while let Some(candidate) = open.pop() {
+        if satisfies(&candidate.state, goal) {
+            return Some(candidate.actions);
+        }
+        if candidate.actions.len() >= max_depth {
+            continue;
+        }
+        for (index, action) in actions.iter().enumerate() {
+            if !action.applies(&candidate.state) {
+                continue;
+            }
+            let state = action.apply(&candidate.state);
+            let cost = candidate.cost + action.cost.max(0.0);
+            if visited.get(&state).map_or(false, |known| *known <= cost) {
+                continue;
+            }
+            visited.insert(state.clone(), cost);
+            let mut steps = candidate.actions.clone();
+            steps.push(index);
+            open.push(Candidate {
+                cost,
+                estimate: heuristic.estimate(&state, goal),
+                state,
+                actions: steps,
+            });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(facts: &[(&str, bool)]) -> GoapState {
+        facts
+            .iter()
+            .map(|(fact, value)| (fact.to_string(), *value))
+            .collect()
+    }
+
+    fn action(
+        name: &str,
+        cost: f32,
+        preconditions: &[(&str, bool)],
+        effects: &[(&str, bool)],
+    ) -> GoapAction {
+        GoapAction {
+            name: name.into(),
+            cost,
+            preconditions: state(preconditions),
+            effects: state(effects),
+            subtree: format!("{}.bht.ron", name),
+        }
+    }
+
+    fn names(actions: &[GoapAction], plan: Option<Vec<usize>>) -> Option<Vec<&str>> {
+        plan.map(|plan| {
+            plan.iter()
+                .map(|index| actions[*index].name.as_str())
+                .collect()
+        })
+    }
+
+    #[test]
+    fn picks_cheapest_over_shortest() {
+        let actions = vec![
+            action("buy_food", 10.0, &[], &[("fed", true)]),
+            action("gather", 1.0, &[], &[("has_berries", true)]),
+            action(
+                "eat_berries",
+                1.0,
+                &[("has_berries", true)],
+                &[("fed", true)],
+            ),
+        ];
+        let plan = plan(&state(&[]), &state(&[("fed", true)]), &actions, 4);
+        assert_eq!(names(&actions, plan), Some(vec!["gather", "eat_berries"]));
+    }
+
+    #[test]
+    fn uses_every_effect_of_an_action() {
+        let actions = vec![
+            action("get_axe", 1.0, &[], &[("has_axe", true)]),
+            action("get_wood", 1.0, &[], &[("has_wood", true)]),
+            action(
+                "visit_shed",
+                1.5,
+                &[],
+                &[("has_axe", true), ("has_wood", true)],
+            ),
+            action(
+                "chop",
+                1.0,
+                &[("has_axe", true), ("has_wood", true)],
+                &[("firewood", true), ("has_wood", false)],
+            ),
+        ];
+        let plan = plan(&state(&[]), &state(&[("firewood", true)]), &actions, 4);
+        assert_eq!(names(&actions, plan), Some(vec!["visit_shed", "chop"]));
+    }
+
+    #[test]
+    fn no_plan_without_a_path_to_the_goal() {
+        let actions = vec![
+            action("gather", 1.0, &[], &[("has_berries", true)]),
+            action("eat", 1.0, &[("has_knife", true)], &[("fed", true)]),
+        ];
+        let plan = plan(&state(&[]), &state(&[("fed", true)]), &actions, 8);
+        assert_eq!(plan, None);
+    }
+
+    #[test]
+    fn goal_already_met_needs_no_actions() {
+        let actions = vec![action("eat", 1.0, &[], &[("fed", true)])];
+        let plan = plan(
+            &state(&[("fed", true)]),
+            &state(&[("fed", true)]),
+            &actions,
+            4,
+        );
+        assert_eq!(plan, Some(vec![]));
+    }
+
+    #[test]
+    fn max_depth_cuts_longer_plans() {
+        let actions = vec![
+            action("a", 1.0, &[], &[("one", true)]),
+            action("b", 1.0, &[("one", true)], &[("two", true)]),
+            action("c", 1.0, &[("two", true)], &[("three", true)]),
+        ];
+        let goal = state(&[("three", true)]);
+        assert_eq!(plan(&state(&[]), &goal, &actions, 2), None);
+        assert_eq!(
+            names(&actions, plan(&state(&[]), &goal, &actions, 3)),
+            Some(vec!["a", "b", "c"])
+        );
+    }
+}
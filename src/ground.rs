@@ -0,0 +1,293 @@
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    render::{
+        mesh::{Indices, VertexAttributeValues},
+        primitives::Aabb,
+        render_resource::PrimitiveTopology,
+    },
+    transform::TransformSystem,
+};
+
+pub struct GroundPlugin;
+
+impl Plugin for GroundPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Ground>()
+            .register_type::<GroundSnap>()
+            .add_system(mark_ground_meshes)
+            .add_system(
+                snap.in_base_set(CoreSet::PostUpdate)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Marks an entity (usually a scene root) whose meshes are walkable ground
+#[derive(Default, Debug, Component, Reflect, Clone)]
+#[reflect(Component)]
+pub struct Ground;
+
+/// Mesh found under a `Ground` hierarchy, used by ground queries
+#[derive(Default, Debug, Component)]
+pub struct GroundMesh;
+
+/// Keeps an entity on top of the ground
+#[derive(Debug, Component, Reflect, Clone)]
+#[reflect(Component)]
+pub struct GroundSnap {
+    /// Steepest walkable slope, in degrees
+    pub max_slope: f32,
+    /// Highest step up from the last position, anything taller blocks the way
+    pub max_step: f32,
+    /// Tilt the entity to match the ground normal
+    pub align: bool,
+    /// Height above the entity from where the ground is probed
+    pub probe: f32,
+    /// Last valid position, in local space
+    pub last: Option<Vec3>,
+}
+
+impl Default for GroundSnap {
+    fn default() -> Self {
+        Self {
+            max_slope: 45.0,
+            max_step: 0.5,
+            align: false,
+            probe: 2.0,
+            last: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GroundHit {
+    pub entity: Entity,
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub distance: f32,
+}
+
+impl GroundHit {
+    /// Angle between the ground normal and up, in degrees
+    pub fn slope(&self) -> f32 {
+        self.normal.angle_between(Vec3::Y).to_degrees()
+    }
+}
+
+#[derive(SystemParam)]
+pub struct GroundQuery<'w, 's> {
+    meshes: Res<'w, Assets<Mesh>>,
+    grounds: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Handle<Mesh>,
+            &'static GlobalTransform,
+            Option<&'static Aabb>,
+        ),
+        With<GroundMesh>,
+    >,
+}
+
+impl<'w, 's> GroundQuery<'w, 's> {
+    /// Closest ground hit along a ray
+    pub fn cast(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<GroundHit> {
+        let direction = direction.normalize_or_zero();
+        if direction == Vec3::ZERO {
+            return None;
+        }
+
+        let mut closest: Option<GroundHit> = None;
+        for (entity, mesh, transform, aabb) in &self.grounds {
+            let Some(mesh) = self.meshes.get(mesh) else {
+                continue;
+            };
+
+            // cast in mesh space
+            let world_from_local = transform.affine();
+            let local_from_world = world_from_local.inverse();
+            let local_origin = local_from_world.transform_point3(origin);
+            let local_direction = local_from_world.transform_vector3(direction);
+
+            if let Some(aabb) = aabb {
+                if !ray_aabb(local_origin, local_direction, aabb) {
+                    continue;
+                }
+            }
+
+            if let Some((local_point, local_normal)) = ray_mesh(local_origin, local_direction, mesh)
+            {
+                let point = world_from_local.transform_point3(local_point);
+                let distance = point.distance(origin);
+                if distance > max_distance {
+                    continue;
+                }
+                if closest.map_or(true, |hit| distance < hit.distance) {
+                    // normals transform with the inverse transpose
+                    let normal = local_from_world
+                        .matrix3
+                        .transpose()
+                        .mul_vec3a(local_normal.into());
+                    let mut normal = Vec3::from(normal).normalize_or_zero();
+                    // always report the side facing the ray
+                    if normal.dot(direction) > 0.0 {
+                        normal = -normal;
+                    }
+                    closest = Some(GroundHit {
+                        entity,
+                        point,
+                        normal,
+                        distance,
+                    });
+                }
+            }
+        }
+        closest
+    }
+
+    /// Ground right below a position, probing from `probe` units above it
+    pub fn height_at(&self, position: Vec3, probe: f32) -> Option<GroundHit> {
+        self.cast(position + Vec3::Y * probe, Vec3::NEG_Y, f32::MAX)
+    }
+}
+
+fn ray_aabb(origin: Vec3, direction: Vec3, aabb: &Aabb) -> bool {
+    let min = Vec3::from(aabb.min());
+    let max = Vec3::from(aabb.max());
+    let inv = direction.recip();
+    let t0 = (min - origin) * inv;
+    let t1 = (max - origin) * inv;
+    let near = t0.min(t1).max_element();
+    let far = t0.max(t1).min_element();
+    far >= near.max(0.0)
+}
+
+fn ray_mesh(origin: Vec3, direction: Vec3, mesh: &Mesh) -> Option<(Vec3, Vec3)> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return None;
+    }
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return None;
+    };
+
+    let triangle = |a: usize, b: usize, c: usize| -> Option<(f32, Vec3)> {
+        let a = Vec3::from(*positions.get(a)?);
+        let b = Vec3::from(*positions.get(b)?);
+        let c = Vec3::from(*positions.get(c)?);
+        ray_triangle(origin, direction, a, b, c)
+    };
+
+    let mut closest: Option<(f32, Vec3)> = None;
+    let mut test = |hit: Option<(f32, Vec3)>| {
+        if let Some((t, normal)) = hit {
+            if closest.map_or(true, |(closest_t, _)| t < closest_t) {
+                closest = Some((t, normal));
+            }
+        }
+    };
+    match mesh.indices() {
+        Some(Indices::U16(indices)) => {
+            for tri in indices.chunks_exact(3) {
+                test(triangle(tri[0] as usize, tri[1] as usize, tri[2] as usize));
+            }
+        }
+        Some(Indices::U32(indices)) => {
+            for tri in indices.chunks_exact(3) {
+                test(triangle(tri[0] as usize, tri[1] as usize, tri[2] as usize));
+            }
+        }
+        None => {
+            for i in (0..positions.len() / 3).map(|i| i * 3) {
+                test(triangle(i, i + 1, i + 2));
+            }
+        }
+    }
+
+    closest.map(|(t, normal)| (origin + direction * t, normal))
+}
+
+// Möller–Trumbore, returns ray distance and face normal
+fn ray_triangle(origin: Vec3, direction: Vec3, a: Vec3, b: Vec3, c: Vec3) -> Option<(f32, Vec3)> {
+    let ab = b - a;
+    let ac = c - a;
+    let p = direction.cross(ac);
+    let det = ab.dot(p);
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let s = origin - a;
+    let u = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(ab);
+    let v = direction.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = ac.dot(q) * inv_det;
+    if t < 0.0 {
+        return None;
+    }
+    Some((t, ab.cross(ac).normalize_or_zero()))
+}
+
+// Tag every mesh under a Ground hierarchy, scenes spawn their meshes a few frames later
+fn mark_ground_meshes(
+    mut commands: Commands,
+    grounds: Query<Entity, With<Ground>>,
+    children: Query<&Children>,
+    meshes: Query<(), (With<Handle<Mesh>>, Without<GroundMesh>)>,
+) {
+    for ground in &grounds {
+        for entity in std::iter::once(ground).chain(children.iter_descendants(ground)) {
+            if meshes.contains(entity) {
+                commands.entity(entity).insert(GroundMesh);
+            }
+        }
+    }
+}
+
+pub fn snap(
+    mut snaps: Query<(&mut Transform, &mut GroundSnap, Option<&Parent>)>,
+    parents: Query<&GlobalTransform>,
+    ground: GroundQuery,
+) {
+    for (mut transform, mut snap, parent) in &mut snaps {
+        let world_from_parent = parent
+            .and_then(|parent| parents.get(parent.get()).ok())
+            .map(|parent| parent.affine())
+            .unwrap_or_default();
+        let position = world_from_parent.transform_point3(transform.translation);
+
+        let Some(hit) = ground.height_at(position, snap.probe) else {
+            continue;
+        };
+
+        // too steep or too tall, go back to the last walkable position
+        let step = snap.last.map_or(0.0, |last| {
+            hit.point.y - world_from_parent.transform_point3(last).y
+        });
+        if hit.slope() > snap.max_slope || step > snap.max_step {
+            if let Some(last) = snap.last {
+                transform.translation = last;
+            }
+            continue;
+        }
+
+        let snapped = Vec3::new(position.x, hit.point.y, position.z);
+        transform.translation = world_from_parent.inverse().transform_point3(snapped);
+        snap.last = Some(transform.translation);
+
+        if snap.align {
+            let (yaw, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
+            transform.rotation =
+                Quat::from_rotation_arc(Vec3::Y, hit.normal) * Quat::from_rotation_y(yaw);
+        }
+    }
+}
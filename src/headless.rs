@@ -0,0 +1,74 @@
+use bevy::{
+    app::AppExit,
+    asset::AssetPlugin,
+    gltf::GltfPlugin,
+    log::LogPlugin,
+    pbr::{CubemapVisibleEntities, NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    render::{
+        mesh::MeshPlugin,
+        primitives::{Aabb, CubemapFrusta, Frustum},
+        view::VisibleEntities,
+    },
+    scene::ScenePlugin,
+    time::TimePlugin,
+};
+
+/// Runs the simulation without a window or renderer, on top of `MinimalPlugins`.
+/// Scenes are still loaded and spawned, so hierarchies and animations work.
+#[derive(Default)]
+pub struct HeadlessPlugin {
+    /// Exit after this many frames, run forever if not set
+    pub frames: Option<u32>,
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<TimePlugin>() {
+            app.add_plugins(MinimalPlugins);
+        }
+        app.add_plugin(LogPlugin::default())
+            .add_plugin(TransformPlugin)
+            .add_plugin(HierarchyPlugin)
+            .add_plugin(AssetPlugin {
+                watch_for_changes: true,
+                ..default()
+            })
+            .add_plugin(ScenePlugin)
+            .add_plugin(MeshPlugin)
+            .add_plugin(AnimationPlugin)
+            .add_plugin(GltfPlugin)
+            // assets and components found in glTF scenes, usually added by the renderer
+            .add_asset::<StandardMaterial>()
+            .add_asset::<Image>()
+            .register_type::<Handle<Mesh>>()
+            .register_type::<Handle<StandardMaterial>>()
+            .register_type::<Visibility>()
+            .register_type::<ComputedVisibility>()
+            .register_type::<Aabb>()
+            .register_type::<NotShadowCaster>()
+            .register_type::<NotShadowReceiver>()
+            .register_type::<PointLight>()
+            .register_type::<SpotLight>()
+            .register_type::<DirectionalLight>()
+            .register_type::<CubemapVisibleEntities>()
+            .register_type::<CubemapFrusta>()
+            .register_type::<Frustum>()
+            .register_type::<VisibleEntities>();
+
+        if let Some(frames) = self.frames {
+            app.add_system(exit_after(frames).in_base_set(CoreSet::Last));
+        }
+    }
+}
+
+fn exit_after(frames: u32) -> impl FnMut(EventWriter<AppExit>) {
+    let mut frame = 0;
+    move |mut exit: EventWriter<AppExit>| {
+        frame += 1;
+        if frame >= frames {
+            info!("Headless: exiting after {} frames", frame);
+            exit.send(AppExit);
+        }
+    }
+}
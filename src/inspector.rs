@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_inspector, egui};
+use simula_inspector::{Inspector, InspectorPlugin, Inspectors};
+use std::marker::PhantomData;
+
+/// Lists a resource in the inspector menu, its window shows when checked. Does nothing
+/// without `InspectorPlugin`, so add it after that one.
+pub struct ResourceMenuPlugin<T>(PhantomData<T>);
+
+impl<T> Default for ResourceMenuPlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: Resource + Reflect> Plugin for ResourceMenuPlugin<T> {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<InspectorPlugin>() {
+            return;
+        }
+        app.init_resource::<ResourceMenu<T>>();
+        app.world
+            .resource_mut::<Inspectors>()
+            .inspectors
+            .push(Inspector {
+                menu_ui: menu_ui::<T>,
+                window_ui: window_ui::<T>,
+            });
+    }
+}
+
+#[derive(Resource)]
+struct ResourceMenu<T> {
+    open: bool,
+    _resource: PhantomData<T>,
+}
+
+impl<T> Default for ResourceMenu<T> {
+    fn default() -> Self {
+        Self {
+            open: false,
+            _resource: PhantomData,
+        }
+    }
+}
+
+fn title<T>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+fn menu_ui<T: Resource + Reflect>(ui: &mut egui::Ui, world: &mut World) {
+    let mut menu = world.resource_mut::<ResourceMenu<T>>();
+    ui.checkbox(&mut menu.open, title::<T>());
+}
+
+fn window_ui<T: Resource + Reflect>(context: &mut egui::Context, world: &mut World) {
+    let mut open = world.resource::<ResourceMenu<T>>().open;
+    if !open {
+        return;
+    }
+    egui::Window::new(title::<T>())
+        .open(&mut open)
+        .show(context, |ui| {
+            bevy_inspector::ui_for_resource::<T>(world, ui);
+        });
+    world.resource_mut::<ResourceMenu<T>>().open = open;
+}
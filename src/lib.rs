@@ -0,0 +1,27 @@
+pub mod behaviors;
+pub mod cli;
+pub mod clock;
+pub mod day_night;
+pub mod deterministic;
+pub mod ecosystem;
+pub mod export;
+pub mod goap;
+pub mod ground;
+pub mod headless;
+pub mod inspector;
+pub mod lod;
+pub mod manifest;
+pub mod memory;
+pub mod needs;
+pub mod perception;
+pub mod profile;
+pub mod random;
+pub mod region;
+pub mod sandbox;
+pub mod schedule;
+pub mod smart_object;
+pub mod snapshot;
+pub mod stimulus;
+#[cfg(test)]
+mod testing;
+pub mod trace;
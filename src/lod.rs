@@ -0,0 +1,283 @@
+use crate::behaviors::npc::{NPCBehavior, NPCTrees, SpawnOwned};
+use bevy::{
+    core::FrameCount,
+    ecs::system::SystemParam,
+    math::Vec3A,
+    prelude::*,
+    render::primitives::{Frustum, Sphere},
+    utils::HashMap,
+};
+use simula_behavior::prelude::*;
+use simula_camera::orbitcam::OrbitCamera;
+
+/// Ticks behavior trees of NPCs far from the `OrbitCamera`, or out of its view, less often.
+/// Trees skip frames by having their cursors parked. `Wait`, `Delay` and `Timeout` measure
+/// against the elapsed time of the app, so they end on the first tick past their duration.
+/// Nodes stepping by the frame time use `LodTime` to get all the time since the last tick.
+pub struct BehaviorLodPlugin;
+
+impl Plugin for BehaviorLodPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BehaviorLodSettings>().add_systems(
+            (assign_tiers, park_cursors, animate)
+                .chain()
+                .in_base_set(CoreSet::PreUpdate),
+        );
+    }
+}
+
+/// What NPC animation does at a tier
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LodAnim {
+    /// Plays every frame
+    Full,
+    /// Advances only on frames the tree ticks, catching up the frames skipped
+    Reduced,
+    /// Holds the current pose
+    Frozen,
+}
+
+#[derive(Debug, Clone)]
+pub struct LodTier {
+    /// NPCs up to this far from the camera use this tier
+    pub distance: f32,
+    /// Tick every this many frames
+    pub interval: u32,
+    pub anim: LodAnim,
+}
+
+/// Tiers, nearest first. NPCs past the last tier's distance use the last tier.
+#[derive(Debug, Clone, Resource)]
+pub struct BehaviorLodSettings {
+    pub tiers: Vec<LodTier>,
+    /// Tier of NPCs the camera doesn't see, if farther than this
+    pub offscreen: usize,
+    /// Radius of the sphere around an NPC checked against the view
+    pub radius: f32,
+}
+
+impl Default for BehaviorLodSettings {
+    fn default() -> Self {
+        Self {
+            tiers: vec![
+                LodTier {
+                    distance: 15.0,
+                    interval: 1,
+                    anim: LodAnim::Full,
+                },
+                LodTier {
+                    distance: 40.0,
+                    interval: 4,
+                    anim: LodAnim::Reduced,
+                },
+                LodTier {
+                    distance: f32::INFINITY,
+                    interval: 30,
+                    anim: LodAnim::Frozen,
+                },
+            ],
+            offscreen: 2,
+            radius: 1.0,
+        }
+    }
+}
+
+/// Tier of a behavior tree, on the tree entity
+#[derive(Debug, Default, Clone, Component)]
+pub struct BehaviorLod {
+    pub tier: usize,
+    pub interval: u32,
+    /// The tree runs this frame
+    pub ticking: bool,
+    /// Seconds since the tree last ran, including this frame
+    pub elapsed: f32,
+}
+
+/// Cursor of a tree skipping this frame
+#[derive(Debug, Component)]
+pub struct ParkedCursor(BehaviorCursor);
+
+/// Speed factor applied to an animation player by its tier, on the player
+#[derive(Debug, Component)]
+pub struct LodAnimSpeed(f32);
+
+/// Frame time as seen by a behavior tree, longer for trees that skipped frames
+#[derive(SystemParam)]
+pub struct LodTime<'w, 's> {
+    time: Res<'w, Time>,
+    lods: Query<'w, 's, &'static BehaviorLod>,
+}
+
+impl<'w, 's> LodTime<'w, 's> {
+    /// Seconds since the tree last ran, none on frames it skips
+    pub fn delta(&self, tree: Option<Entity>) -> f32 {
+        tree.and_then(|tree| self.lods.get(tree).ok())
+            .map_or(self.time.delta_seconds(), |lod| {
+                if lod.ticking {
+                    lod.elapsed
+                } else {
+                    0.0
+                }
+            })
+    }
+}
+
+// nearest NPC of each tree decides its tier
+fn assign_tiers(
+    mut commands: Commands,
+    settings: Res<BehaviorLodSettings>,
+    time: Res<Time>,
+    frame: Res<FrameCount>,
+    cameras: Query<(&GlobalTransform, &Frustum), With<OrbitCamera>>,
+    npcs: Query<(&SpawnOwned, &GlobalTransform), Without<Parent>>,
+    trees: NPCTrees,
+    mut lods: Query<(Entity, Option<&mut BehaviorLod>), With<BehaviorTree<NPCBehavior>>>,
+) {
+    // without a camera, or tiers, every tree runs every frame
+    let mut nearest: HashMap<Entity, usize> = HashMap::default();
+    let last = settings.tiers.len().checked_sub(1);
+    if let (Ok((camera, frustum)), Some(last)) = (cameras.get_single(), last) {
+        let eye = camera.translation();
+        for (owned, transform) in &npcs {
+            let Some(tree) = trees.tree(owned) else {
+                continue;
+            };
+            let position = transform.translation();
+            let distance = position.distance(eye);
+            let mut tier = settings
+                .tiers
+                .iter()
+                .position(|tier| distance <= tier.distance)
+                .unwrap_or(last);
+            let sphere = Sphere {
+                center: Vec3A::from(position),
+                radius: settings.radius,
+            };
+            if !frustum.intersects_sphere(&sphere, true) {
+                tier = tier.max(settings.offscreen.min(last));
+            }
+            nearest
+                .entry(tree)
+                .and_modify(|nearest| *nearest = (*nearest).min(tier))
+                .or_insert(tier);
+        }
+    }
+
+    let delta = time.delta_seconds();
+    for (tree, lod) in &mut lods {
+        // trees without NPCs run every frame
+        let tier = nearest.get(&tree).copied().unwrap_or(0);
+        let interval = settings
+            .tiers
+            .get(tier)
+            .map_or(1, |tier| tier.interval.max(1));
+        // spread trees of a tier over its frames
+        let ticking = frame.0.wrapping_add(tree.index()) % interval == 0;
+        match lod {
+            Some(mut lod) => {
+                if lod.ticking {
+                    lod.elapsed = 0.0;
+                }
+                lod.elapsed += delta;
+                lod.tier = tier;
+                lod.interval = interval;
+                lod.ticking = ticking;
+            }
+            None => {
+                commands.entity(tree).insert(BehaviorLod {
+                    tier,
+                    interval,
+                    ticking,
+                    elapsed: delta,
+                });
+            }
+        }
+    }
+}
+
+// trees skipping the frame keep their place, without a cursor to run from
+fn park_cursors(
+    mut commands: Commands,
+    lods: Query<&BehaviorLod>,
+    cursors: Query<(Entity, &BehaviorNode, &BehaviorCursor)>,
+    parked: Query<(
+        Entity,
+        &BehaviorNode,
+        &ParkedCursor,
+        Option<&BehaviorCursor>,
+    )>,
+) {
+    let ticking = |node: &BehaviorNode| {
+        node.tree
+            .and_then(|tree| lods.get(tree).ok())
+            .map_or(true, |lod| lod.ticking)
+    };
+    for (entity, node, cursor) in &cursors {
+        if !ticking(node) {
+            commands
+                .entity(entity)
+                .remove::<BehaviorCursor>()
+                .insert(ParkedCursor(cursor.clone()));
+        }
+    }
+    for (entity, node, parked, cursor) in &parked {
+        if ticking(node) {
+            let mut entity = commands.entity(entity);
+            entity.remove::<ParkedCursor>();
+            // a cursor given to the node while parked is newer, it stays
+            if cursor.is_none() {
+                entity.insert(parked.0.clone());
+            }
+        }
+    }
+}
+
+// players under an NPC follow the tier of its tree
+fn animate(
+    mut commands: Commands,
+    settings: Res<BehaviorLodSettings>,
+    lods: Query<&BehaviorLod>,
+    npcs: Query<&SpawnOwned, Without<Parent>>,
+    parents: Query<&Parent>,
+    trees: NPCTrees,
+    mut players: Query<(Entity, &mut AnimationPlayer, Option<&mut LodAnimSpeed>)>,
+) {
+    for (entity, mut player, applied) in &mut players {
+        let Some(lod) = std::iter::once(entity)
+            .chain(parents.iter_ancestors(entity))
+            .find_map(|ancestor| npcs.get(ancestor).ok())
+            .and_then(|owned| trees.tree(owned))
+            .and_then(|tree| lods.get(tree).ok())
+        else {
+            continue;
+        };
+        let Some(tier) = settings.tiers.get(lod.tier) else {
+            continue;
+        };
+        let (paused, speed) = match tier.anim {
+            LodAnim::Full => (false, 1.0),
+            LodAnim::Reduced => (!lod.ticking, lod.interval as f32),
+            LodAnim::Frozen => (true, 1.0),
+        };
+        // only touch players that need it, so they aren't marked changed every frame
+        if player.is_paused() != paused {
+            if paused {
+                player.pause();
+            } else {
+                player.resume();
+            }
+        }
+        // scale the speed the player was given, like by an `Anim` node
+        let factor = applied.as_ref().map_or(1.0, |applied| applied.0);
+        if factor != speed {
+            let own = player.speed() / factor;
+            player.set_speed(own * speed);
+            match applied {
+                Some(mut applied) => applied.0 = speed,
+                None => {
+                    commands.entity(entity).insert(LodAnimSpeed(speed));
+                }
+            }
+        }
+    }
+}
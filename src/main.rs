@@ -1,12 +1,30 @@
-use behaviors::{
-    bioma::{BiomaBehavior, BiomaBehaviorPlugin},
-    npc::{NPCBehavior, NPCBehaviorPlugin},
+use autonpcs::{
+    behaviors::{
+        bioma::BiomaBehavior,
+        npc::{gizmos::NPCGizmosPlugin, NPCBehavior},
+    },
+    cli::Cli,
+    clock::{ClockText, SimClock},
+    day_night::Sun,
+    deterministic::DeterministicPlugin,
+    export::ExportInspectorPlugin,
+    ground::Ground,
+    headless::HeadlessPlugin,
+    lod::BehaviorLodPlugin,
+    manifest::{ManifestBehaviorPlugin, ManifestEntry, ManifestMode, ManifestPlugin},
+    random::RandomSeed,
+    region::{Region, RegionShape},
+    sandbox::SandboxPlugin,
+    smart_object::{SmartAdvert, SmartObject, SmartSlot},
+    snapshot::SnapshotPlugin,
+    trace::{TraceRecorder, TraceReplayPlugin},
 };
 use bevy::{
     diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
     prelude::*,
     window::PresentMode,
 };
+use clap::Parser;
 use simula_action::ActionPlugin;
 use simula_behavior::prelude::*;
 use simula_camera::orbitcam::*;
@@ -16,127 +34,178 @@ use simula_viz::{
     grid::{Grid, GridBundle, GridPlugin},
     lines::LinesPlugin,
 };
-
-mod behaviors;
+use std::time::Duration;
 
 fn main() {
-    App::new()
-        .insert_resource(Msaa::Sample4)
-        .insert_resource(ClearColor(Color::rgb(0.105, 0.10, 0.13)))
-        .add_plugins(
-            DefaultPlugins
-                .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        title: "Autonomous NPCs - Sandbox".to_string(),
-                        resolution: (1920., 1080.).into(),
-                        present_mode: PresentMode::AutoVsync,
-                        fit_canvas_to_parent: true,
-                        prevent_default_event_handling: false,
+    let cli = Cli::parse();
+
+    let autoload = |trees: &[String]| {
+        trees
+            .iter()
+            .map(|tree| ManifestEntry::new(tree.as_str(), ManifestMode::Autoload))
+            .collect::<Vec<_>>()
+    };
+    let present_mode = if cli.no_vsync {
+        PresentMode::AutoNoVsync
+    } else {
+        PresentMode::AutoVsync
+    };
+    let mut clock = SimClock::default();
+    if let Some(time_scale) = cli.time_scale {
+        clock.time_scale = time_scale;
+    }
+
+    let mut app = App::new();
+    app.insert_resource(RandomSeed(cli.seed))
+        .insert_resource(clock);
+    if cli.headless {
+        app.add_plugin(HeadlessPlugin { frames: cli.frames });
+    } else {
+        app.insert_resource(Msaa::Sample4)
+            .insert_resource(ClearColor(Color::rgb(0.105, 0.10, 0.13)))
+            .add_plugins(
+                DefaultPlugins
+                    .set(WindowPlugin {
+                        primary_window: Some(Window {
+                            title: "Autonomous NPCs - Sandbox".to_string(),
+                            resolution: (cli.width, cli.height).into(),
+                            present_mode,
+                            fit_canvas_to_parent: true,
+                            prevent_default_event_handling: false,
+                            ..default()
+                        }),
                         ..default()
+                    })
+                    .set(AssetPlugin {
+                        watch_for_changes: true,
+                        ..Default::default()
                     }),
-                    ..default()
-                })
-                .set(AssetPlugin {
-                    watch_for_changes: true,
-                    ..Default::default()
-                }),
-        )
-        .add_plugin(InspectorPlugin)
-        .add_plugin(WorldInspectorPlugin)
-        .add_plugin(ActionPlugin)
-        .add_plugin(FrameTimeDiagnosticsPlugin::default())
-        .add_plugin(OrbitCameraPlugin)
-        .add_plugin(LinesPlugin)
-        .add_plugin(AxesPlugin)
-        .add_plugin(GridPlugin)
-        .add_system(debug_info)
+            );
+        if !cli.no_inspector {
+            app.add_plugin(InspectorPlugin)
+                .add_plugin(WorldInspectorPlugin);
+        }
+        app.add_plugin(ActionPlugin)
+            .add_plugin(FrameTimeDiagnosticsPlugin::default())
+            .add_plugin(OrbitCameraPlugin)
+            .add_plugin(LinesPlugin)
+            .add_plugin(AxesPlugin)
+            .add_plugin(GridPlugin)
+            .add_system(debug_info)
+            .add_startup_system(view_setup);
+    }
+    if cli.deterministic {
+        app.add_plugin(DeterministicPlugin {
+            step: Duration::from_secs_f64(1.0 / cli.step_rate),
+            realtime: !cli.headless,
+        });
+    }
+    app.add_plugin(SandboxPlugin)
         .add_startup_system(scene_setup)
-        // Behavior setup
-        .add_plugin(BehaviorPlugin)
-        // BiomaBehavior setup
-        .add_plugin(BiomaBehaviorPlugin)
-        .add_plugin(BehaviorServerPlugin::<BiomaBehavior>::default())
-        .add_plugin(BehaviorInspectorPlugin::<BiomaBehavior>::default())
-        .add_startup_system(behavior_setup::<BiomaBehavior>)
-        // NPCBehavior setup
-        .add_plugin(NPCBehaviorPlugin)
-        .add_plugin(BehaviorServerPlugin::<NPCBehavior>::default())
-        .add_plugin(BehaviorInspectorPlugin::<NPCBehavior>::default())
-        .add_startup_system(behavior_setup::<NPCBehavior>)
-        .run();
-}
-
-fn behavior_setup<T: BehaviorFactory>(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    behavior_server: Res<protocol::BehaviorServer<T>>,
-    mut behavior_trackers: ResMut<BehaviorTrackers<T>>,
-) {
-    // load debug behaviors
-    let behaviors = [
-        "bht/d/delay",
-        "bht/d/gate_true",
-        "bht/d/gate_blackboard",
-        "bht/d/all",
-        "bht/d/any_repeat",
-        "bht/d/any_subtree",
-        "bht/d/any",
-        "bht/d/sequence",
-        "bht/d/defaults",
-        "bht/d/repeater",
-        "bht/d/repeat_repeater",
-        "bht/d/subtree_gate",
-        "bht/d/zero_timers",
-        "?dynamic_01",
-        "?dynamic_02",
-        "?dynamic_03",
-    ];
-    for behavior in behaviors.into_iter() {
-        // create a behavior tree that will automatically load and run
-        if let Some(behavior) = behavior.strip_prefix("*") {
-            // get a handle to a behavior asset from asset server
-            let behavior_handle: Handle<BehaviorAsset<T>> =
-                asset_server.load(format!("{}.bht.ron", behavior).as_str());
-
-            // create a new entity for the behavior tree
-            commands.spawn((
-                Name::new(format!("BHT: {}", behavior)),
-                behavior_handle.clone(),
-                BehaviorTree::<T>::default(),
-                BehaviorTreeReset::<T>::default(),
-            ));
+        .add_plugin(ManifestPlugin {
+            path: cli.manifest.clone(),
+        })
+        .add_plugin(
+            ManifestBehaviorPlugin::<BiomaBehavior>::new(|manifest| &manifest.bioma)
+                .with_entries(autoload(&cli.run_bioma)),
+        )
+        .add_plugin(
+            ManifestBehaviorPlugin::<NPCBehavior>::new(|manifest| &manifest.npc)
+                .with_entries(autoload(&cli.run)),
+        )
+        .add_plugin(SnapshotPlugin {
+            path: cli.snapshot.clone(),
+            restore: cli.restore,
+            save_on_exit: cli.save_on_exit,
+        });
+    if let Some(path) = &cli.trace {
+        match TraceRecorder::create(path) {
+            Ok(recorder) => {
+                app.insert_resource(recorder);
+            }
+            Err(err) => error!("Trace: cannot create {}: {}", path, err),
         }
-        // create behavior tree without behavior asset, it will dynamically be added later
-        else if let Some(behavior) = behavior.strip_prefix("?") {
-            // create a new entity for the behavior tree, and insert the scope
-            commands.spawn((
-                Name::new(format!("BHT: {}", behavior)),
-                BehaviorTree::<T>::default(),
-            ));
+    }
+    if !cli.headless {
+        app.add_plugin(NPCGizmosPlugin);
+        if !cli.no_lod {
+            app.add_plugin(BehaviorLodPlugin);
         }
-        // do not load just yet, track file and send file name to client
-        else {
-            let file_id = protocol::BehaviorFileId::new();
-            let file_name = protocol::BehaviorFileName(behavior.into());
-            behavior_trackers.insert(
-                file_id.clone(),
-                BehaviorTracker {
-                    file_name: file_name.clone(),
-                    entity: EntityTracker::None,
-                    asset: AssetTracker::None,
-                },
-            );
-            behavior_server
-                .sender
-                .send(protocol::BehaviorProtocolServer::FileName(
-                    file_id, file_name,
-                ))
-                .unwrap();
+        if let Some(path) = &cli.replay {
+            app.add_plugin(TraceReplayPlugin { path: path.clone() });
+        }
+        if !cli.no_inspector {
+            app.add_plugin(BehaviorInspectorPlugin::<BiomaBehavior>::default())
+                .add_plugin(BehaviorInspectorPlugin::<NPCBehavior>::default())
+                .add_plugin(ExportInspectorPlugin);
         }
     }
+    app.insert_resource(cli).run();
 }
 
 fn scene_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    // ground
+    commands
+        .spawn(SceneBundle {
+            scene: asset_server.load("models/metric_plane/metric_plane_8x8.gltf#Scene0"),
+            ..Default::default()
+        })
+        .insert(Ground)
+        .insert(Name::new("Ground: Plane"));
+
+    commands
+        .spawn(SceneBundle {
+            scene: asset_server.load("models/metric_box/metric_box_1x1.gltf#Scene0"),
+            transform: Transform::from_xyz(2.0, 0.0, -2.0),
+            ..Default::default()
+        })
+        .insert(Ground)
+        .insert(Name::new("Ground: Box"));
+
+    // smart objects
+    commands
+        .spawn(SpatialBundle::from_transform(Transform::from_xyz(
+            -2.0, 0.0, 2.0,
+        )))
+        .insert(SmartObject {
+            interaction: "sit".into(),
+            adverts: vec![SmartAdvert {
+                need: "energy".into(),
+                amount: 0.5,
+            }],
+            subtree: "bht/u/sit.bht.ron".into(),
+            slots: vec![
+                SmartSlot::new(Vec3::new(-0.5, 0.0, 0.0)),
+                SmartSlot::new(Vec3::new(0.5, 0.0, 0.0)),
+            ],
+        })
+        .insert(Name::new("Smart: Bench"));
+
+    // regions, addressed by name
+    commands
+        .spawn(SpatialBundle::from_transform(Transform::from_xyz(
+            0.0, 0.0, 3.0,
+        )))
+        .insert(Region {
+            shape: RegionShape::Box {
+                size: Vec2::new(4.0, 2.0),
+            },
+            color: Color::ORANGE,
+        })
+        .insert(Name::new("market"));
+
+    commands
+        .spawn(SpatialBundle::from_transform(Transform::from_xyz(
+            0.0, 0.0, -3.0,
+        )))
+        .insert(Region {
+            shape: RegionShape::Circle { radius: 1.5 },
+            color: Color::GREEN,
+        })
+        .insert(Name::new("spawn_north"));
+}
+
+fn view_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     // grid
     let grid_color = Color::rgb(0.08, 0.06, 0.08);
     commands
@@ -166,15 +235,19 @@ fn scene_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
 
     let theta = std::f32::consts::FRAC_PI_4;
     let light_transform = Mat4::from_euler(EulerRot::ZYX, 0.0, std::f32::consts::FRAC_PI_2, -theta);
-    commands.spawn(DirectionalLightBundle {
-        directional_light: DirectionalLight {
-            color: Color::rgb(1.0, 1.0, 1.0),
-            illuminance: 5000.,
+    commands.spawn((
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                color: Color::rgb(1.0, 1.0, 1.0),
+                illuminance: 5000.,
+                ..Default::default()
+            },
+            transform: Transform::from_matrix(light_transform),
             ..Default::default()
         },
-        transform: Transform::from_matrix(light_transform),
-        ..Default::default()
-    });
+        Sun,
+        Name::new("Sun"),
+    ));
 
     // orbit camera
     commands
@@ -187,30 +260,40 @@ fn scene_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
             ..Default::default()
         });
 
-    // FPS on screen
-    commands.spawn(TextBundle {
-        text: Text {
-            sections: vec![TextSection {
-                value: "\nFPS: ".to_string(),
-                style: TextStyle {
-                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
-                    font_size: 12.0,
-                    color: Color::rgb(0.0, 1.0, 0.0),
+    // FPS and clock on screen
+    let hud_style = TextStyle {
+        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+        font_size: 12.0,
+        color: Color::rgb(0.0, 1.0, 0.0),
+    };
+    commands.spawn((
+        TextBundle {
+            text: Text {
+                sections: vec![
+                    TextSection {
+                        value: "\nFPS: ".to_string(),
+                        style: hud_style.clone(),
+                    },
+                    TextSection {
+                        value: "".to_string(),
+                        style: hud_style,
+                    },
+                ],
+                ..Default::default()
+            },
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(5.0),
+                    right: Val::Px(5.0),
+                    ..Default::default()
                 },
-            }],
-            ..Default::default()
-        },
-        style: Style {
-            position_type: PositionType::Absolute,
-            position: UiRect {
-                bottom: Val::Px(5.0),
-                right: Val::Px(5.0),
                 ..Default::default()
             },
             ..Default::default()
         },
-        ..Default::default()
-    });
+        ClockText,
+    ));
 }
 
 fn debug_info(diagnostics: Res<Diagnostics>, mut query: Query<&mut Text>) {
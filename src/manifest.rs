@@ -0,0 +1,318 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadState, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+use simula_behavior::prelude::*;
+use std::marker::PhantomData;
+
+/// Loads the sandbox manifest, `default.sandbox.ron` unless told otherwise
+pub struct ManifestPlugin {
+    pub path: String,
+}
+
+impl Default for ManifestPlugin {
+    fn default() -> Self {
+        Self {
+            path: "default.sandbox.ron".into(),
+        }
+    }
+}
+
+impl Plugin for ManifestPlugin {
+    fn build(&self, app: &mut App) {
+        let path = self.path.clone();
+        app.add_asset::<SandboxManifest>()
+            .init_asset_loader::<SandboxManifestLoader>()
+            .add_startup_system(
+                move |mut commands: Commands, asset_server: Res<AssetServer>| {
+                    commands.insert_resource(Manifest {
+                        handle: asset_server.load(path.as_str()),
+                    });
+                },
+            );
+    }
+}
+
+/// Spawns and tracks the behaviors of one `BehaviorFactory` type listed in the manifest
+pub struct ManifestBehaviorPlugin<T: BehaviorFactory> {
+    section: fn(&SandboxManifest) -> &[ManifestEntry],
+    extra: Vec<ManifestEntry>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: BehaviorFactory> ManifestBehaviorPlugin<T> {
+    pub fn new(section: fn(&SandboxManifest) -> &[ManifestEntry]) -> Self {
+        Self {
+            section,
+            extra: vec![],
+            _marker: PhantomData,
+        }
+    }
+
+    /// Entries added on top of the manifest ones, like the trees given with `--run`. They
+    /// come up on the first update, without waiting for the manifest.
+    pub fn with_entries(mut self, entries: Vec<ManifestEntry>) -> Self {
+        self.extra = entries;
+        self
+    }
+}
+
+impl<T: BehaviorFactory> Plugin for ManifestBehaviorPlugin<T> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ManifestState::<T> {
+            section: self.section,
+            extra: self.extra.clone(),
+            applied: vec![],
+            extra_applied: false,
+            synced: false,
+            _marker: PhantomData,
+        })
+        .add_system(sync::<T>);
+    }
+}
+
+/// How a manifest entry is brought into the sandbox
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ManifestMode {
+    /// Spawn a tree that loads and runs the file right away
+    Autoload,
+    /// Spawn a tree without an asset, the behavior is added later by a client
+    Dynamic,
+    /// Do not load, send the file name to clients so they can open it
+    #[default]
+    Tracked,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Path without the `.bht.ron` extension, or the tree name of a dynamic entry
+    pub path: String,
+    #[serde(default)]
+    pub mode: ManifestMode,
+}
+
+impl ManifestEntry {
+    pub fn new(path: impl Into<String>, mode: ManifestMode) -> Self {
+        Self {
+            path: path.into(),
+            mode,
+        }
+    }
+}
+
+/// Behavior files to bring up, per `BehaviorFactory` type
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TypeUuid)]
+#[uuid = "3C0E6F1A-8F0B-4C4E-9D52-7A3B5E1C2D90"]
+pub struct SandboxManifest {
+    #[serde(default)]
+    pub bioma: Vec<ManifestEntry>,
+    #[serde(default)]
+    pub npc: Vec<ManifestEntry>,
+}
+
+#[derive(Default)]
+pub struct SandboxManifestLoader;
+
+impl AssetLoader for SandboxManifestLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let asset = ron::de::from_bytes::<SandboxManifest>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(asset));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sandbox.ron"]
+    }
+}
+
+#[derive(Resource)]
+pub struct Manifest {
+    pub handle: Handle<SandboxManifest>,
+}
+
+enum ManifestApplied {
+    Entity(Entity),
+    Tracked(protocol::BehaviorFileId),
+}
+
+#[derive(Resource)]
+pub struct ManifestState<T: BehaviorFactory> {
+    section: fn(&SandboxManifest) -> &[ManifestEntry],
+    extra: Vec<ManifestEntry>,
+    applied: Vec<(ManifestEntry, ManifestApplied)>,
+    extra_applied: bool,
+    synced: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: BehaviorFactory> ManifestState<T> {
+    /// The manifest has been applied at least once, or failed to load
+    pub fn synced(&self) -> bool {
+        self.synced
+    }
+}
+
+// Bring the sandbox in line with the manifest each time it loads or changes
+#[allow(clippy::too_many_arguments)]
+fn sync<T: BehaviorFactory>(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<SandboxManifest>>,
+    manifest: Option<Res<Manifest>>,
+    manifests: Res<Assets<SandboxManifest>>,
+    asset_server: Res<AssetServer>,
+    behavior_server: Res<protocol::BehaviorServer<T>>,
+    mut behavior_trackers: ResMut<BehaviorTrackers<T>>,
+    mut state: ResMut<ManifestState<T>>,
+) {
+    let state = state.as_mut();
+
+    // entries given on the command line come up right away, with or without a manifest
+    if !state.extra_applied {
+        state.extra_applied = true;
+        for entry in state.extra.clone() {
+            if state.applied.iter().any(|(applied, _)| *applied == entry) {
+                continue;
+            }
+            let applied = apply(
+                &mut commands,
+                &asset_server,
+                &behavior_server,
+                &mut behavior_trackers,
+                &entry,
+            );
+            state.applied.push((entry, applied));
+        }
+    }
+
+    let Some(manifest) = manifest else {
+        return;
+    };
+    // nothing to wait for, leave the sandbox with the command line entries
+    if !state.synced && asset_server.get_load_state(&manifest.handle) == LoadState::Failed {
+        error!(
+            "Manifest: cannot load {:?}",
+            asset_server.get_handle_path(&manifest.handle)
+        );
+        state.synced = true;
+        return;
+    }
+    let changed = events.iter().any(|event| match event {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+            *handle == manifest.handle
+        }
+        AssetEvent::Removed { .. } => false,
+    });
+    if !changed {
+        return;
+    }
+    let Some(sandbox) = manifests.get(&manifest.handle) else {
+        return;
+    };
+    let mut entries = (state.section)(sandbox).to_vec();
+    for entry in &state.extra {
+        if !entries.contains(entry) {
+            entries.push(entry.clone());
+        }
+    }
+
+    // removed entries
+    state.applied.retain(|(entry, applied)| {
+        if entries.contains(entry) {
+            return true;
+        }
+        info!("Manifest: removing {}", entry.path);
+        match applied {
+            ManifestApplied::Entity(entity) => {
+                if let Some(entity) = commands.get_entity(*entity) {
+                    entity.despawn_recursive();
+                }
+            }
+            ManifestApplied::Tracked(file_id) => {
+                behavior_trackers.remove(file_id);
+            }
+        }
+        false
+    });
+
+    // new entries
+    for entry in entries {
+        if state.applied.iter().any(|(applied, _)| *applied == entry) {
+            continue;
+        }
+        let applied = apply(
+            &mut commands,
+            &asset_server,
+            &behavior_server,
+            &mut behavior_trackers,
+            &entry,
+        );
+        state.applied.push((entry, applied));
+    }
+    state.synced = true;
+}
+
+fn apply<T: BehaviorFactory>(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    behavior_server: &protocol::BehaviorServer<T>,
+    behavior_trackers: &mut BehaviorTrackers<T>,
+    entry: &ManifestEntry,
+) -> ManifestApplied {
+    info!("Manifest: {:?} {}", entry.mode, entry.path);
+    match entry.mode {
+        // create a behavior tree that will automatically load and run
+        ManifestMode::Autoload => {
+            let behavior_handle: Handle<BehaviorAsset<T>> =
+                asset_server.load(format!("{}.bht.ron", entry.path).as_str());
+            let entity = commands
+                .spawn((
+                    Name::new(format!("BHT: {}", entry.path)),
+                    behavior_handle,
+                    BehaviorTree::<T>::default(),
+                    BehaviorTreeReset::<T>::default(),
+                ))
+                .id();
+            ManifestApplied::Entity(entity)
+        }
+        // create behavior tree without behavior asset, it will dynamically be added later
+        ManifestMode::Dynamic => {
+            let entity = commands
+                .spawn((
+                    Name::new(format!("BHT: {}", entry.path)),
+                    BehaviorTree::<T>::default(),
+                ))
+                .id();
+            ManifestApplied::Entity(entity)
+        }
+        // do not load just yet, track file and send file name to client
+        ManifestMode::Tracked => {
+            let file_id = protocol::BehaviorFileId::new();
+            let file_name = protocol::BehaviorFileName(entry.path.as_str().into());
+            behavior_trackers.insert(
+                file_id.clone(),
+                BehaviorTracker {
+                    file_name: file_name.clone(),
+                    entity: EntityTracker::None,
+                    asset: AssetTracker::None,
+                },
+            );
+            behavior_server
+                .sender
+                .send(protocol::BehaviorProtocolServer::FileName(
+                    file_id.clone(),
+                    file_name,
+                ))
+                .unwrap();
+            ManifestApplied::Tracked(file_id)
+        }
+    }
+}
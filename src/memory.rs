@@ -0,0 +1,356 @@
+use crate::{
+    behaviors::{
+        blackboard::{BlackboardWrite, ScriptFunctionsAppExt},
+        npc::{NPCTrees, SpawnOwned},
+    },
+    perception::{self, Perceived, Perceptions},
+    stimulus::Stimulus,
+};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use rhai::{Dynamic, Engine, ImmutableString, Map, RegisterFn};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+pub struct MemoryPlugin;
+
+impl Plugin for MemoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<NPCMemory>()
+            .register_type::<MemoryFact>()
+            .register_type::<MemoryKind>()
+            .init_resource::<MemoryClock>()
+            .add_event::<Remember>()
+            .add_script_functions(register_script_functions)
+            .add_system(tick.in_base_set(CoreSet::PreUpdate))
+            .add_system(remember_perceptions.after(perception::perceive))
+            .add_system(remember_stimuli)
+            .add_system(remember_events)
+            .add_system(
+                forget
+                    .after(remember_perceptions)
+                    .after(remember_stimuli)
+                    .after(remember_events),
+            )
+            .add_system(write_scope.after(forget));
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Reflect, FromReflect)]
+pub enum MemoryKind {
+    #[default]
+    Sighting,
+    Sound,
+    Location,
+    Interaction,
+}
+
+impl MemoryKind {
+    fn from_str(kind: &str) -> Option<Self> {
+        match kind {
+            "sighting" => Some(Self::Sighting),
+            "sound" => Some(Self::Sound),
+            "location" => Some(Self::Location),
+            "interaction" => Some(Self::Interaction),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Reflect, FromReflect)]
+pub struct MemoryFact {
+    /// Who or what the fact is about, an entity name or a stimulus kind
+    pub subject: String,
+    pub kind: MemoryKind,
+    pub position: Vec3,
+    pub time: f64,
+    /// Confidence at `time`, it fades from there
+    pub confidence: f32,
+    /// Times the fact has been remembered, e.g. how often the NPC got hit
+    pub count: u32,
+}
+
+impl MemoryFact {
+    pub fn new(subject: impl Into<String>, kind: MemoryKind, position: Vec3, time: f64) -> Self {
+        Self {
+            subject: subject.into(),
+            kind,
+            position,
+            time,
+            confidence: 1.0,
+            count: 1,
+        }
+    }
+
+    /// Confidence left at `now`, losing `decay` per second
+    pub fn confidence_at(&self, now: f64, decay: f32) -> f32 {
+        self.confidence - decay * (now - self.time).max(0.0) as f32
+    }
+
+    fn to_map(&self, confidence: f32) -> Map {
+        let mut map = Map::new();
+        map.insert("subject".into(), Dynamic::from(self.subject.clone()));
+        map.insert("x".into(), Dynamic::from(self.position.x as f64));
+        map.insert("y".into(), Dynamic::from(self.position.y as f64));
+        map.insert("z".into(), Dynamic::from(self.position.z as f64));
+        map.insert("time".into(), Dynamic::from(self.time));
+        map.insert("confidence".into(), Dynamic::from(confidence as f64));
+        map.insert("count".into(), Dynamic::from(self.count as i64));
+        map
+    }
+}
+
+/// Facts an NPC remembers, their confidence fades over time
+#[derive(Debug, Component, Reflect, Clone)]
+#[reflect(Component)]
+pub struct NPCMemory {
+    /// Confidence lost per second
+    pub decay: f32,
+    /// Facts are forgotten below this confidence
+    pub forget_below: f32,
+    pub facts: Vec<MemoryFact>,
+}
+
+impl Default for NPCMemory {
+    fn default() -> Self {
+        Self {
+            decay: 0.05,
+            forget_below: 0.05,
+            facts: vec![],
+        }
+    }
+}
+
+impl NPCMemory {
+    /// Adds a fact or refreshes the known one, true if the fact is new
+    pub fn remember(&mut self, fact: MemoryFact) -> bool {
+        let decay = self.decay;
+        if let Some(known) = self
+            .facts
+            .iter_mut()
+            .find(|known| known.kind == fact.kind && known.subject == fact.subject)
+        {
+            known.confidence = known.confidence_at(fact.time, decay).max(fact.confidence);
+            known.position = fact.position;
+            known.time = fact.time;
+            known.count += fact.count;
+            false
+        } else {
+            self.facts.push(fact);
+            true
+        }
+    }
+
+    pub fn recall(&self, subject: &str, kind: MemoryKind) -> Option<&MemoryFact> {
+        self.facts
+            .iter()
+            .find(|fact| fact.kind == kind && fact.subject == subject)
+    }
+}
+
+/// Adds a fact to an NPC memory, like an interaction with another entity
+#[derive(Debug, Clone)]
+pub struct Remember {
+    pub npc: Entity,
+    pub fact: MemoryFact,
+}
+
+/// Elapsed seconds, shared with the script views so they fade confidence when read
+#[derive(Default, Debug, Clone, Resource)]
+pub struct MemoryClock(Arc<AtomicU64>);
+
+impl MemoryClock {
+    fn now(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+fn tick(time: Res<Time>, clock: Res<MemoryClock>) {
+    clock
+        .0
+        .store(time.elapsed_seconds_f64().to_bits(), Ordering::Relaxed);
+}
+
+/// Memories of the NPCs of a tree, available to scripts as `memory`
+#[derive(Debug, Clone)]
+pub struct MemoryView {
+    memory: NPCMemory,
+    clock: MemoryClock,
+}
+
+impl MemoryView {
+    fn confidence(&self, fact: &MemoryFact) -> f32 {
+        fact.confidence_at(self.clock.now(), self.memory.decay)
+    }
+
+    fn recall(&mut self, subject: &str, kind: MemoryKind) -> Dynamic {
+        match self.memory.recall(subject, kind) {
+            Some(fact) => Dynamic::from(fact.to_map(self.confidence(fact))),
+            None => Dynamic::from(()),
+        }
+    }
+}
+
+fn register_script_functions(engine: &mut Engine) {
+    engine.register_type::<MemoryView>();
+    engine.register_fn(
+        "last_seen",
+        |memory: &mut MemoryView, subject: ImmutableString| {
+            memory.recall(&subject, MemoryKind::Sighting)
+        },
+    );
+    engine.register_fn(
+        "last_heard",
+        |memory: &mut MemoryView, subject: ImmutableString| {
+            memory.recall(&subject, MemoryKind::Sound)
+        },
+    );
+    engine.register_fn(
+        "recall",
+        |memory: &mut MemoryView, subject: ImmutableString, kind: ImmutableString| {
+            match MemoryKind::from_str(&kind) {
+                Some(kind) => memory.recall(&subject, kind),
+                None => Dynamic::from(()),
+            }
+        },
+    );
+    engine.register_fn(
+        "confidence",
+        |memory: &mut MemoryView, subject: ImmutableString| {
+            memory
+                .memory
+                .facts
+                .iter()
+                .filter(|fact| fact.subject == subject.as_str())
+                .map(|fact| memory.confidence(fact) as f64)
+                .fold(0.0, f64::max)
+        },
+    );
+    engine.register_fn(
+        "knows",
+        |memory: &mut MemoryView, subject: ImmutableString| {
+            memory
+                .memory
+                .facts
+                .iter()
+                .any(|fact| fact.subject == subject.as_str())
+        },
+    );
+}
+
+fn subject(perceived: &Perceived) -> String {
+    perceived
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("{:?}", perceived.entity))
+}
+
+fn remember_perceptions(time: Res<Time>, mut npcs: Query<(&Perceptions, &mut NPCMemory)>) {
+    let now = time.elapsed_seconds_f64();
+    for (perceptions, mut memory) in &mut npcs {
+        // refreshing known facts happens every frame, only new facts count as a change
+        let mut added = false;
+        // only what is perceived this frame, older perceptions are already remembered
+        for (list, kind) in [
+            (&perceptions.seen, MemoryKind::Sighting),
+            (&perceptions.heard, MemoryKind::Sound),
+        ] {
+            for perceived in list.iter().filter(|p| p.time >= now) {
+                let mut fact = MemoryFact::new(subject(perceived), kind, perceived.position, now);
+                fact.confidence = perceived.intensity.max(0.5);
+                fact.count = 0;
+                added |= memory.bypass_change_detection().remember(fact);
+            }
+        }
+        if added {
+            memory.set_changed();
+        }
+    }
+}
+
+fn remember_stimuli(
+    time: Res<Time>,
+    mut stimuli: EventReader<Stimulus>,
+    mut npcs: Query<(&GlobalTransform, &mut NPCMemory)>,
+) {
+    let now = time.elapsed_seconds_f64();
+    for stimulus in stimuli.iter() {
+        for (transform, mut memory) in &mut npcs {
+            if stimulus.reaches(transform.translation()) {
+                let mut fact = MemoryFact::new(
+                    stimulus.kind.as_str(),
+                    MemoryKind::Location,
+                    stimulus.position,
+                    now,
+                );
+                fact.confidence = stimulus.intensity.clamp(0.0, 1.0);
+                memory.remember(fact);
+            }
+        }
+    }
+}
+
+fn remember_events(mut events: EventReader<Remember>, mut npcs: Query<&mut NPCMemory>) {
+    for event in events.iter() {
+        if let Ok(mut memory) = npcs.get_mut(event.npc) {
+            memory.remember(event.fact.clone());
+        }
+    }
+}
+
+// Drop faded facts, memories only count as changed when one is dropped
+fn forget(time: Res<Time>, mut npcs: Query<&mut NPCMemory>) {
+    let now = time.elapsed_seconds_f64();
+    for mut memory in &mut npcs {
+        let (decay, forget_below) = (memory.decay, memory.forget_below);
+        let faded = |fact: &MemoryFact| fact.confidence_at(now, decay) < forget_below;
+        if memory.facts.iter().any(faded) {
+            memory.facts.retain(|fact| !faded(fact));
+        }
+    }
+}
+
+// Publish the memories of every tree as the `memory` script variable, when they change
+fn write_scope(
+    changed: Query<&SpawnOwned, Changed<NPCMemory>>,
+    npcs: Query<(&SpawnOwned, &NPCMemory)>,
+    trees: NPCTrees,
+    clock: Res<MemoryClock>,
+    mut writes: EventWriter<BlackboardWrite>,
+) {
+    let changed: HashSet<Entity> = changed
+        .iter()
+        .filter_map(|owned| trees.tree(owned))
+        .collect();
+    if changed.is_empty() {
+        return;
+    }
+    let mut by_tree: HashMap<Entity, MemoryView> = HashMap::default();
+    for (owned, memory) in &npcs {
+        let Some(tree) = trees.tree(owned).filter(|tree| changed.contains(tree)) else {
+            continue;
+        };
+        // facts fade at the rate of the first NPC of the tree
+        let view = by_tree.entry(tree).or_insert_with(|| MemoryView {
+            memory: NPCMemory {
+                facts: vec![],
+                ..memory.clone()
+            },
+            clock: clock.clone(),
+        });
+        for fact in &memory.facts {
+            view.memory.remember(fact.clone());
+        }
+    }
+    for (tree, view) in by_tree {
+        writes.send(BlackboardWrite::variable(
+            tree,
+            "memory",
+            Dynamic::from(view),
+        ));
+    }
+}
@@ -0,0 +1,121 @@
+use crate::behaviors::{
+    blackboard::BlackboardWrite,
+    npc::{NPCTrees, SpawnOwned},
+};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use rhai::{Dynamic, Map};
+
+pub struct NeedsPlugin;
+
+impl Plugin for NeedsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Need>()
+            .register_type::<NPCNeeds>()
+            .add_system(decay)
+            .add_system(write_scope.after(decay));
+    }
+}
+
+/// A drive of an NPC, 1 is fully satisfied and 0 is desperate
+#[derive(Default, Debug, Clone, Reflect, FromReflect)]
+pub struct Need {
+    pub name: String,
+    pub value: f32,
+    /// Value lost per second
+    pub decay: f32,
+}
+
+impl Need {
+    pub fn new(name: impl Into<String>, decay: f32) -> Self {
+        Self {
+            name: name.into(),
+            value: 1.0,
+            decay,
+        }
+    }
+}
+
+/// Needs that motivate an autonomous NPC
+#[derive(Debug, Component, Reflect, Clone)]
+#[reflect(Component)]
+pub struct NPCNeeds {
+    pub needs: Vec<Need>,
+}
+
+impl Default for NPCNeeds {
+    fn default() -> Self {
+        Self {
+            needs: vec![
+                Need::new("hunger", 0.01),
+                Need::new("energy", 0.005),
+                Need::new("social", 0.008),
+            ],
+        }
+    }
+}
+
+impl NPCNeeds {
+    pub fn need(&self, name: &str) -> Option<&Need> {
+        self.needs.iter().find(|need| need.name == name)
+    }
+
+    pub fn need_mut(&mut self, name: &str) -> Option<&mut Need> {
+        self.needs.iter_mut().find(|need| need.name == name)
+    }
+}
+
+/// Needs count as changed, and get published again, each time they drop by this much
+pub const NEED_STEP: f32 = 0.01;
+
+fn decay(time: Res<Time>, mut npcs: Query<&mut NPCNeeds>) {
+    let delta = time.delta_seconds();
+    for mut needs in &mut npcs {
+        let mut stepped = false;
+        for need in needs.bypass_change_detection().needs.iter_mut() {
+            let value = (need.value - need.decay * delta).clamp(0.0, 1.0);
+            stepped |= (value / NEED_STEP).ceil() != (need.value / NEED_STEP).ceil();
+            need.value = value;
+        }
+        if stepped {
+            needs.set_changed();
+        }
+    }
+}
+
+// Publish needs as the `needs` script variable, e.g. `needs.hunger < 0.3`, for trees where
+// they changed. With several NPCs in a tree, the most urgent value wins.
+fn write_scope(
+    npcs: Query<(&SpawnOwned, Ref<NPCNeeds>)>,
+    trees: NPCTrees,
+    mut writes: EventWriter<BlackboardWrite>,
+) {
+    let changed: HashSet<Entity> = npcs
+        .iter()
+        .filter(|(_, needs)| needs.is_changed())
+        .filter_map(|(owned, _)| trees.tree(owned))
+        .collect();
+    if changed.is_empty() {
+        return;
+    }
+    let mut by_tree: HashMap<Entity, HashMap<String, f32>> = HashMap::default();
+    for (owned, needs) in &npcs {
+        let Some(tree) = trees.tree(owned).filter(|tree| changed.contains(tree)) else {
+            continue;
+        };
+        let values = by_tree.entry(tree).or_default();
+        for need in &needs.needs {
+            let value = values.entry(need.name.clone()).or_insert(need.value);
+            *value = value.min(need.value);
+        }
+    }
+    for (tree, values) in by_tree {
+        let mut map = Map::new();
+        for (name, value) in values {
+            map.insert(name.into(), Dynamic::from(value as f64));
+        }
+        writes.send(BlackboardWrite::variable(tree, "needs", Dynamic::from(map)));
+    }
+}
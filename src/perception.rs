@@ -0,0 +1,274 @@
+use crate::{
+    behaviors::{
+        blackboard::BlackboardWrite,
+        npc::{NPCTrees, SpawnOwned},
+    },
+    day_night::TimeOfDay,
+    ground::GroundQuery,
+};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use rhai::{Array, Dynamic, Map};
+
+pub struct PerceptionPlugin;
+
+impl Plugin for PerceptionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Perception>()
+            .register_type::<Perceivable>()
+            .register_type::<Noise>()
+            .add_system(perceive)
+            .add_system(write_blackboard.after(perceive));
+    }
+}
+
+/// Senses of an NPC
+#[derive(Debug, Component, Reflect, Clone)]
+#[reflect(Component)]
+pub struct Perception {
+    /// Field of view, in degrees
+    pub fov: f32,
+    /// How far the NPC can see
+    pub sight_range: f32,
+    /// Fraction of the sight range left at night
+    pub night_sight: f32,
+    /// Eye height above the NPC root
+    pub eye_height: f32,
+    /// How far the NPC can hear a noise of loudness 1
+    pub hearing_radius: f32,
+    /// Weakest noise the NPC can hear
+    pub hearing_threshold: f32,
+    /// Seconds a perceived entity is remembered
+    pub memory: f64,
+}
+
+impl Default for Perception {
+    fn default() -> Self {
+        Self {
+            fov: 120.0,
+            sight_range: 10.0,
+            night_sight: 0.5,
+            eye_height: 1.6,
+            hearing_radius: 8.0,
+            hearing_threshold: 0.1,
+            memory: 5.0,
+        }
+    }
+}
+
+/// Entity that can be seen by NPCs
+#[derive(Debug, Component, Reflect, Clone)]
+#[reflect(Component)]
+pub struct Perceivable {
+    /// Height above the entity root that NPCs look at
+    pub height: f32,
+}
+
+impl Default for Perceivable {
+    fn default() -> Self {
+        Self { height: 1.0 }
+    }
+}
+
+/// Entity making noise that can be heard by NPCs
+#[derive(Debug, Component, Reflect, Clone)]
+#[reflect(Component)]
+pub struct Noise {
+    pub loudness: f32,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self { loudness: 1.0 }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Perceived {
+    pub entity: Entity,
+    pub name: Option<String>,
+    pub position: Vec3,
+    pub time: f64,
+    pub intensity: f32,
+}
+
+/// What an NPC currently perceives, and recently perceived
+#[derive(Default, Debug, Component, Clone)]
+pub struct Perceptions {
+    pub seen: Vec<Perceived>,
+    pub heard: Vec<Perceived>,
+}
+
+impl Perceptions {
+    fn remember(list: &mut Vec<Perceived>, perceived: Perceived) {
+        if let Some(known) = list.iter_mut().find(|p| p.entity == perceived.entity) {
+            *known = perceived;
+        } else {
+            list.push(perceived);
+        }
+    }
+
+    fn forget(&mut self, now: f64, memory: f64) {
+        self.seen.retain(|p| now - p.time <= memory);
+        self.heard.retain(|p| now - p.time <= memory);
+    }
+
+    fn entities(&self) -> (Vec<Entity>, Vec<Entity>) {
+        (
+            self.seen.iter().map(|p| p.entity).collect(),
+            self.heard.iter().map(|p| p.entity).collect(),
+        )
+    }
+}
+
+/// glTF characters face +Z
+pub fn npc_forward(transform: &GlobalTransform) -> Vec3 {
+    transform.back()
+}
+
+pub fn perceive(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_of_day: Option<Res<TimeOfDay>>,
+    mut perceivers: Query<(
+        Entity,
+        &Perception,
+        &GlobalTransform,
+        Option<&SpawnOwned>,
+        Option<&mut Perceptions>,
+    )>,
+    perceivables: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            Option<&Name>,
+            Option<&Perceivable>,
+            Option<&Noise>,
+            Option<&SpawnOwned>,
+        ),
+        Or<(With<Perceivable>, With<Noise>)>,
+    >,
+    trees: NPCTrees,
+    ground: GroundQuery,
+) {
+    let now = time.elapsed_seconds_f64();
+    let daylight = time_of_day.map_or(1.0, |time_of_day| time_of_day.daylight);
+    for (perceiver, perception, transform, owned, perceptions) in &mut perceivers {
+        // start perceiving next frame
+        let Some(mut perceptions) = perceptions else {
+            commands.entity(perceiver).insert(Perceptions::default());
+            continue;
+        };
+        // times and positions are refreshed every frame, the component only counts as
+        // changed when something is perceived or forgotten
+        let before = perceptions.entities();
+        let current = perceptions.bypass_change_detection();
+        current.forget(now, perception.memory);
+
+        let tree = owned.and_then(|owned| trees.tree(owned));
+        let eye = transform.translation() + Vec3::Y * perception.eye_height;
+        let forward = npc_forward(transform);
+        let half_fov = perception.fov.to_radians() * 0.5;
+        let sight_range = perception.sight_range
+            * (perception.night_sight + (1.0 - perception.night_sight) * daylight);
+
+        for (entity, other, name, perceivable, noise, other_owned) in &perceivables {
+            if entity == perceiver {
+                continue;
+            }
+            // NPCs spawned by the same tree don't perceive each other
+            if tree.is_some() && other_owned.and_then(|owned| trees.tree(owned)) == tree {
+                continue;
+            }
+
+            let position = other.translation();
+            let distance = position.distance(transform.translation());
+            let perceived = |intensity| Perceived {
+                entity,
+                name: name.map(|name| name.as_str().to_owned()),
+                position,
+                time: now,
+                intensity,
+            };
+
+            let sight = perceivable.filter(|_| distance <= sight_range);
+            if let Some(perceivable) = sight {
+                let target = position + Vec3::Y * perceivable.height;
+                let to_target = target - eye;
+                let in_cone = to_target.length_squared() < f32::EPSILON
+                    || forward.angle_between(to_target) <= half_fov;
+                // occluded if scene geometry is closer than the target
+                let visible = in_cone && ground.cast(eye, to_target, to_target.length()).is_none();
+                if visible {
+                    let intensity = 1.0 - distance / sight_range.max(f32::EPSILON);
+                    Perceptions::remember(&mut current.seen, perceived(intensity));
+                }
+            }
+
+            if let Some(noise) = noise {
+                let radius = perception.hearing_radius * noise.loudness;
+                if radius > 0.0 {
+                    let intensity = noise.loudness * (1.0 - distance / radius).clamp(0.0, 1.0);
+                    if intensity >= perception.hearing_threshold {
+                        Perceptions::remember(&mut current.heard, perceived(intensity));
+                    }
+                }
+            }
+        }
+        if current.entities() != before {
+            perceptions.set_changed();
+        }
+    }
+}
+
+fn to_array(perceived: &[Perceived]) -> Array {
+    perceived
+        .iter()
+        .map(|p| {
+            let mut map = Map::new();
+            map.insert("entity".into(), Dynamic::from(p.entity.to_bits() as i64));
+            map.insert(
+                "name".into(),
+                Dynamic::from(p.name.clone().unwrap_or_default()),
+            );
+            map.insert("x".into(), Dynamic::from(p.position.x as f64));
+            map.insert("y".into(), Dynamic::from(p.position.y as f64));
+            map.insert("z".into(), Dynamic::from(p.position.z as f64));
+            map.insert("time".into(), Dynamic::from(p.time));
+            map.insert("intensity".into(), Dynamic::from(p.intensity as f64));
+            Dynamic::from(map)
+        })
+        .collect()
+}
+
+// Publish perceptions as `blackboard.seen` and `blackboard.heard`, every NPC of a tree
+// together, for trees where one of them changed
+fn write_blackboard(
+    perceivers: Query<(&SpawnOwned, Ref<Perceptions>)>,
+    trees: NPCTrees,
+    mut writes: EventWriter<BlackboardWrite>,
+) {
+    let changed: HashSet<Entity> = perceivers
+        .iter()
+        .filter(|(_, perceptions)| perceptions.is_changed())
+        .filter_map(|(owned, _)| trees.tree(owned))
+        .collect();
+    if changed.is_empty() {
+        return;
+    }
+    let mut by_tree: HashMap<Entity, (Array, Array)> = HashMap::default();
+    for (owned, perceptions) in &perceivers {
+        let Some(tree) = trees.tree(owned).filter(|tree| changed.contains(tree)) else {
+            continue;
+        };
+        let (seen, heard) = by_tree.entry(tree).or_default();
+        seen.extend(to_array(&perceptions.seen));
+        heard.extend(to_array(&perceptions.heard));
+    }
+    for (tree, (seen, heard)) in by_tree {
+        writes.send(BlackboardWrite::new(tree, "seen", Dynamic::from(seen)));
+        writes.send(BlackboardWrite::new(tree, "heard", Dynamic::from(heard)));
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{
+    behaviors::npc::{anim, spawn, NPCBehavior},
+    deterministic::SimSet,
+};
+use bevy::{prelude::*, utils::HashMap};
+use simula_behavior::prelude::*;
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Measures time spent in behavior systems, frame by frame, with markers ordered around them.
+/// Measurements are only taken when the `SystemProfile` resource is present.
+pub struct ProfilePlugin;
+
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SystemProfile>()
+            .add_system(
+                profile_start("spawn::run")
+                    .in_base_set(SimSet)
+                    .before(spawn::run),
+            )
+            .add_system(
+                profile_stop("spawn::run")
+                    .in_base_set(SimSet)
+                    .after(spawn::run),
+            )
+            .add_system(
+                profile_start("anim::run")
+                    .in_base_set(SimSet)
+                    .before(anim::run),
+            )
+            .add_system(
+                profile_stop("anim::run")
+                    .in_base_set(SimSet)
+                    .after(anim::run),
+            )
+            .add_system(
+                profile_start("subtree::run")
+                    .in_base_set(SimSet)
+                    .before(subtree::run::<NPCBehavior>),
+            )
+            .add_system(
+                profile_stop("subtree::run")
+                    .in_base_set(SimSet)
+                    .after(subtree::run::<NPCBehavior>),
+            )
+            .add_system(
+                profile_start("behavior core")
+                    .in_base_set(CoreSet::PostUpdate)
+                    .before(BehaviorSet::PostUpdate),
+            )
+            .add_system(
+                profile_stop("behavior core")
+                    .in_base_set(CoreSet::PostUpdate)
+                    .after(BehaviorSet::PostUpdate),
+            )
+            .add_system(end_frame.in_base_set(CoreSet::Last));
+    }
+}
+
+#[derive(Resource)]
+pub struct SystemProfile {
+    current: Mutex<HashMap<&'static str, Duration>>,
+    started: Mutex<HashMap<&'static str, Instant>>,
+    /// Time per measured system, one entry per frame, the oldest first
+    pub frames: VecDeque<HashMap<&'static str, Duration>>,
+    /// Most frames kept, older ones are dropped
+    pub capacity: usize,
+}
+
+impl Default for SystemProfile {
+    fn default() -> Self {
+        Self {
+            current: default(),
+            started: default(),
+            frames: default(),
+            capacity: 600,
+        }
+    }
+}
+
+impl SystemProfile {
+    pub fn add(&self, name: &'static str, elapsed: Duration) {
+        *self.current.lock().unwrap().entry(name).or_default() += elapsed;
+    }
+}
+
+/// Starts measuring systems, ordered between a start and a stop.
+/// Other systems may run in between, use a single threaded executor for steadier numbers.
+pub fn profile_start(name: &'static str) -> impl FnMut(Option<Res<SystemProfile>>) {
+    move |profile: Option<Res<SystemProfile>>| {
+        if let Some(profile) = profile {
+            profile.started.lock().unwrap().insert(name, Instant::now());
+        }
+    }
+}
+
+pub fn profile_stop(name: &'static str) -> impl FnMut(Option<Res<SystemProfile>>) {
+    move |profile: Option<Res<SystemProfile>>| {
+        if let Some(profile) = profile {
+            let started = profile.started.lock().unwrap().remove(name);
+            if let Some(started) = started {
+                profile.add(name, started.elapsed());
+            }
+        }
+    }
+}
+
+fn end_frame(profile: Option<ResMut<SystemProfile>>) {
+    if let Some(mut profile) = profile {
+        let frame = std::mem::take(&mut *profile.current.lock().unwrap());
+        profile.frames.push_back(frame);
+        while profile.frames.len() > profile.capacity {
+            profile.frames.pop_front();
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+
+/// Seed for everything random in the simulation, set with `--seed`
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct RandomSeed(pub u64);
+
+impl RandomSeed {
+    /// Seed derived from this one and a value, like an entity or a counter
+    pub fn mix(&self, value: u64) -> u64 {
+        hash(self.0 ^ value)
+    }
+}
+
+// splitmix64, so the same seed always gives the same point
+pub fn hash(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Number in `0..1` for the seed
+pub fn unit(seed: u64) -> f32 {
+    (hash(seed) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Fisher-Yates shuffle, the same seed always gives the same order
+pub fn shuffle<T>(seed: u64, items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        let j = (hash(seed.wrapping_add(i as u64)) % (i as u64 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
@@ -0,0 +1,270 @@
+use crate::{
+    behaviors::blackboard::{BlackboardWrite, ScriptFunctionsAppExt},
+    random::{hash, unit},
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use rhai::{Dynamic, Engine, Map, RegisterFn, INT};
+use serde::{Deserialize, Serialize};
+use simula_core::epath::{self, EPath, EPathQueries};
+use simula_script::ScriptContext;
+use simula_viz::lines::{Lines, LinesBundle, LinesPlugin};
+
+pub struct RegionPlugin;
+
+impl Plugin for RegionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Region>()
+            .register_type::<RegionShape>()
+            .add_script_functions(register_script_functions)
+            .add_system(write_scope);
+
+        // outlines, not available headless
+        if app.is_plugin_added::<LinesPlugin>() {
+            app.add_startup_system(setup).add_system(draw);
+        }
+    }
+}
+
+/// Outline on the XZ plane of the region entity
+#[derive(Debug, Clone, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+pub enum RegionShape {
+    Box { size: Vec2 },
+    Circle { radius: f32 },
+    Polygon { points: Vec<Vec2> },
+}
+
+impl Default for RegionShape {
+    fn default() -> Self {
+        RegionShape::Box { size: Vec2::ONE }
+    }
+}
+
+impl RegionShape {
+    fn contains_local(&self, point: Vec2) -> bool {
+        match self {
+            RegionShape::Box { size } => {
+                point.x.abs() <= size.x * 0.5 && point.y.abs() <= size.y * 0.5
+            }
+            RegionShape::Circle { radius } => point.length_squared() <= radius * radius,
+            RegionShape::Polygon { points } => {
+                // even-odd rule
+                let mut inside = false;
+                let mut j = points.len().wrapping_sub(1);
+                for i in 0..points.len() {
+                    let (a, b) = (points[i], points[j]);
+                    if (a.y > point.y) != (b.y > point.y)
+                        && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+                    {
+                        inside = !inside;
+                    }
+                    j = i;
+                }
+                inside
+            }
+        }
+    }
+
+    fn bounds(&self) -> (Vec2, Vec2) {
+        match self {
+            RegionShape::Box { size } => (-*size * 0.5, *size * 0.5),
+            RegionShape::Circle { radius } => (-Vec2::splat(*radius), Vec2::splat(*radius)),
+            RegionShape::Polygon { points } => points.iter().fold(
+                (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+                |(min, max), point| (min.min(*point), max.max(*point)),
+            ),
+        }
+    }
+
+    fn outline(&self) -> Vec<Vec2> {
+        match self {
+            RegionShape::Box { size } => {
+                let half = *size * 0.5;
+                vec![
+                    Vec2::new(-half.x, -half.y),
+                    Vec2::new(half.x, -half.y),
+                    Vec2::new(half.x, half.y),
+                    Vec2::new(-half.x, half.y),
+                ]
+            }
+            RegionShape::Circle { radius } => (0..32)
+                .map(|i| {
+                    let angle = i as f32 / 32.0 * std::f32::consts::TAU;
+                    Vec2::new(angle.cos(), angle.sin()) * *radius
+                })
+                .collect(),
+            RegionShape::Polygon { points } => points.clone(),
+        }
+    }
+}
+
+/// Named area of the world, like "market" or "spawn_north", addressed by its `Name`
+#[derive(Debug, Clone, Default, Component, Reflect, FromReflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct Region {
+    pub shape: RegionShape,
+    pub color: Color,
+}
+
+impl Region {
+    pub fn contains(&self, transform: &GlobalTransform, point: Vec3) -> bool {
+        let local = transform.affine().inverse().transform_point3(point);
+        self.shape.contains_local(Vec2::new(local.x, local.z))
+    }
+
+    /// Point inside the region, on the region plane
+    pub fn random_point(&self, transform: &GlobalTransform, seed: u64) -> Vec3 {
+        let (min, max) = self.shape.bounds();
+        let mut local = (min + max) * 0.5;
+        for attempt in 0..32 {
+            let seed = hash(seed.wrapping_add(attempt));
+            let candidate = Vec2::new(
+                min.x + (max.x - min.x) * unit(seed),
+                min.y + (max.y - min.y) * unit(seed ^ 0xA5A5_A5A5),
+            );
+            if self.shape.contains_local(candidate) {
+                local = candidate;
+                break;
+            }
+        }
+        transform.transform_point(Vec3::new(local.x, 0.0, local.y))
+    }
+}
+
+/// Finds regions by name or EPath
+#[derive(SystemParam)]
+pub struct RegionQuery<'w, 's> {
+    regions: Query<'w, 's, (Entity, &'static Region, &'static GlobalTransform)>,
+}
+
+impl<'w, 's> RegionQuery<'w, 's> {
+    pub fn get(&self, entity: Entity) -> Option<(&Region, &GlobalTransform)> {
+        self.regions
+            .get(entity)
+            .ok()
+            .map(|(_, region, transform)| (region, transform))
+    }
+
+    /// First region matched by the path
+    pub fn select(
+        &self,
+        path: &EPath,
+        equeries: &EPathQueries,
+    ) -> Option<(&Region, &GlobalTransform)> {
+        epath::select(None, path, equeries)
+            .iter()
+            .find_map(|target| self.get(target.entity))
+    }
+}
+
+/// Region with its world transform, available to scripts through `regions`
+#[derive(Debug, Clone)]
+pub struct RegionView {
+    region: Region,
+    transform: GlobalTransform,
+}
+
+fn to_vec3(map: &Map) -> Vec3 {
+    let get = |key: &str| {
+        map.get(key)
+            .and_then(|value| {
+                value
+                    .clone()
+                    .try_cast::<f64>()
+                    .or_else(|| value.clone().try_cast::<INT>().map(|value| value as f64))
+            })
+            .unwrap_or_default() as f32
+    };
+    Vec3::new(get("x"), get("y"), get("z"))
+}
+
+fn to_map(point: Vec3) -> Map {
+    let mut map = Map::new();
+    map.insert("x".into(), Dynamic::from(point.x as f64));
+    map.insert("y".into(), Dynamic::from(point.y as f64));
+    map.insert("z".into(), Dynamic::from(point.z as f64));
+    map
+}
+
+fn register_script_functions(engine: &mut Engine) {
+    engine.register_type::<RegionView>();
+    engine.register_fn("contains", |region: &mut RegionView, pos: Map| {
+        region.region.contains(&region.transform, to_vec3(&pos))
+    });
+    engine.register_fn("random_point", |region: &mut RegionView, seed: INT| {
+        to_map(region.region.random_point(&region.transform, seed as u64))
+    });
+}
+
+#[derive(Component)]
+struct RegionLines;
+
+fn setup(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Region Lines"),
+        RegionLines,
+        LinesBundle::default(),
+    ));
+}
+
+fn draw(
+    regions: Query<(&Region, &GlobalTransform)>,
+    mut lines: Query<&mut Lines, With<RegionLines>>,
+) {
+    let Ok(mut lines) = lines.get_single_mut() else {
+        return;
+    };
+    for (region, transform) in &regions {
+        let outline: Vec<Vec3> = region
+            .shape
+            .outline()
+            .into_iter()
+            .map(|point| transform.transform_point(Vec3::new(point.x, 0.02, point.y)))
+            .collect();
+        for (i, start) in outline.iter().enumerate() {
+            let end = outline[(i + 1) % outline.len()];
+            lines.line_colored(*start, end, 0.0, region.color);
+        }
+    }
+}
+
+// Publish regions by name as the `regions` script variable, to new trees and when regions change
+fn write_scope(
+    regions: Query<(&Name, &Region, &GlobalTransform)>,
+    changed: Query<
+        (),
+        (
+            With<Region>,
+            Or<(Changed<Region>, Changed<GlobalTransform>, Changed<Name>)>,
+        ),
+    >,
+    mut removed: RemovedComponents<Region>,
+    trees: Query<(Entity, Ref<Handle<ScriptContext>>)>,
+    mut writes: EventWriter<BlackboardWrite>,
+) {
+    let all = !changed.is_empty() || removed.iter().next().is_some();
+    let targets: Vec<Entity> = trees
+        .iter()
+        .filter(|(_, context)| all || context.is_changed())
+        .map(|(tree, _)| tree)
+        .collect();
+    if targets.is_empty() {
+        return;
+    }
+    let mut map = Map::new();
+    for (name, region, transform) in &regions {
+        map.insert(
+            name.as_str().into(),
+            Dynamic::from(RegionView {
+                region: region.clone(),
+                transform: *transform,
+            }),
+        );
+    }
+    for tree in targets {
+        writes.send(BlackboardWrite::variable(
+            tree,
+            "regions",
+            Dynamic::from(map.clone()),
+        ));
+    }
+}
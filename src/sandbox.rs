@@ -0,0 +1,77 @@
+use crate::{
+    behaviors::{
+        bioma::{BiomaBehavior, BiomaBehaviorPlugin},
+        blackboard::BlackboardPlugin,
+        ecosystem::EcosystemBehaviorPlugin,
+        export::ExportBehaviorPlugin,
+        invoke::InvokePlugin,
+        npc::{NPCBehavior, NPCBehaviorPlugin},
+        population::PopulationPlugin,
+        utility::UtilityPlugin,
+    },
+    clock::ClockPlugin,
+    day_night::DayNightPlugin,
+    deterministic::SimSet,
+    ecosystem::EcosystemPlugin,
+    export::ExportPlugin,
+    goap::GoapPlugin,
+    ground::GroundPlugin,
+    memory::MemoryPlugin,
+    needs::NeedsPlugin,
+    perception::PerceptionPlugin,
+    random::RandomSeed,
+    region::RegionPlugin,
+    schedule::SchedulePlugin,
+    smart_object::SmartObjectPlugin,
+    stimulus::StimulusPlugin,
+    trace::TracePlugin,
+};
+use bevy::prelude::*;
+use simula_behavior::prelude::*;
+
+/// Simulation and behavior plugins, shared by the windowed, headless and test apps
+pub struct SandboxPlugin;
+
+impl Plugin for SandboxPlugin {
+    fn build(&self, app: &mut App) {
+        // systems added without a base set go to `SimSet`, so deterministic mode can step them
+        app.configure_set(SimSet.after(CoreSet::Update).before(CoreSet::UpdateFlush))
+            .edit_schedule(CoreSchedule::Main, |schedule| {
+                schedule.set_default_base_set(SimSet);
+            });
+
+        app.init_resource::<RandomSeed>()
+            .add_plugin(GroundPlugin)
+            .add_plugin(ClockPlugin)
+            .add_plugin(DayNightPlugin)
+            // Behavior setup
+            .add_plugin(BehaviorPlugin)
+            .add_plugin(BlackboardPlugin)
+            .add_plugin(UtilityPlugin)
+            .add_plugin(PerceptionPlugin)
+            .add_plugin(StimulusPlugin)
+            .add_plugin(MemoryPlugin)
+            .add_plugin(NeedsPlugin)
+            .add_plugin(GoapPlugin)
+            .add_plugin(SmartObjectPlugin)
+            .add_plugin(SchedulePlugin)
+            .add_plugin(RegionPlugin)
+            .add_plugin(EcosystemPlugin)
+            .add_plugin(TracePlugin)
+            .add_plugin(ExportPlugin)
+            // BiomaBehavior setup
+            .add_plugin(BiomaBehaviorPlugin)
+            .add_plugin(PopulationPlugin)
+            .add_plugin(InvokePlugin)
+            .add_plugin(EcosystemBehaviorPlugin)
+            .add_plugin(ExportBehaviorPlugin)
+            .add_plugin(BehaviorServerPlugin::<BiomaBehavior>::default())
+            // NPCBehavior setup
+            .add_plugin(NPCBehaviorPlugin)
+            .add_plugin(BehaviorServerPlugin::<NPCBehavior>::default());
+
+        app.edit_schedule(CoreSchedule::Main, |schedule| {
+            schedule.set_default_base_set(CoreSet::Update);
+        });
+    }
+}
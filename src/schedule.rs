@@ -0,0 +1,91 @@
+use crate::clock::HOURS_PER_DAY;
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+pub struct SchedulePlugin;
+
+impl Plugin for SchedulePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<ScheduleAsset>()
+            .init_asset_loader::<ScheduleAssetLoader>();
+    }
+}
+
+/// Subtree to run between two hours of the day, ranges may wrap around midnight
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleEntry {
+    pub name: String,
+    pub start: f64,
+    pub end: f64,
+    pub subtree: String,
+}
+
+impl ScheduleEntry {
+    pub fn contains(&self, hour: f64) -> bool {
+        if self.start <= self.end {
+            hour >= self.start && hour < self.end
+        } else {
+            hour >= self.start || hour < self.end
+        }
+    }
+
+    pub fn duration(&self) -> f64 {
+        (self.end - self.start).rem_euclid(HOURS_PER_DAY)
+    }
+}
+
+/// Daily routine for the `DailySchedule` node, loaded from `.schedule.ron` files
+#[derive(Debug, Clone, Serialize, Deserialize, TypeUuid)]
+#[uuid = "8E2F4B6A-1C3D-4E5F-A7B9-0D2C4E6F8A1B"]
+pub struct ScheduleAsset {
+    pub entries: Vec<ScheduleEntry>,
+    /// Subtree to run when no entry covers the hour
+    #[serde(default)]
+    pub idle: Option<String>,
+}
+
+impl ScheduleAsset {
+    /// Entry covering the hour, the shortest one wins so "eat at 12" beats "work 9-17"
+    pub fn entry_at(&self, hour: f64) -> Option<usize> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.contains(hour))
+            .min_by(|(_, a), (_, b)| a.duration().total_cmp(&b.duration()))
+            .map(|(index, _)| index)
+    }
+
+    /// Subtree to run at the hour
+    pub fn subtree_at(&self, hour: f64) -> Option<&str> {
+        match self.entry_at(hour) {
+            Some(index) => Some(self.entries[index].subtree.as_str()),
+            None => self.idle.as_deref(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ScheduleAssetLoader;
+
+impl AssetLoader for ScheduleAssetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let asset = ron::de::from_bytes::<ScheduleAsset>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(asset));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["schedule.ron"]
+    }
+}
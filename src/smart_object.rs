@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct SmartObjectPlugin;
+
+impl Plugin for SmartObjectPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SmartObject>()
+            .register_type::<SmartSlot>()
+            .register_type::<SmartAdvert>()
+            .add_system(release_missing);
+    }
+}
+
+/// What an interaction does for the NPC using it
+#[derive(Debug, Clone, Default, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct SmartAdvert {
+    pub need: String,
+    pub amount: f32,
+}
+
+/// Place where one NPC uses the object, relative to the object
+#[derive(Debug, Clone, Default, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct SmartSlot {
+    pub offset: Vec3,
+    #[serde(skip)]
+    pub reserved: Option<Entity>,
+}
+
+impl SmartSlot {
+    pub fn new(offset: Vec3) -> Self {
+        Self {
+            offset,
+            reserved: None,
+        }
+    }
+}
+
+/// World object advertising an interaction to NPCs, such as "sit" on a bench
+#[derive(Debug, Component, Reflect, FromReflect, Clone, Default, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct SmartObject {
+    pub interaction: String,
+    #[serde(default)]
+    pub adverts: Vec<SmartAdvert>,
+    /// Behavior tree run by the NPC while using the object
+    pub subtree: String,
+    pub slots: Vec<SmartSlot>,
+}
+
+impl SmartObject {
+    /// Slot reserved by an NPC, if any
+    pub fn reserved_by(&self, npc: Entity) -> Option<usize> {
+        self.slots
+            .iter()
+            .position(|slot| slot.reserved == Some(npc))
+    }
+
+    pub fn has_free_slot(&self) -> bool {
+        self.slots.iter().any(|slot| slot.reserved.is_none())
+    }
+
+    /// Reserve a free slot, or return the one the NPC already holds
+    pub fn reserve(&mut self, npc: Entity) -> Option<usize> {
+        if let Some(index) = self.reserved_by(npc) {
+            return Some(index);
+        }
+        let index = self.slots.iter().position(|slot| slot.reserved.is_none())?;
+        self.slots[index].reserved = Some(npc);
+        Some(index)
+    }
+
+    pub fn release(&mut self, npc: Entity) {
+        for slot in self.slots.iter_mut() {
+            if slot.reserved == Some(npc) {
+                slot.reserved = None;
+            }
+        }
+    }
+
+    pub fn advert(&self, need: &str) -> Option<f32> {
+        self.adverts
+            .iter()
+            .find(|advert| advert.need == need)
+            .map(|advert| advert.amount)
+    }
+
+    pub fn slot_position(&self, transform: &GlobalTransform, index: usize) -> Option<Vec3> {
+        self.slots
+            .get(index)
+            .map(|slot| transform.transform_point(slot.offset))
+    }
+}
+
+// Free slots reserved by NPCs that no longer exist
+fn release_missing(mut objects: Query<&mut SmartObject>, entities: Query<Entity>) {
+    for mut object in &mut objects {
+        let missing = object
+            .slots
+            .iter()
+            .any(|slot| matches!(slot.reserved, Some(npc) if entities.get(npc).is_err()));
+        if missing {
+            for slot in object.slots.iter_mut() {
+                if matches!(slot.reserved, Some(npc) if entities.get(npc).is_err()) {
+                    slot.reserved = None;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,689 @@
+use crate::{
+    behaviors::{
+        bioma::BiomaBehavior,
+        blackboard::BlackboardWrite,
+        npc::{NPCBehavior, SpawnOwned},
+    },
+    ground::GroundSnap,
+    manifest::ManifestState,
+    memory::NPCMemory,
+    needs::NPCNeeds,
+    perception::{Perceivable, Perception},
+    trace::{asset_path, TreeNodes},
+};
+use bevy::{app::AppExit, prelude::*, scene::SceneInstance};
+use rhai::{Array, Dynamic, ImmutableString, Map};
+use serde::{Deserialize, Serialize};
+use simula_action::{action_map, Action, ActionMap, ActionMapInput};
+use simula_behavior::prelude::*;
+use simula_script::ScriptContext;
+
+/// Saves and restores behavior trees, their blackboards and the NPCs they spawned.
+/// Nodes are saved by their result, not their inner state: on restore each running leaf
+/// starts over, delegated to by its parent, and the nodes above it are running again.
+pub struct SnapshotPlugin {
+    /// Snapshot file, relative to the working directory
+    pub path: String,
+    /// Restore the snapshot once the manifest has been applied
+    pub restore: bool,
+    /// Save the snapshot when the app exits
+    pub save_on_exit: bool,
+}
+
+impl Default for SnapshotPlugin {
+    fn default() -> Self {
+        Self {
+            path: "snapshot.ron".into(),
+            restore: false,
+            save_on_exit: false,
+        }
+    }
+}
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SnapshotFile {
+            path: self.path.clone(),
+            save_on_exit: self.save_on_exit,
+        })
+        .add_event::<SnapshotSave>()
+        .add_event::<SnapshotRestore>()
+        .add_system(save.in_base_set(CoreSet::Last))
+        .add_system(restore)
+        .add_system(restore_nodes.after(restore))
+        .add_system(restore_owners.after(restore));
+
+        if self.restore {
+            app.add_startup_system(|mut restores: EventWriter<SnapshotRestore>| {
+                restores.send(SnapshotRestore)
+            });
+        }
+
+        // hotkeys, not available headless
+        if app.is_plugin_added::<WindowPlugin>() {
+            app.add_startup_system(setup)
+                .add_system(action_map::<SnapshotAction, SnapshotActionInput>)
+                .add_system(control.after(action_map::<SnapshotAction, SnapshotActionInput>));
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct SnapshotFile {
+    pub path: String,
+    pub save_on_exit: bool,
+}
+
+/// Request to save a snapshot to the snapshot file
+pub struct SnapshotSave;
+
+/// Request to replace trees and NPCs with the ones in the snapshot file
+pub struct SnapshotRestore;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    #[serde(default)]
+    pub bioma: Vec<TreeSnapshot>,
+    #[serde(default)]
+    pub npc: Vec<TreeSnapshot>,
+    #[serde(default)]
+    pub npcs: Vec<NPCSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeSnapshot {
+    /// Entity bits when saved, to link spawned NPCs back
+    pub id: u64,
+    pub name: String,
+    pub asset: String,
+    #[serde(default)]
+    pub nodes: Vec<NodeSnapshot>,
+    #[serde(default)]
+    pub blackboard: Vec<(String, SnapshotValue)>,
+}
+
+/// Node addressed by child indices from the root node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeSnapshot {
+    pub path: Vec<usize>,
+    pub state: NodeSnapshotState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NodeSnapshotState {
+    Running,
+    Success,
+    Failure,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SnapshotValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Array(Vec<SnapshotValue>),
+    Map(Vec<(String, SnapshotValue)>),
+}
+
+impl SnapshotValue {
+    pub fn from_dynamic(value: &Dynamic) -> Option<Self> {
+        if let Some(value) = value.clone().try_cast::<bool>() {
+            Some(Self::Bool(value))
+        } else if let Some(value) = value.clone().try_cast::<i64>() {
+            Some(Self::Int(value))
+        } else if let Some(value) = value.clone().try_cast::<f64>() {
+            Some(Self::Float(value))
+        } else if let Some(value) = value.clone().try_cast::<ImmutableString>() {
+            Some(Self::Str(value.to_string()))
+        } else if let Some(values) = value.clone().try_cast::<Array>() {
+            values
+                .iter()
+                .map(Self::from_dynamic)
+                .collect::<Option<_>>()
+                .map(Self::Array)
+        } else if let Some(map) = value.clone().try_cast::<Map>() {
+            map.iter()
+                .map(|(key, value)| Self::from_dynamic(value).map(|value| (key.to_string(), value)))
+                .collect::<Option<_>>()
+                .map(Self::Map)
+        } else {
+            None
+        }
+    }
+
+    pub fn to_dynamic(&self) -> Dynamic {
+        match self {
+            Self::Bool(value) => Dynamic::from(*value),
+            Self::Int(value) => Dynamic::from(*value),
+            Self::Float(value) => Dynamic::from(*value),
+            Self::Str(value) => Dynamic::from(value.clone()),
+            Self::Array(values) => Dynamic::from(
+                values
+                    .iter()
+                    .map(SnapshotValue::to_dynamic)
+                    .collect::<Array>(),
+            ),
+            Self::Map(entries) => {
+                let mut map = Map::new();
+                for (key, value) in entries {
+                    map.insert(key.as_str().into(), value.to_dynamic());
+                }
+                Dynamic::from(map)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NPCSnapshot {
+    pub name: String,
+    pub scene: String,
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+    #[serde(default)]
+    pub snap: bool,
+    /// Tree id and path of the spawn node owning the NPC
+    pub owner: (u64, Vec<usize>),
+    /// Index of the NPC this one is attached to
+    #[serde(default)]
+    pub parent: Option<usize>,
+    #[serde(default)]
+    pub needs: Vec<(String, f32)>,
+}
+
+/// Tree waiting for its nodes to be restored
+#[derive(Component)]
+pub struct RestoreNodes(Vec<NodeSnapshot>);
+
+/// NPC waiting for the spawn node owning it
+#[derive(Component)]
+pub struct RestoreOwner {
+    tree: Entity,
+    path: Vec<usize>,
+}
+
+#[allow(clippy::type_complexity)]
+fn snapshot_trees<T: BehaviorFactory>(
+    trees: &Query<(Entity, &Name, &Handle<BehaviorAsset<T>>), With<BehaviorTree<T>>>,
+    asset_server: &AssetServer,
+    tree_nodes: &TreeNodes,
+    results: &Query<(
+        Option<&BehaviorRunning>,
+        Option<&BehaviorSuccess>,
+        Option<&BehaviorFailure>,
+    )>,
+    blackboards: &Query<&Handle<ScriptContext>>,
+    contexts: &Assets<ScriptContext>,
+) -> Vec<TreeSnapshot> {
+    let mut snapshots = vec![];
+    for (tree, name, handle) in trees {
+        let Some(asset) = asset_server.get_handle_path(handle) else {
+            continue;
+        };
+        let nodes = tree_nodes
+            .walk(tree)
+            .into_iter()
+            .filter_map(|(path, node)| {
+                let state = match results.get(node).ok()? {
+                    (_, Some(_), _) => NodeSnapshotState::Success,
+                    (_, _, Some(_)) => NodeSnapshotState::Failure,
+                    (Some(_), _, _) => NodeSnapshotState::Running,
+                    _ => return None,
+                };
+                Some(NodeSnapshot { path, state })
+            })
+            .collect();
+        let blackboard = blackboards
+            .get(tree)
+            .ok()
+            .and_then(|handle| contexts.get(handle))
+            .and_then(|context| context.scope.get_value::<Map>("blackboard"))
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|(key, value)| {
+                let snapshot = SnapshotValue::from_dynamic(value);
+                if snapshot.is_none() {
+                    warn!("Snapshot: skipping blackboard entry {} of {}", key, name);
+                }
+                snapshot.map(|value| (key.to_string(), value))
+            })
+            .collect();
+        snapshots.push(TreeSnapshot {
+            id: tree.to_bits(),
+            name: name.to_string(),
+            asset: asset_path(asset),
+            nodes,
+            blackboard,
+        });
+    }
+    snapshots
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn save(
+    mut saves: EventReader<SnapshotSave>,
+    mut exits: EventReader<AppExit>,
+    file: Res<SnapshotFile>,
+    asset_server: Res<AssetServer>,
+    bioma_trees: Query<
+        (Entity, &Name, &Handle<BehaviorAsset<BiomaBehavior>>),
+        With<BehaviorTree<BiomaBehavior>>,
+    >,
+    npc_trees: Query<
+        (Entity, &Name, &Handle<BehaviorAsset<NPCBehavior>>),
+        With<BehaviorTree<NPCBehavior>>,
+    >,
+    tree_nodes: TreeNodes,
+    results: Query<(
+        Option<&BehaviorRunning>,
+        Option<&BehaviorSuccess>,
+        Option<&BehaviorFailure>,
+    )>,
+    blackboards: Query<&Handle<ScriptContext>>,
+    contexts: Res<Assets<ScriptContext>>,
+    npcs: Query<
+        (
+            Entity,
+            &Name,
+            &Handle<Scene>,
+            &Transform,
+            &SpawnOwned,
+            Option<&Parent>,
+            Option<&GroundSnap>,
+            Option<&NPCNeeds>,
+        ),
+        With<SceneInstance>,
+    >,
+    nodes: Query<&BehaviorNode>,
+) {
+    let exiting = file.save_on_exit && exits.iter().next().is_some();
+    if saves.iter().count() == 0 && !exiting {
+        return;
+    }
+
+    let mut snapshot = Snapshot {
+        bioma: snapshot_trees(
+            &bioma_trees,
+            &asset_server,
+            &tree_nodes,
+            &results,
+            &blackboards,
+            &contexts,
+        ),
+        npc: snapshot_trees(
+            &npc_trees,
+            &asset_server,
+            &tree_nodes,
+            &results,
+            &blackboards,
+            &contexts,
+        ),
+        npcs: vec![],
+    };
+
+    // NPCs owned by a spawn node of a saved tree
+    let saved: Vec<Entity> = npcs
+        .iter()
+        .filter(|(_, _, _, _, owned, _, _, _)| {
+            nodes
+                .get(***owned)
+                .ok()
+                .and_then(|node| node.tree)
+                .map_or(false, |tree| npc_trees.contains(tree))
+        })
+        .map(|(entity, ..)| entity)
+        .collect();
+    for entity in &saved {
+        let Ok((_, name, scene, transform, owned, parent, snap, needs)) = npcs.get(*entity) else {
+            continue;
+        };
+        let Some(tree) = nodes.get(**owned).ok().and_then(|node| node.tree) else {
+            continue;
+        };
+        let Some((path, _)) = tree_nodes
+            .walk(tree)
+            .into_iter()
+            .find(|(_, node)| *node == **owned)
+        else {
+            continue;
+        };
+        let Some(scene) = asset_server.get_handle_path(scene) else {
+            continue;
+        };
+        snapshot.npcs.push(NPCSnapshot {
+            name: name.to_string(),
+            scene: asset_path(scene),
+            translation: transform.translation.to_array(),
+            rotation: transform.rotation.to_array(),
+            scale: transform.scale.to_array(),
+            snap: snap.is_some(),
+            owner: (tree.to_bits(), path),
+            parent: parent.and_then(|parent| saved.iter().position(|npc| *npc == parent.get())),
+            needs: needs
+                .map(|needs| {
+                    needs
+                        .needs
+                        .iter()
+                        .map(|need| (need.name.clone(), need.value))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        });
+    }
+
+    let written = ron::ser::to_string_pretty(&snapshot, ron::ser::PrettyConfig::default())
+        .map_err(|err| err.to_string())
+        .and_then(|source| std::fs::write(&file.path, source).map_err(|err| err.to_string()));
+    match written {
+        Ok(()) => info!(
+            "Snapshot: saved {} trees and {} NPCs to {}",
+            snapshot.bioma.len() + snapshot.npc.len(),
+            snapshot.npcs.len(),
+            file.path
+        ),
+        Err(err) => error!("Snapshot: cannot save {}: {}", file.path, err),
+    }
+}
+
+fn spawn_trees<T: BehaviorFactory>(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    trees: &[TreeSnapshot],
+    writes: &mut EventWriter<BlackboardWrite>,
+) -> Vec<(u64, Entity)> {
+    let mut spawned = vec![];
+    for tree in trees {
+        let handle: Handle<BehaviorAsset<T>> = asset_server.load(tree.asset.as_str());
+        let entity = commands
+            .spawn((
+                Name::new(tree.name.clone()),
+                handle,
+                BehaviorTree::<T>::default(),
+                BehaviorTreeReset::<T>::default(),
+                RestoreNodes(tree.nodes.clone()),
+            ))
+            .id();
+        for (key, value) in &tree.blackboard {
+            writes.send(BlackboardWrite::new(
+                entity,
+                key.as_str(),
+                value.to_dynamic(),
+            ));
+        }
+        spawned.push((tree.id, entity));
+    }
+    spawned
+}
+
+// Replace trees and NPCs, once the manifest has brought up its own
+#[allow(clippy::too_many_arguments)]
+fn restore(
+    mut commands: Commands,
+    mut restores: EventReader<SnapshotRestore>,
+    mut pending: Local<Option<Snapshot>>,
+    file: Res<SnapshotFile>,
+    asset_server: Res<AssetServer>,
+    bioma_manifest: Option<Res<ManifestState<BiomaBehavior>>>,
+    npc_manifest: Option<Res<ManifestState<NPCBehavior>>>,
+    trees: Query<
+        Entity,
+        Or<(
+            With<BehaviorTree<BiomaBehavior>>,
+            With<BehaviorTree<NPCBehavior>>,
+        )>,
+    >,
+    npcs: Query<(Entity, Option<&Parent>), With<SpawnOwned>>,
+    mut writes: EventWriter<BlackboardWrite>,
+) {
+    if restores.iter().count() > 0 {
+        let loaded = std::fs::read_to_string(&file.path)
+            .map_err(|err| err.to_string())
+            .and_then(|source| ron::from_str::<Snapshot>(&source).map_err(|err| err.to_string()));
+        match loaded {
+            Ok(snapshot) => *pending = Some(snapshot),
+            Err(err) => error!("Snapshot: cannot restore {}: {}", file.path, err),
+        }
+    }
+    let synced = bioma_manifest.map_or(true, |state| state.synced())
+        && npc_manifest.map_or(true, |state| state.synced());
+    if !synced {
+        return;
+    }
+    let Some(snapshot) = pending.take() else {
+        return;
+    };
+
+    for tree in &trees {
+        commands.entity(tree).despawn_recursive();
+    }
+    for (npc, parent) in &npcs {
+        // attached NPCs go with their parent
+        if parent.map_or(true, |parent| !npcs.contains(parent.get())) {
+            commands.entity(npc).despawn_recursive();
+        }
+    }
+
+    let mut spawned =
+        spawn_trees::<BiomaBehavior>(&mut commands, &asset_server, &snapshot.bioma, &mut writes);
+    spawned.extend(spawn_trees::<NPCBehavior>(
+        &mut commands,
+        &asset_server,
+        &snapshot.npc,
+        &mut writes,
+    ));
+
+    let mut npc_entities = vec![];
+    for npc in &snapshot.npcs {
+        let mut needs = NPCNeeds::default();
+        for (name, value) in &npc.needs {
+            if let Some(need) = needs.need_mut(name) {
+                need.value = *value;
+            }
+        }
+        let mut entity = commands.spawn((
+            SceneBundle {
+                scene: asset_server.load(npc.scene.as_str()),
+                transform: Transform {
+                    translation: Vec3::from_array(npc.translation),
+                    rotation: Quat::from_array(npc.rotation),
+                    scale: Vec3::from_array(npc.scale),
+                },
+                ..default()
+            },
+            Name::new(npc.name.clone()),
+            Perception::default(),
+            Perceivable::default(),
+            NPCMemory::default(),
+            needs,
+        ));
+        if npc.snap {
+            entity.insert(GroundSnap::default());
+        }
+        let (tree, path) = &npc.owner;
+        if let Some((_, tree)) = spawned.iter().find(|(id, _)| id == tree) {
+            entity.insert(RestoreOwner {
+                tree: *tree,
+                path: path.clone(),
+            });
+        }
+        npc_entities.push(entity.id());
+    }
+    for (npc, entity) in snapshot.npcs.iter().zip(&npc_entities) {
+        if let Some(parent) = npc.parent.and_then(|parent| npc_entities.get(parent)) {
+            commands.entity(*parent).add_child(*entity);
+        }
+    }
+
+    info!(
+        "Snapshot: restored {} trees and {} NPCs from {}",
+        spawned.len(),
+        npc_entities.len(),
+        file.path
+    );
+}
+
+// Put nodes back in their saved state. Every running leaf starts over, delegated to by its
+// parent. A parallel composite, like `All` or `Any` with several children running, starts over
+// as a whole. Composites holding their own progress, like `UtilitySelector`, go on from their
+// start state.
+fn restore_nodes(
+    mut commands: Commands,
+    trees: Query<(Entity, &RestoreNodes)>,
+    tree_nodes: TreeNodes,
+    cursors: Query<Entity, With<BehaviorCursor>>,
+) {
+    for (tree, restore) in &trees {
+        let nodes = tree_nodes.walk(tree);
+        if nodes.is_empty() {
+            continue;
+        }
+        let find = |path: &[usize]| {
+            nodes
+                .iter()
+                .find(|(node_path, _)| node_path.as_slice() == path)
+                .map(|(_, node)| *node)
+        };
+        let running: Vec<&[usize]> = restore
+            .0
+            .iter()
+            .filter(|saved| saved.state == NodeSnapshotState::Running)
+            .map(|saved| saved.path.as_slice())
+            .collect();
+        let running_children = |path: &[usize]| {
+            running
+                .iter()
+                .filter(|other| other.len() == path.len() + 1 && other.starts_with(path))
+                .count()
+        };
+
+        // nodes to start over, the running leaves or the parallel composites above them
+        let mut restarts: Vec<&[usize]> = vec![];
+        for leaf in running.iter().filter(|path| {
+            !running
+                .iter()
+                .any(|other| other.len() > path.len() && other.starts_with(path))
+        }) {
+            let mut restart = *leaf;
+            while let Some((_, parent)) = restart.split_last() {
+                if running_children(parent) < 2 {
+                    break;
+                }
+                restart = parent;
+            }
+            if !restarts.contains(&restart) {
+                restarts.push(restart);
+            }
+        }
+        let nested = |path: &[usize]| {
+            restarts
+                .iter()
+                .any(|restart| path.len() > restart.len() && path.starts_with(restart))
+        };
+        let restarts: Vec<&[usize]> = restarts
+            .iter()
+            .copied()
+            .filter(|restart| !nested(restart))
+            .collect();
+
+        for saved in &restore.0 {
+            // states below a node starting over are left to its run
+            if nested(&saved.path) {
+                continue;
+            }
+            let Some(node) = find(&saved.path) else {
+                warn!("Snapshot: no node at {:?}", saved.path);
+                continue;
+            };
+            match saved.state {
+                NodeSnapshotState::Success => {
+                    commands.entity(node).insert(BehaviorSuccess);
+                }
+                NodeSnapshotState::Failure => {
+                    commands.entity(node).insert(BehaviorFailure);
+                }
+                NodeSnapshotState::Running => {}
+            }
+        }
+
+        let delegates: Vec<(Entity, Entity)> = restarts
+            .iter()
+            .filter_map(|restart| {
+                let (_, parent) = restart.split_last()?;
+                Some((find(parent)?, find(restart)?))
+            })
+            .collect();
+        if !delegates.is_empty() {
+            for (_, node) in &nodes {
+                if cursors.contains(*node) {
+                    commands.entity(*node).remove::<BehaviorCursor>();
+                }
+            }
+            for (parent, node) in delegates {
+                commands
+                    .entity(parent)
+                    .insert(BehaviorCursor::Delegate(node));
+            }
+        }
+        commands.entity(tree).remove::<RestoreNodes>();
+    }
+}
+
+fn restore_owners(
+    mut commands: Commands,
+    npcs: Query<(Entity, &RestoreOwner)>,
+    tree_nodes: TreeNodes,
+) {
+    for (npc, owner) in &npcs {
+        if let Some(node) = tree_nodes.get(owner.tree, &owner.path) {
+            commands
+                .entity(npc)
+                .insert(SpawnOwned(node))
+                .remove::<RestoreOwner>();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum SnapshotAction {
+    Save,
+    Restore,
+}
+
+pub type SnapshotActionInput = ActionMapInput<SnapshotAction, ()>;
+
+fn setup(mut commands: Commands) {
+    let mut action_map = ActionMap::<SnapshotAction, ()>::default();
+    action_map.push(SnapshotActionInput {
+        action: SnapshotAction::Save,
+        button: KeyCode::F6.into(),
+        ..Default::default()
+    });
+    action_map.push(SnapshotActionInput {
+        action: SnapshotAction::Restore,
+        button: KeyCode::F7.into(),
+        ..Default::default()
+    });
+
+    commands.spawn((
+        Name::new("Snapshot"),
+        Action::<SnapshotAction>::default(),
+        action_map,
+    ));
+}
+
+fn control(
+    actions: Query<&Action<SnapshotAction>>,
+    mut saves: EventWriter<SnapshotSave>,
+    mut restores: EventWriter<SnapshotRestore>,
+) {
+    for action in &actions {
+        if action.on_enter(SnapshotAction::Save) {
+            saves.send(SnapshotSave);
+        }
+        if action.on_enter(SnapshotAction::Restore) {
+            restores.send(SnapshotRestore);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use bevy::prelude::*;
+use rhai::{Dynamic, Map};
+
+pub struct StimulusPlugin;
+
+impl Plugin for StimulusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Stimulus>();
+    }
+}
+
+/// Something happening in the world that NPCs can react to, like a sound,
+/// an explosion or a door opening. Any system can send it as an event.
+#[derive(Debug, Clone)]
+pub struct Stimulus {
+    pub kind: String,
+    pub position: Vec3,
+    pub radius: f32,
+    pub intensity: f32,
+    /// Behavior tree that emitted the stimulus, it won't react to it
+    pub source: Option<Entity>,
+}
+
+impl Stimulus {
+    pub fn new(kind: impl Into<String>, position: Vec3, radius: f32, intensity: f32) -> Self {
+        Self {
+            kind: kind.into(),
+            position,
+            radius,
+            intensity,
+            source: None,
+        }
+    }
+
+    pub fn reaches(&self, position: Vec3) -> bool {
+        self.position.distance(position) <= self.radius
+    }
+
+    pub fn to_map(&self) -> Map {
+        let mut map = Map::new();
+        map.insert("kind".into(), Dynamic::from(self.kind.clone()));
+        map.insert("x".into(), Dynamic::from(self.position.x as f64));
+        map.insert("y".into(), Dynamic::from(self.position.y as f64));
+        map.insert("z".into(), Dynamic::from(self.position.z as f64));
+        map.insert("radius".into(), Dynamic::from(self.radius as f64));
+        map.insert("intensity".into(), Dynamic::from(self.intensity as f64));
+        map
+    }
+}
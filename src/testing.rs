@@ -0,0 +1,193 @@
+//! Harness to run behavior trees in a headless app and check on them
+
+use crate::{deterministic::DeterministicPlugin, headless::HeadlessPlugin, sandbox::SandboxPlugin};
+use bevy::{
+    asset::{HandleId, LoadState},
+    prelude::*,
+};
+use rhai::{Dynamic, Map};
+use simula_behavior::prelude::*;
+use simula_script::ScriptContext;
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+/// Simulated time per frame
+pub const FRAME: Duration = Duration::from_nanos(16_666_667);
+
+/// Longest real time to wait for assets before stepping anyway
+pub const LOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeState {
+    Missing,
+    Idle,
+    Running,
+    Success,
+    Failure,
+}
+
+/// Write an asset, like a subtree or a `.goap.ron`, to a temporary file and return its path
+pub fn temp_asset(file_name: &str, source: &str) -> String {
+    let dir: PathBuf = std::env::temp_dir().join("autonpcs-tests");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(file_name);
+    std::fs::write(&path, source).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+pub struct TestApp {
+    pub app: App,
+    /// Tree assets spawned, frames aren't counted while they load
+    handles: Vec<HandleUntyped>,
+}
+
+impl Default for TestApp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestApp {
+    /// Headless app with every behavior plugin, stepping a fixed `FRAME` each update
+    pub fn new() -> Self {
+        let mut app = App::new();
+        app.add_plugin(DeterministicPlugin {
+            step: FRAME,
+            realtime: false,
+        })
+        .add_plugin(HeadlessPlugin::default())
+        .add_plugin(SandboxPlugin);
+        Self {
+            app,
+            handles: vec![],
+        }
+    }
+
+    /// Spawn a tree running an asset, like "bht/u/spawn_test.bht.ron"
+    pub fn spawn_tree<T: BehaviorFactory>(&mut self, path: impl Into<String>) -> Entity {
+        let path = path.into();
+        let handle: Handle<BehaviorAsset<T>> =
+            self.app.world.resource::<AssetServer>().load(path.as_str());
+        self.handles.push(handle.clone_untyped());
+        self.app
+            .world
+            .spawn((
+                Name::new(format!("BHT: {}", path)),
+                handle,
+                BehaviorTree::<T>::default(),
+                BehaviorTreeReset::<T>::default(),
+            ))
+            .id()
+    }
+
+    /// Spawn a tree from `.bht.ron` source, written to a temporary file
+    pub fn spawn_tree_str<T: BehaviorFactory>(&mut self, name: &str, source: &str) -> Entity {
+        let path = temp_asset(&format!("{}.bht.ron", name), source);
+        self.spawn_tree::<T>(path)
+    }
+
+    pub fn step(&mut self, frames: u32) {
+        for _ in 0..frames {
+            self.wait_for_assets();
+            self.app.update();
+        }
+    }
+
+    /// Update until spawned trees and scenes are loaded, these updates don't count as frames
+    pub fn wait_for_assets(&mut self) {
+        let deadline = Instant::now() + LOAD_TIMEOUT;
+        while self.loading() {
+            if Instant::now() > deadline {
+                warn!("Assets still loading after {:?}", LOAD_TIMEOUT);
+                return;
+            }
+            self.app.update();
+            std::thread::yield_now();
+        }
+    }
+
+    fn loading(&mut self) -> bool {
+        let scenes: Vec<HandleId> = self
+            .app
+            .world
+            .query::<&Handle<Scene>>()
+            .iter(&self.app.world)
+            .map(|handle| handle.id())
+            .collect();
+        let asset_server = self.app.world.resource::<AssetServer>();
+        self.handles
+            .iter()
+            .map(|handle| handle.id())
+            .chain(scenes)
+            .any(|id| asset_server.get_load_state(id) == LoadState::Loading)
+    }
+
+    /// Step until the condition holds, returns the number of frames it took
+    pub fn run_until(
+        &mut self,
+        max_frames: u32,
+        mut condition: impl FnMut(&mut Self) -> bool,
+    ) -> Option<u32> {
+        for frame in 1..=max_frames {
+            self.step(1);
+            if condition(self) {
+                return Some(frame);
+            }
+        }
+        None
+    }
+
+    /// First node of the tree with the name
+    pub fn node(&mut self, tree: Entity, name: &str) -> Option<Entity> {
+        self.app
+            .world
+            .query::<(Entity, &BehaviorNode, &Name)>()
+            .iter(&self.app.world)
+            .find(|(_, node, node_name)| node.tree == Some(tree) && node_name.as_str() == name)
+            .map(|(entity, _, _)| entity)
+    }
+
+    pub fn node_state(&mut self, tree: Entity, name: &str) -> NodeState {
+        let Some(node) = self.node(tree, name) else {
+            return NodeState::Missing;
+        };
+        let node = self.app.world.entity(node);
+        if node.contains::<BehaviorFailure>() {
+            NodeState::Failure
+        } else if node.contains::<BehaviorSuccess>() {
+            NodeState::Success
+        } else if node.contains::<BehaviorRunning>() {
+            NodeState::Running
+        } else {
+            NodeState::Idle
+        }
+    }
+
+    pub fn blackboard(&self, tree: Entity) -> Map {
+        self.app
+            .world
+            .get::<Handle<ScriptContext>>(tree)
+            .and_then(|handle| {
+                self.app
+                    .world
+                    .resource::<Assets<ScriptContext>>()
+                    .get(handle)
+            })
+            .and_then(|context| context.scope.get_value::<Map>("blackboard"))
+            .unwrap_or_default()
+    }
+
+    pub fn blackboard_value(&self, tree: Entity, key: &str) -> Option<Dynamic> {
+        self.blackboard(tree).get(key).cloned()
+    }
+
+    pub fn count<C: Component>(&mut self) -> usize {
+        self.app.world.query::<&C>().iter(&self.app.world).count()
+    }
+
+    pub fn send<E: Event>(&mut self, event: E) {
+        self.app.world.send_event(event);
+    }
+}
@@ -0,0 +1,676 @@
+use crate::{
+    behaviors::{bioma::BiomaBehavior, npc::NPCBehavior},
+    lod::ParkedCursor,
+};
+use bevy::{
+    app::AppExit, asset::AssetPath, core::FrameCount, ecs::system::SystemParam, prelude::*,
+    utils::HashMap,
+};
+use bevy_inspector_egui::{
+    bevy_egui::{EguiContexts, EguiPlugin},
+    egui,
+};
+use rhai::Map;
+use serde::{Deserialize, Serialize};
+use simula_behavior::prelude::*;
+use simula_script::ScriptContext;
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+/// Records behavior execution while a `TraceRecorder` is present
+pub struct TracePlugin;
+
+impl Plugin for TracePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<NodeEffect>()
+            .add_system(record.in_base_set(CoreSet::Last))
+            .add_system(flush.in_base_set(CoreSet::Last).after(record));
+    }
+}
+
+/// Replays a recorded trace in the behavior inspector. The recorded trees are spawned again
+/// but held still, their nodes take the states of the frame scrubbed to.
+pub struct TraceReplayPlugin {
+    pub path: String,
+}
+
+impl Plugin for TraceReplayPlugin {
+    fn build(&self, app: &mut App) {
+        match TraceReplay::open(&self.path) {
+            Ok(replay) => {
+                info!("Trace: {} frames from {}", replay.frames.len(), self.path);
+                if !app.is_plugin_added::<EguiPlugin>() {
+                    app.add_plugin(EguiPlugin);
+                }
+                app.insert_resource(replay)
+                    .add_startup_system(spawn_trees)
+                    .add_systems(
+                        (hold_trees, show_states)
+                            .chain()
+                            .in_base_set(CoreSet::PreUpdate),
+                    )
+                    .add_system(replay_ui);
+            }
+            Err(err) => error!("Trace: cannot open {}: {}", self.path, err),
+        }
+    }
+}
+
+/// Side effect of a behavior node on the world, sent so it shows up in traces
+#[derive(Debug, Clone)]
+pub enum NodeEffect {
+    Spawn {
+        node: Entity,
+        scene: Entity,
+        name: String,
+        asset: String,
+    },
+    Anim {
+        node: Entity,
+        target: String,
+        asset: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TraceState {
+    Started,
+    Running,
+    Success,
+    Failure,
+    /// Stopped running without a result
+    Aborted,
+}
+
+/// Entities are written as their bits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TraceEvent {
+    /// First time a tree is seen
+    Tree {
+        tree: u64,
+        name: String,
+        asset: String,
+        bioma: bool,
+    },
+    /// First time a node is seen
+    Node {
+        node: u64,
+        tree: Option<u64>,
+        parent: Option<u64>,
+        /// Place among the children of its parent
+        #[serde(default)]
+        index: usize,
+        name: String,
+    },
+    State {
+        node: u64,
+        state: TraceState,
+    },
+    Blackboard {
+        tree: u64,
+        key: String,
+        value: String,
+    },
+    Spawn {
+        node: u64,
+        scene: u64,
+        name: String,
+        asset: String,
+    },
+    Anim {
+        node: u64,
+        target: String,
+        asset: String,
+    },
+}
+
+/// One line of a trace file, only written for frames with events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceFrame {
+    pub frame: u32,
+    pub time: f64,
+    pub events: Vec<TraceEvent>,
+}
+
+#[derive(Resource)]
+pub struct TraceRecorder {
+    writer: BufWriter<File>,
+    /// Last seen blackboard of each tree, to find what changed
+    blackboards: HashMap<Entity, HashMap<String, String>>,
+}
+
+impl TraceRecorder {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            blackboards: HashMap::default(),
+        })
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn record(
+    recorder: Option<ResMut<TraceRecorder>>,
+    frame: Res<FrameCount>,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    new_trees: Query<
+        (
+            Entity,
+            Option<&Name>,
+            Option<&Handle<BehaviorAsset<BiomaBehavior>>>,
+            Option<&Handle<BehaviorAsset<NPCBehavior>>>,
+        ),
+        Or<(
+            Added<BehaviorTree<BiomaBehavior>>,
+            Added<BehaviorTree<NPCBehavior>>,
+        )>,
+    >,
+    nodes: Query<(Entity, &BehaviorNode, Option<&Name>, Option<&Parent>), Added<BehaviorNode>>,
+    children: Query<&BehaviorChildren>,
+    started: Query<Entity, Added<BehaviorStarted>>,
+    running: Query<Entity, Added<BehaviorRunning>>,
+    successes: Query<Entity, Added<BehaviorSuccess>>,
+    failures: Query<Entity, Added<BehaviorFailure>>,
+    results: Query<(Option<&BehaviorSuccess>, Option<&BehaviorFailure>), With<BehaviorNode>>,
+    mut stopped: RemovedComponents<BehaviorRunning>,
+    mut effects: EventReader<NodeEffect>,
+    trees: Query<(Entity, &Handle<ScriptContext>)>,
+    contexts: Res<Assets<ScriptContext>>,
+) {
+    let Some(mut recorder) = recorder else {
+        effects.clear();
+        return;
+    };
+    let mut events = vec![];
+
+    for (tree, name, bioma, npc) in &new_trees {
+        let path = match (bioma, npc) {
+            (Some(handle), _) => asset_server.get_handle_path(handle),
+            (_, Some(handle)) => asset_server.get_handle_path(handle),
+            _ => None,
+        };
+        let Some(path) = path else {
+            continue;
+        };
+        events.push(TraceEvent::Tree {
+            tree: tree.to_bits(),
+            name: name.map(|name| name.to_string()).unwrap_or_default(),
+            asset: asset_path(path),
+            bioma: bioma.is_some(),
+        });
+    }
+
+    for (entity, node, name, parent) in &nodes {
+        let index = parent
+            .and_then(|parent| children.get(parent.get()).ok())
+            .and_then(|children| children.iter().position(|child| *child == entity))
+            .unwrap_or_default();
+        events.push(TraceEvent::Node {
+            node: entity.to_bits(),
+            tree: node.tree.map(|tree| tree.to_bits()),
+            parent: parent.map(|parent| parent.get().to_bits()),
+            index,
+            name: name.map(|name| name.to_string()).unwrap_or_default(),
+        });
+    }
+
+    let states = started
+        .iter()
+        .map(|entity| (entity, TraceState::Started))
+        .chain(running.iter().map(|entity| (entity, TraceState::Running)))
+        .chain(successes.iter().map(|entity| (entity, TraceState::Success)))
+        .chain(failures.iter().map(|entity| (entity, TraceState::Failure)));
+    for (entity, state) in states {
+        events.push(TraceEvent::State {
+            node: entity.to_bits(),
+            state,
+        });
+    }
+    // nodes that stopped running without a result, and are still around
+    for entity in &mut stopped {
+        if let Ok((None, None)) = results.get(entity) {
+            events.push(TraceEvent::State {
+                node: entity.to_bits(),
+                state: TraceState::Aborted,
+            });
+        }
+    }
+
+    // blackboards are compared, so writes from scripts are recorded too
+    for (tree, handle) in &trees {
+        let Some(blackboard) = contexts
+            .get(handle)
+            .and_then(|context| context.scope.get_value::<Map>("blackboard"))
+        else {
+            continue;
+        };
+        let seen = recorder.blackboards.entry(tree).or_default();
+        for (key, value) in blackboard {
+            let value = value.to_string();
+            if seen.get(key.as_str()) != Some(&value) {
+                seen.insert(key.to_string(), value.clone());
+                events.push(TraceEvent::Blackboard {
+                    tree: tree.to_bits(),
+                    key: key.to_string(),
+                    value,
+                });
+            }
+        }
+    }
+
+    for effect in effects.iter() {
+        events.push(match effect.clone() {
+            NodeEffect::Spawn {
+                node,
+                scene,
+                name,
+                asset,
+            } => TraceEvent::Spawn {
+                node: node.to_bits(),
+                scene: scene.to_bits(),
+                name,
+                asset,
+            },
+            NodeEffect::Anim {
+                node,
+                target,
+                asset,
+            } => TraceEvent::Anim {
+                node: node.to_bits(),
+                target,
+                asset,
+            },
+        });
+    }
+
+    if events.is_empty() {
+        return;
+    }
+    let frame = TraceFrame {
+        frame: frame.0,
+        time: time.elapsed_seconds_f64(),
+        events,
+    };
+    let written = ron::to_string(&frame)
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+        .and_then(|line| writeln!(recorder.writer, "{}", line));
+    if let Err(err) = written {
+        error!("Trace: cannot write: {}", err);
+    }
+}
+
+fn flush(recorder: Option<ResMut<TraceRecorder>>, mut exits: EventReader<AppExit>) {
+    if let Some(mut recorder) = recorder {
+        if exits.iter().next().is_some() {
+            if let Err(err) = recorder.writer.flush() {
+                error!("Trace: cannot flush: {}", err);
+            }
+        }
+    }
+}
+
+/// Nodes of behavior trees, addressed by their path from the root
+#[derive(SystemParam)]
+pub struct TreeNodes<'w, 's> {
+    nodes: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static BehaviorNode,
+            &'static Parent,
+            Option<&'static BehaviorChildren>,
+        ),
+    >,
+}
+
+impl<'w, 's> TreeNodes<'w, 's> {
+    pub fn root(&self, tree: Entity) -> Option<Entity> {
+        self.nodes
+            .iter()
+            .find(|(_, node, parent, _)| node.tree == Some(tree) && parent.get() == tree)
+            .map(|(entity, _, _, _)| entity)
+    }
+
+    pub fn get(&self, tree: Entity, path: &[usize]) -> Option<Entity> {
+        let mut node = self.root(tree)?;
+        for index in path {
+            let (_, _, _, children) = self.nodes.get(node).ok()?;
+            node = *children?.get(*index)?;
+        }
+        Some(node)
+    }
+
+    /// Every node of the tree with its path, parents first
+    pub fn walk(&self, tree: Entity) -> Vec<(Vec<usize>, Entity)> {
+        let mut nodes = vec![];
+        let Some(root) = self.root(tree) else {
+            return nodes;
+        };
+        let mut stack = vec![(vec![], root)];
+        while let Some((path, node)) = stack.pop() {
+            if let Ok((_, _, _, Some(children))) = self.nodes.get(node) {
+                for (index, child) in children.iter().enumerate().rev() {
+                    let mut child_path = path.clone();
+                    child_path.push(index);
+                    stack.push((child_path, *child));
+                }
+            }
+            nodes.push((path, node));
+        }
+        nodes
+    }
+}
+
+/// Asset path with its label, as `AssetServer::load` takes it
+pub fn asset_path(path: AssetPath) -> String {
+    match path.label() {
+        Some(label) => format!("{}#{}", path.path().display(), label),
+        None => path.path().display().to_string(),
+    }
+}
+
+struct TraceNode {
+    tree: Option<u64>,
+    parent: Option<u64>,
+    index: usize,
+    state: Option<TraceState>,
+}
+
+#[derive(Resource)]
+pub struct TraceReplay {
+    pub frames: Vec<TraceFrame>,
+    /// Index of the frame shown
+    pub cursor: usize,
+    pub playing: bool,
+    /// Index of the frame the state below was reconstructed for
+    shown: Option<usize>,
+    nodes: HashMap<u64, TraceNode>,
+    blackboards: HashMap<u64, Vec<(String, String)>>,
+    names: HashMap<u64, String>,
+    /// Spawned trees showing the replay, by their id in the trace
+    trees: HashMap<u64, Entity>,
+}
+
+impl TraceReplay {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut frames = vec![];
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let frame = ron::from_str::<TraceFrame>(&line)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            frames.push(frame);
+        }
+        Ok(Self {
+            frames,
+            cursor: 0,
+            playing: false,
+            shown: None,
+            nodes: HashMap::default(),
+            blackboards: HashMap::default(),
+            names: HashMap::default(),
+            trees: HashMap::default(),
+        })
+    }
+
+    // bring nodes and blackboards to the cursor, going on from the last frame shown
+    // unless scrubbing back
+    fn seek(&mut self) {
+        if self.frames.is_empty() || self.shown == Some(self.cursor) {
+            return;
+        }
+        let from = match self.shown {
+            Some(shown) if shown < self.cursor => shown + 1,
+            _ => {
+                self.nodes.clear();
+                self.blackboards.clear();
+                self.names.clear();
+                0
+            }
+        };
+        for frame in &self.frames[from..=self.cursor] {
+            for event in &frame.events {
+                match event {
+                    TraceEvent::Tree { tree, name, .. } => {
+                        self.names.insert(*tree, name.clone());
+                    }
+                    TraceEvent::Node {
+                        node,
+                        tree,
+                        parent,
+                        index,
+                        ..
+                    } => {
+                        self.nodes.insert(
+                            *node,
+                            TraceNode {
+                                tree: *tree,
+                                parent: *parent,
+                                index: *index,
+                                state: None,
+                            },
+                        );
+                    }
+                    TraceEvent::State { node, state } => {
+                        if let Some(node) = self.nodes.get_mut(node) {
+                            node.state = Some(*state);
+                        }
+                    }
+                    TraceEvent::Blackboard { tree, key, value } => {
+                        let entries = self.blackboards.entry(*tree).or_default();
+                        match entries.iter_mut().find(|(k, _)| k == key) {
+                            Some(entry) => entry.1 = value.clone(),
+                            None => entries.push((key.clone(), value.clone())),
+                        }
+                    }
+                    TraceEvent::Spawn { .. } | TraceEvent::Anim { .. } => {}
+                }
+            }
+        }
+        self.shown = Some(self.cursor);
+    }
+
+    // tree and child indices from the root, as `TreeNodes` addresses nodes
+    fn path(&self, mut id: u64) -> Option<(u64, Vec<usize>)> {
+        let mut path = vec![];
+        loop {
+            let node = self.nodes.get(&id)?;
+            // root nodes have the tree as parent
+            match node.parent.filter(|parent| self.nodes.contains_key(parent)) {
+                Some(parent) => {
+                    path.push(node.index);
+                    id = parent;
+                }
+                None => {
+                    path.reverse();
+                    return Some((node.tree?, path));
+                }
+            }
+        }
+    }
+}
+
+/// Tree spawned to show a replay, it doesn't run
+#[derive(Component)]
+pub struct ReplayTree;
+
+fn spawn_tree<T: BehaviorFactory>(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    name: &str,
+    asset: &str,
+) -> Entity {
+    let handle: Handle<BehaviorAsset<T>> = asset_server.load(asset);
+    commands
+        .spawn((
+            Name::new(format!("Replay: {}", name)),
+            handle,
+            BehaviorTree::<T>::default(),
+            BehaviorTreeReset::<T>::default(),
+            ReplayTree,
+        ))
+        .id()
+}
+
+fn spawn_trees(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut replay: ResMut<TraceReplay>,
+) {
+    let replay = replay.as_mut();
+    for frame in &replay.frames {
+        for event in &frame.events {
+            let TraceEvent::Tree {
+                tree,
+                name,
+                asset,
+                bioma,
+            } = event
+            else {
+                continue;
+            };
+            let entity = if *bioma {
+                spawn_tree::<BiomaBehavior>(&mut commands, &asset_server, name, asset)
+            } else {
+                spawn_tree::<NPCBehavior>(&mut commands, &asset_server, name, asset)
+            };
+            replay.trees.insert(*tree, entity);
+        }
+    }
+}
+
+// nodes of replayed trees never get to run
+#[allow(clippy::type_complexity)]
+fn hold_trees(
+    mut commands: Commands,
+    trees: Query<(), With<ReplayTree>>,
+    nodes: Query<
+        (Entity, &BehaviorNode),
+        Or<(
+            With<BehaviorCursor>,
+            With<ParkedCursor>,
+            With<BehaviorStarted>,
+        )>,
+    >,
+) {
+    for (entity, node) in &nodes {
+        if node.tree.map_or(false, |tree| trees.contains(tree)) {
+            commands
+                .entity(entity)
+                .remove::<(BehaviorCursor, ParkedCursor, BehaviorStarted)>();
+        }
+    }
+}
+
+// states of the frame shown go on the nodes, so the inspector highlights them
+fn show_states(
+    mut commands: Commands,
+    replay: Res<TraceReplay>,
+    mut applied: Local<Option<usize>>,
+    added: Query<(), Added<BehaviorNode>>,
+    tree_nodes: TreeNodes,
+) {
+    // again when the frame changes, or nodes of trees still loading show up
+    if *applied == replay.shown && added.is_empty() {
+        return;
+    }
+    *applied = replay.shown;
+    for (id, node) in &replay.nodes {
+        let Some(entity) = replay.path(*id).and_then(|(tree, path)| {
+            let tree = replay.trees.get(&tree)?;
+            tree_nodes.get(*tree, &path)
+        }) else {
+            continue;
+        };
+        let mut entity = commands.entity(entity);
+        match node.state {
+            Some(TraceState::Started) | Some(TraceState::Running) => {
+                entity
+                    .remove::<(BehaviorSuccess, BehaviorFailure)>()
+                    .insert(BehaviorRunning);
+            }
+            Some(TraceState::Success) => {
+                entity
+                    .remove::<(BehaviorRunning, BehaviorFailure)>()
+                    .insert(BehaviorSuccess);
+            }
+            Some(TraceState::Failure) => {
+                entity
+                    .remove::<(BehaviorRunning, BehaviorSuccess)>()
+                    .insert(BehaviorFailure);
+            }
+            Some(TraceState::Aborted) | None => {
+                entity.remove::<(BehaviorRunning, BehaviorSuccess, BehaviorFailure)>();
+            }
+        }
+    }
+}
+
+// timeline under the inspector, to scrub through the trace
+fn replay_ui(mut contexts: EguiContexts, mut replay: ResMut<TraceReplay>) {
+    if replay.frames.is_empty() {
+        return;
+    }
+    let last = replay.frames.len() - 1;
+    if replay.playing {
+        if replay.cursor < last {
+            replay.cursor += 1;
+        } else {
+            replay.playing = false;
+        }
+    }
+    let replay = replay.as_mut();
+
+    egui::TopBottomPanel::bottom("trace_replay")
+        .resizable(true)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("⏮").clicked() {
+                    replay.cursor = replay.cursor.saturating_sub(1);
+                }
+                let play = if replay.playing { "⏸" } else { "▶" };
+                if ui.button(play).clicked() {
+                    replay.playing = !replay.playing;
+                }
+                if ui.button("⏭").clicked() {
+                    replay.cursor = (replay.cursor + 1).min(last);
+                }
+                ui.add(egui::Slider::new(&mut replay.cursor, 0..=last));
+                let frame = &replay.frames[replay.cursor];
+                ui.label(format!("frame {} at {:.3}s", frame.frame, frame.time));
+            });
+            replay.seek();
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                // trees by name, so they don't move around while scrubbing
+                let mut trees: Vec<_> = replay
+                    .blackboards
+                    .iter()
+                    .map(|(tree, entries)| {
+                        let name = replay.names.get(tree).map_or("", |name| name.as_str());
+                        (name, tree, entries)
+                    })
+                    .collect();
+                trees.sort_by_key(|(name, tree, _)| (*name, **tree));
+                for (name, tree, entries) in trees {
+                    ui.label(format!("{} blackboard", name));
+                    ui.indent(("blackboard", *tree), |ui| {
+                        for (key, value) in entries {
+                            ui.label(egui::RichText::new(format!("{}: {}", key, value)).small());
+                        }
+                    });
+                }
+                ui.separator();
+                for event in &replay.frames[replay.cursor].events {
+                    ui.label(egui::RichText::new(format!("{:?}", event)).small());
+                }
+            });
+        });
+}